@@ -0,0 +1,486 @@
+//! Encadeamento dos registros do audit log
+//!
+//! Cada registro guarda `seq`, o `hash` do registro anterior (`prev_hash`) e o
+//! próprio `hash`: SHA-256 do registro em JSON canônico (chaves ordenadas, sem
+//! `hash` e `hmac`). Com uma chave configurada, `hmac` autentica o mesmo
+//! conteúdo. Editar, remover, inserir ou reordenar linhas quebra a cadeia.
+//!
+//! O último `seq`/`hash` também é gravado em `<log>.head` (com HMAC, se houver
+//! chave); um log que termina antes dele foi truncado. A cadeia atravessa os
+//! arquivos rotacionados (ver [`super::rotate`]).
+
+use super::{rotate, Event};
+use anyhow::{Context, Result};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// `prev_hash` do primeiro registro da cadeia
+pub const GENESIS: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// Tamanho mínimo da chave do HMAC
+const MIN_KEY_BYTES: usize = 16;
+
+/// Último elo da cadeia, gravado no arquivo `.head`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Head {
+    pub seq: u64,
+    pub hash: String,
+}
+
+/// Conteúdo do arquivo `.head`: o último elo e, com chave, o seu HMAC
+#[derive(Serialize, Deserialize)]
+struct HeadRecord {
+    #[serde(flatten)]
+    head: Head,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hmac: Option<String>,
+}
+
+/// Caminho do arquivo `.head` de um log
+pub fn head_path(log_path: &Path) -> PathBuf {
+    let mut path = log_path.as_os_str().to_owned();
+    path.push(".head");
+    PathBuf::from(path)
+}
+
+/// Lê a chave do HMAC (espaços no fim do arquivo são ignorados)
+pub fn read_key(path: &Path) -> Result<Vec<u8>> {
+    let key = fs::read(path)
+        .with_context(|| format!("Failed to read audit HMAC key {}", path.display()))?;
+    let len = key.trim_ascii_end().len();
+    if len < MIN_KEY_BYTES {
+        anyhow::bail!(
+            "Audit HMAC key {} must have at least {} bytes",
+            path.display(),
+            MIN_KEY_BYTES
+        );
+    }
+    Ok(key[..len].to_vec())
+}
+
+/// JSON com as chaves ordenadas, independente da ordem de serialização
+fn canonical(value: &Value) -> String {
+    match value {
+        Value::Object(map) => {
+            let fields: BTreeMap<&String, String> =
+                map.iter().map(|(k, v)| (k, canonical(v))).collect();
+            let fields: Vec<String> = fields
+                .into_iter()
+                .map(|(k, v)| format!("{}:{}", Value::String(k.clone()), v))
+                .collect();
+            format!("{{{}}}", fields.join(","))
+        }
+        Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(canonical).collect();
+            format!("[{}]", items.join(","))
+        }
+        other => other.to_string(),
+    }
+}
+
+/// Conteúdo protegido de um registro: tudo menos `hash` e `hmac`
+fn content(record: &Value) -> String {
+    let mut record = record.clone();
+    if let Value::Object(map) = &mut record {
+        map.remove("hash");
+        map.remove("hmac");
+    }
+    canonical(&record)
+}
+
+fn mac(key: &[u8]) -> HmacSha256 {
+    HmacSha256::new_from_slice(key).expect("HMAC accepts keys of any size")
+}
+
+/// HMAC do `.head`; o prefixo o distingue do HMAC de um registro
+fn head_mac(key: &[u8], head: &Head) -> Result<HmacSha256> {
+    let mut mac = mac(key);
+    mac.update(b"head:");
+    mac.update(canonical(&serde_json::to_value(head)?).as_bytes());
+    Ok(mac)
+}
+
+/// Preenche `seq`, `prev_hash`, `hash` e `hmac` e retorna a linha JSON
+pub fn seal(mut event: Event, prev: Option<&Head>, key: Option<&[u8]>) -> Result<(String, Head)> {
+    event.seq = prev.map_or(1, |p| p.seq + 1);
+    event.prev_hash = prev.map_or(GENESIS.to_string(), |p| p.hash.clone());
+    event.hash = String::new();
+    event.hmac = None;
+
+    let content = content(&serde_json::to_value(&event)?);
+    event.hash = hex::encode(Sha256::digest(content.as_bytes()));
+    event.hmac = key.map(|key| {
+        let mut mac = mac(key);
+        mac.update(content.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    });
+
+    let head = Head {
+        seq: event.seq,
+        hash: event.hash.clone(),
+    };
+    Ok((serde_json::to_string(&event)?, head))
+}
+
+/// Último registro encadeado do arquivo (lido de trás para frente)
+pub fn last_head(file: &mut File) -> Result<Option<Head>> {
+    const CHUNK: u64 = 8192;
+    let mut end = file.metadata()?.len();
+    let mut tail: Vec<u8> = Vec::new();
+    loop {
+        let trimmed = tail.trim_ascii_end();
+        let line = match trimmed.iter().rposition(|&b| b == b'\n') {
+            Some(pos) => &trimmed[pos + 1..],
+            None if end == 0 => trimmed,
+            None => {
+                let start = end.saturating_sub(CHUNK);
+                let mut chunk = vec![0; (end - start) as usize];
+                file.seek(SeekFrom::Start(start))?;
+                file.read_exact(&mut chunk)?;
+                chunk.extend_from_slice(&tail);
+                tail = chunk;
+                end = start;
+                continue;
+            }
+        };
+        // Uma linha fora do formato (ex: log antigo em texto) inicia uma nova cadeia
+        return Ok(serde_json::from_slice::<Event>(line)
+            .ok()
+            .filter(|e| !e.hash.is_empty())
+            .map(|e| Head {
+                seq: e.seq,
+                hash: e.hash,
+            }));
+    }
+}
+
+/// Último elo gravado no `.head` (usado quando o arquivo ativo acabou de ser rotacionado)
+pub fn read_head(log_path: &Path) -> Option<Head> {
+    let head = fs::read_to_string(head_path(log_path)).ok()?;
+    serde_json::from_str::<HeadRecord>(&head)
+        .ok()
+        .map(|record| record.head)
+}
+
+/// Grava o `.head` de forma atômica, autenticado pela chave (se houver)
+pub fn write_head(log_path: &Path, head: &Head, key: Option<&[u8]>) -> Result<()> {
+    let path = head_path(log_path);
+    let tmp = path.with_extension("head.tmp");
+    let hmac = match key {
+        Some(key) => Some(hex::encode(head_mac(key, head)?.finalize().into_bytes())),
+        None => None,
+    };
+    let record = HeadRecord {
+        head: head.clone(),
+        hmac,
+    };
+    fs::write(&tmp, serde_json::to_string(&record)?).context("Failed to write audit head")?;
+    fs::rename(&tmp, &path).context("Failed to write audit head")
+}
+
+/// Resultado de `verify`
+#[derive(Debug, Default)]
+pub struct Verification {
+    /// Registros encadeados verificados
+    pub records: u64,
+    /// `seq` do primeiro registro encontrado (maior que 1 se arquivos antigos
+    /// foram removidos pela retenção)
+    pub first_seq: u64,
+    /// Linhas anteriores à cadeia (formato antigo), não verificáveis
+    pub legacy: u64,
+    /// Se os HMACs foram conferidos
+    pub hmac_checked: bool,
+    /// Sinais de adulteração, com a linha onde foram encontrados
+    pub problems: Vec<String>,
+}
+
+/// Confere a cadeia de um log e dos seus arquivos rotacionados, os HMACs (se
+/// houver chave) e o `.head`
+pub fn verify(log_path: &Path, key: Option<&[u8]>) -> Result<Verification> {
+    let files = rotate::files(log_path)?;
+    if files.is_empty() {
+        anyhow::bail!("Failed to read audit log {}: not found", log_path.display());
+    }
+    let mut report = Verification {
+        hmac_checked: key.is_some(),
+        ..Default::default()
+    };
+    let mut prev: Option<Head> = None;
+
+    for file in &files {
+        let contents = rotate::read(file)?;
+        let name = file.file_name().unwrap_or_default().to_string_lossy();
+        for (idx, line) in contents.lines().enumerate() {
+            verify_line(
+                &mut report,
+                &mut prev,
+                &format!("{}:{}", name, idx + 1),
+                line,
+                key,
+            );
+        }
+    }
+
+    // Sem o `.head`, só um log de uma cadeia que nunca começou (sem chave e
+    // sem arquivos rotacionados) é aceito
+    let head_file = head_path(log_path);
+    match fs::read_to_string(&head_file) {
+        Ok(head) => match serde_json::from_str::<HeadRecord>(&head) {
+            Ok(record) => verify_head(&mut report, &head_file, &record, prev.as_ref(), key)?,
+            Err(_) => report
+                .problems
+                .push(format!("{} is corrupted", head_file.display())),
+        },
+        Err(_) if prev.is_some() || key.is_some() || files.len() > 1 => report
+            .problems
+            .push(format!("{} is missing", head_file.display())),
+        Err(_) => {}
+    }
+
+    Ok(report)
+}
+
+/// Confere o `.head` contra o último registro e, com a chave, o seu HMAC
+fn verify_head(
+    report: &mut Verification,
+    head_file: &Path,
+    record: &HeadRecord,
+    last: Option<&Head>,
+    key: Option<&[u8]>,
+) -> Result<()> {
+    if let Some(key) = key {
+        let mac = head_mac(key, &record.head)?;
+        let valid = record
+            .hmac
+            .as_deref()
+            .and_then(|tag| hex::decode(tag).ok())
+            .is_some_and(|tag| mac.verify_slice(&tag).is_ok());
+        if !valid {
+            report
+                .problems
+                .push(format!("{}: missing or invalid HMAC", head_file.display()));
+        }
+    }
+    if last != Some(&record.head) {
+        report.problems.push(format!(
+            "log ends at seq {} but {} records seq {} (records were removed from the end)",
+            last.map_or(0, |p| p.seq),
+            head_file.display(),
+            record.head.seq
+        ));
+    }
+    Ok(())
+}
+
+/// Confere uma linha contra o elo anterior
+fn verify_line(
+    report: &mut Verification,
+    prev: &mut Option<Head>,
+    n: &str,
+    line: &str,
+    key: Option<&[u8]>,
+) {
+    if line.trim().is_empty() {
+        return;
+    }
+    let record: Option<Value> = serde_json::from_str(line).ok();
+    let Some(record) = record.filter(|r| r.get("hash").is_some()) else {
+        if prev.is_some() {
+            report
+                .problems
+                .push(format!("{}: record outside the hash chain", n));
+        } else {
+            report.legacy += 1;
+        }
+        return;
+    };
+    report.records += 1;
+
+    let str_field = |name: &str| record.get(name).and_then(Value::as_str).unwrap_or("");
+    let seq = record.get("seq").and_then(Value::as_u64).unwrap_or(0);
+    let hash = str_field("hash");
+    let content = content(&record);
+
+    if hex::encode(Sha256::digest(content.as_bytes())) != hash {
+        report
+            .problems
+            .push(format!("{}: hash mismatch (record was modified)", n));
+    }
+
+    // O primeiro registro só começa em `GENESIS` se nenhum arquivo foi removido
+    let (expected_seq, expected_prev) = match prev.as_ref() {
+        Some(p) => (p.seq + 1, Some(p.hash.as_str())),
+        None => {
+            report.first_seq = seq;
+            (seq.max(1), (seq == 1).then_some(GENESIS))
+        }
+    };
+    if seq != expected_seq {
+        report.problems.push(format!(
+            "{}: seq {} where {} was expected (records removed, inserted or reordered)",
+            n, seq, expected_seq
+        ));
+    } else if expected_prev.is_some_and(|p| str_field("prev_hash") != p) {
+        report.problems.push(format!(
+            "{}: prev_hash does not match the previous record",
+            n
+        ));
+    }
+
+    // Com a chave, todo registro precisa de HMAC: sem ela qualquer um
+    // consegue recalcular a cadeia inteira
+    if let Some(key) = key {
+        match record.get("hmac").and_then(Value::as_str) {
+            Some(tag) => {
+                let mut mac = mac(key);
+                mac.update(content.as_bytes());
+                let valid = hex::decode(tag).is_ok_and(|tag| mac.verify_slice(&tag).is_ok());
+                if !valid {
+                    report.problems.push(format!("{}: invalid HMAC", n));
+                }
+            }
+            None => report.problems.push(format!("{}: missing HMAC", n)),
+        }
+    }
+
+    *prev = Some(Head {
+        seq,
+        hash: hash.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn event(command: &str) -> Event {
+        Event {
+            timestamp: "2026-01-01T12:00:00+00:00".to_string(),
+            status: "ALLOWED".to_string(),
+            command: command.to_string(),
+            ..Default::default()
+        }
+    }
+
+    /// Grava um log com os comandos dados e retorna as linhas
+    fn write_log(path: &Path, commands: &[&str], key: Option<&[u8]>) -> Vec<String> {
+        let mut file = File::create(path).unwrap();
+        let mut prev = None;
+        let mut lines = Vec::new();
+        for command in commands {
+            let (line, head) = seal(event(command), prev.as_ref(), key).unwrap();
+            writeln!(file, "{}", line).unwrap();
+            write_head(path, &head, key).unwrap();
+            lines.push(line);
+            prev = Some(head);
+        }
+        lines
+    }
+
+    fn problems(path: &Path, lines: &[String], key: Option<&[u8]>) -> Vec<String> {
+        fs::write(path, lines.join("\n") + "\n").unwrap();
+        verify(path, key).unwrap().problems
+    }
+
+    #[test]
+    fn test_chain() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-chain-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log");
+        let key = b"0123456789abcdef0123".as_slice();
+
+        let lines = write_log(&path, &["ls", "id", "df"], Some(key));
+        let report = verify(&path, Some(key)).unwrap();
+        assert_eq!(report.records, 3);
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+        let mut file = File::open(&path).unwrap();
+        assert_eq!(last_head(&mut file).unwrap().unwrap().seq, 3);
+
+        // Edição de um campo
+        let mut edited = lines.clone();
+        edited[1] = edited[1].replace("\"id\"", "\"whoami\"");
+        assert!(problems(&path, &edited, Some(key))[0].contains("hash mismatch"));
+
+        // Log reescrito sem a chave: a cadeia fecha, mas o HMAC não
+        write_log(&path, &["ls", "whoami", "df"], None);
+        let report = verify(&path, None).unwrap();
+        assert!(report.problems.is_empty());
+        let report = verify(&path, Some(key)).unwrap();
+        assert_eq!(report.problems.len(), 4);
+        assert!(report.problems[0].contains("audit.log:1: missing HMAC"));
+        assert!(report.problems[3].contains(".head: missing or invalid HMAC"));
+
+        // Remoção, reordenação e truncamento
+        write_log(&path, &["ls", "id", "df"], Some(key));
+        assert!(
+            problems(&path, &[lines[0].clone(), lines[2].clone()], Some(key))[0]
+                .contains("seq 3 where 2")
+        );
+        assert!(!problems(
+            &path,
+            &[lines[1].clone(), lines[0].clone(), lines[2].clone()],
+            None
+        )
+        .is_empty());
+        assert!(problems(&path, &lines[..2], Some(key))[0].contains("removed from the end"));
+
+        // Truncamento com o `.head` regravado sem a chave
+        let head = Head {
+            seq: 2,
+            hash: serde_json::from_str::<Event>(&lines[1]).unwrap().hash,
+        };
+        write_head(&path, &head, None).unwrap();
+        assert!(problems(&path, &lines[..2], Some(key))[0].contains("missing or invalid HMAC"));
+        write_head(&path, &head, Some(key)).unwrap();
+        assert!(problems(&path, &lines[..2], Some(key)).is_empty());
+
+        // Chave errada
+        assert!(
+            problems(&path, &lines, Some(b"another key with 16+ bytes"))[0]
+                .contains("invalid HMAC")
+        );
+
+        // Linhas do formato antigo antes da cadeia
+        let mut legacy = vec!["[2026-01-01 12:00:00] [ALLOWED] Command: \"ls\"".to_string()];
+        legacy.extend(lines.clone());
+        fs::write(&path, legacy.join("\n")).unwrap();
+        write_log(&path, &["ls", "id", "df"], Some(key));
+        fs::write(&path, legacy.join("\n")).unwrap();
+        let report = verify(&path, Some(key)).unwrap();
+        assert_eq!((report.legacy, report.records), (1, 3));
+        assert!(report.problems.is_empty());
+
+        // Só linhas antigas e o `.head` removido: com a chave, falta o `.head`
+        fs::remove_file(head_path(&path)).unwrap();
+        assert!(problems(&path, &legacy[..1], None).is_empty());
+        assert!(problems(&path, &legacy[..1], Some(key))[0].contains("is missing"));
+
+        // A cadeia continua no arquivo novo depois da rotação
+        write_log(&path, &["ls", "id"], Some(key));
+        rotate::rotate(&path, &Default::default()).unwrap();
+        let prev = read_head(&path).unwrap();
+        let (line, head) = seal(event("df"), Some(&prev), Some(key)).unwrap();
+        fs::write(&path, line + "\n").unwrap();
+        write_head(&path, &head, Some(key)).unwrap();
+        let report = verify(&path, Some(key)).unwrap();
+        assert_eq!((report.first_seq, report.records), (1, 3));
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+
+        // Arquivos antigos removidos pela retenção não são adulteração
+        fs::remove_file(&rotate::rotated_files(&path).unwrap()[0]).unwrap();
+        let report = verify(&path, Some(key)).unwrap();
+        assert_eq!((report.first_seq, report.records), (3, 1));
+        assert!(report.problems.is_empty(), "{:?}", report.problems);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,236 @@
+//! Audit log em JSON Lines
+//!
+//! Cada linha é um objeto JSON independente (ver [`Event`]), pronto para
+//! `jq` ou para ser enviado a um SIEM. A execução de um comando gera uma
+//! entrada `ALLOWED` e uma de resultado (`SUCCESS`, `TIMEOUT`, `CANCELLED` ou
+//! `ERROR`) com o mesmo `execution_id`. Comandos que exigem aprovação do
+//! usuário têm antes uma entrada `APPROVED` ou `DENIED` com o mesmo id.
+//!
+//! Os registros formam uma cadeia de hashes (ver [`chain`]), conferida por
+//! `linux-mcp audit verify`. O arquivo é rotacionado conforme `[audit]` (ver
+//! [`rotate`]) e cada registro também vai para os `[[audit.sinks]]` (ver
+//! [`sink`]).
+
+use crate::config::{AuditSink, Config};
+use crate::redact::Redactor;
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub mod chain;
+pub mod query;
+pub mod rotate;
+pub mod sink;
+
+/// Cliente MCP que originou o evento (`clientInfo` do `initialize`)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Client {
+    pub name: String,
+    pub version: String,
+}
+
+/// Entrada do audit log
+///
+/// Campos sem valor são omitidos da linha.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Event {
+    /// Data e hora local em RFC 3339 (preenchido por `log`)
+    pub timestamp: String,
+    /// ALLOWED, BLOCKED, APPROVED, DENIED, SUCCESS, TIMEOUT, CANCELLED, ERROR,
+    /// CONFIG_RELOADED...
+    pub status: String,
+    /// Correlaciona a entrada ALLOWED com a de resultado
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_id: Option<String>,
+    /// Tool MCP que pediu a execução
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    pub command: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client: Option<Client>,
+    /// Conexão MCP que originou o evento (ver [`new_session_id`])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    /// Credencial que autenticou a conexão (ex: `token:ci`); ausente no stdio
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub principal: Option<String>,
+    /// Perfil de `[profiles]` aplicado à conexão
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+    /// Usuário do servidor (preenchido por `log`)
+    pub uid: u32,
+    /// `none` ou `pkexec`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<String>,
+    /// Regra da política que permitiu ou bloqueou o comando
+    /// (ex: `allowed_commands`, `commands.find`, `denied_paths`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stdout_bytes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stderr_bytes: Option<u64>,
+    /// Bytes lidos mas não guardados por exceder `max_captured_bytes`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dropped_bytes: Option<u64>,
+    /// Motivo do bloqueio ou do erro, e outros detalhes em texto livre
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<String>,
+    /// Posição na cadeia de hashes (preenchido por `log`)
+    pub seq: u64,
+    /// `hash` do registro anterior
+    pub prev_hash: String,
+    /// SHA-256 do registro, sem `hash` e `hmac`
+    pub hash: String,
+    /// HMAC-SHA256 do mesmo conteúdo, com `[audit] hmac_key_file`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac: Option<String>,
+}
+
+/// Identificador de uma conexão MCP, gravado em todos os seus eventos
+pub fn new_session_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(1);
+    format!(
+        "{}-{}-{}",
+        Local::now().format("%Y%m%d%H%M%S"),
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// Caminho do audit log (`log_path` é relativo ao diretório de config)
+pub fn log_file_path(log_file: &str) -> Result<PathBuf> {
+    Ok(crate::config::get_config_dir()?.join(log_file))
+}
+
+/// Chave do HMAC configurada em `[audit] hmac_key_file`
+pub fn hmac_key(config: &Config) -> Result<Option<Vec<u8>>> {
+    config
+        .audit
+        .hmac_key_file
+        .as_deref()
+        .map(|file| chain::read_key(&log_file_path(file)?))
+        .transpose()
+}
+
+/// Arquivos do audit (log, `.head`, rotacionados, sinks em arquivo e chave),
+/// bloqueados para os comandos
+pub fn protected_paths(config: &Config) -> Result<Vec<String>> {
+    let mut logs = vec![log_file_path(&config.log_path)?];
+    for sink in &config.audit.sinks {
+        if let AuditSink::File { path } = sink {
+            logs.push(log_file_path(path)?);
+        }
+    }
+    // O caminho exato também alcança buscas recursivas no diretório do log
+    let mut paths: Vec<String> = logs
+        .iter()
+        .flat_map(|log| [log.display().to_string(), format!("{}*", log.display())])
+        .collect();
+    if let Some(key) = &config.audit.hmac_key_file {
+        paths.push(log_file_path(key)?.display().to_string());
+    }
+    Ok(paths)
+}
+
+/// Acrescenta um evento ao audit log, preenchendo data/hora, uid e a cadeia
+///
+/// Segredos no comando e nos detalhes são mascarados antes da gravação.
+pub fn log(config: &Config, mut event: Event) -> Result<()> {
+    event.timestamp = Local::now().to_rfc3339();
+    event.uid = nix::unistd::getuid().as_raw();
+    let redactor = Redactor::from_config(config);
+    event.command = redactor.text(&event.command);
+    event.details = event.details.map(|d| redactor.text(&d));
+
+    let key = hmac_key(config)?;
+    let log_path = log_file_path(&config.log_path)?;
+
+    // O lock serializa os elos entre tarefas e processos que usam o mesmo log
+    let mut file = rotate::open_locked(&log_path)?;
+    // Sem registro encadeado no arquivo (recém-rotacionado), o `.head` tem o último elo
+    let prev = chain::last_head(&mut file)?.or_else(|| chain::read_head(&log_path));
+    if rotate::due(&file, &config.audit)? {
+        rotate::rotate(&log_path, &config.audit)?;
+        file = rotate::open_locked(&log_path)?;
+    }
+
+    let (line, head) = chain::seal(event, prev.as_ref(), key.as_deref())?;
+    writeln!(file, "{}", line).context("Failed to write to audit log")?;
+    chain::write_head(&log_path, &head, key.as_deref())?;
+    drop(file);
+
+    // Todos os sinks são tentados; o primeiro erro é devolvido
+    let record: serde_json::Value = serde_json::from_str(&line)?;
+    let mut result = Ok(());
+    for target in &config.audit.sinks {
+        if let Err(e) = sink::write(target, &line, &record, &config.audit) {
+            if result.is_ok() {
+                result = Err(e);
+            }
+        }
+    }
+    result
+}
+
+/// Evento simples, sem os campos de execução (ex: recarga do config)
+pub fn log_command(
+    config: &Config,
+    command: &str,
+    status: &str,
+    details: Option<&str>,
+) -> Result<()> {
+    log(
+        config,
+        Event {
+            status: status.to_string(),
+            command: command.to_string(),
+            details: details.map(str::to_string),
+            ..Default::default()
+        },
+    )
+}
+
+/// Lê uma linha do audit log
+pub fn parse(line: &str) -> Option<Event> {
+    serde_json::from_str(line).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_json() {
+        let event = Event {
+            status: "SUCCESS".to_string(),
+            execution_id: Some("20260101120000-7".to_string()),
+            command: "ls -la".to_string(),
+            client: Some(Client {
+                name: "inspector".to_string(),
+                version: "1.0".to_string(),
+            }),
+            rule: Some("allowed_commands".to_string()),
+            exit_code: Some(0),
+            ..Default::default()
+        };
+        let line = serde_json::to_string(&event).unwrap();
+        assert!(!line.contains('\n'));
+        assert!(line.contains(r#""client":{"name":"inspector","version":"1.0"}"#));
+        assert!(!line.contains("stdout_bytes"));
+        assert_eq!(parse(&line), Some(event));
+        assert_eq!(parse("[2026-01-01 12:00:00] [ALLOWED] texto antigo"), None);
+    }
+}
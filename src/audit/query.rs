@@ -0,0 +1,239 @@
+//! Consulta ao audit log (tool `audit_search` e resource `linux://audit/recent`)
+//!
+//! Percorre os arquivos rotacionados e o ativo, do mais antigo ao mais
+//! recente, e devolve as últimas entradas que passam pelos filtros.
+
+use super::{rotate, Event};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDate, TimeZone};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::Path;
+
+/// Campos da cadeia de hashes, omitidos nas respostas
+const CHAIN_FIELDS: &[&str] = &["seq", "prev_hash", "hash", "hmac"];
+
+/// Filtros de uma consulta
+#[derive(Debug, Default)]
+pub struct Query {
+    pub since: Option<DateTime<FixedOffset>>,
+    pub until: Option<DateTime<FixedOffset>>,
+    /// Status aceitos, em maiúsculas (vazio aceita todos)
+    pub statuses: Vec<String>,
+    /// Trecho do comando, sem diferenciar maiúsculas
+    pub command: Option<String>,
+    pub session: Option<String>,
+    /// Quantidade máxima de entradas devolvidas (as mais recentes)
+    pub limit: usize,
+}
+
+/// Resultado de `search`
+#[derive(Debug)]
+pub struct Matches {
+    /// Entradas encontradas, da mais antiga para a mais recente
+    pub events: Vec<Event>,
+    /// Total de entradas que passaram pelos filtros, antes do `limit`
+    pub total: usize,
+}
+
+impl Query {
+    fn matches(&self, event: &Event) -> bool {
+        let time = DateTime::parse_from_rfc3339(&event.timestamp).ok();
+        self.since
+            .is_none_or(|since| time.is_some_and(|t| t >= since))
+            && self
+                .until
+                .is_none_or(|until| time.is_some_and(|t| t < until))
+            && (self.statuses.is_empty() || self.statuses.contains(&event.status))
+            && self
+                .command
+                .as_ref()
+                .is_none_or(|c| event.command.to_lowercase().contains(&c.to_lowercase()))
+            && self
+                .session
+                .as_ref()
+                .is_none_or(|s| event.session.as_ref() == Some(s))
+    }
+}
+
+/// Interpreta um limite de tempo
+///
+/// Aceita RFC 3339 (`2026-01-01T12:00:00-03:00`), uma data local
+/// (`2026-01-01`), `today`, `yesterday` ou uma duração até agora (`30m`,
+/// `24h`, `7d`).
+pub fn parse_time(value: &str, now: DateTime<Local>) -> Result<DateTime<FixedOffset>> {
+    let value = value.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(value) {
+        return Ok(time);
+    }
+    let midnight = |date: NaiveDate| {
+        Local
+            .from_local_datetime(&date.and_hms_opt(0, 0, 0).unwrap_or_default())
+            .earliest()
+            .map(|t| t.fixed_offset())
+            .with_context(|| format!("Invalid local date '{}'", value))
+    };
+    match value {
+        "today" => return midnight(now.date_naive()),
+        "yesterday" => return midnight(now.date_naive() - Duration::days(1)),
+        _ => {}
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return midnight(date);
+    }
+
+    let (split, unit) = value.char_indices().last().context("Empty time")?;
+    let amount: i64 = value[..split]
+        .parse()
+        .with_context(|| format!("Invalid time '{}'", value))?;
+    let ago = match unit {
+        'm' => Duration::minutes(amount),
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        _ => anyhow::bail!("Invalid time '{}'", value),
+    };
+    Ok((now - ago).fixed_offset())
+}
+
+/// Busca no log e nos seus arquivos rotacionados
+///
+/// Linhas fora do formato JSON (log antigo) são ignoradas.
+pub fn search(log_path: &Path, query: &Query) -> Result<Matches> {
+    let mut events = VecDeque::new();
+    let mut total = 0;
+    for file in rotate::files(log_path)? {
+        for line in rotate::read(&file)?.lines() {
+            let Some(event) = super::parse(line).filter(|e| query.matches(e)) else {
+                continue;
+            };
+            total += 1;
+            events.push_back(event);
+            if events.len() > query.limit {
+                events.pop_front();
+            }
+        }
+    }
+    Ok(Matches {
+        events: events.into(),
+        total,
+    })
+}
+
+/// Entrada em JSON sem os campos da cadeia de hashes
+pub fn to_json(event: &Event) -> Value {
+    let mut value = serde_json::to_value(event).unwrap_or_default();
+    if let Value::Object(map) = &mut value {
+        for field in CHAIN_FIELDS {
+            map.remove(*field);
+        }
+    }
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn event(timestamp: &str, status: &str, command: &str, session: &str) -> String {
+        serde_json::to_string(&Event {
+            timestamp: timestamp.to_string(),
+            status: status.to_string(),
+            command: command.to_string(),
+            session: Some(session.to_string()),
+            seq: 1,
+            hash: "abc".to_string(),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_search() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-query-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log");
+        let lines = [
+            "[2026-01-01 09:00:00] [ALLOWED] texto antigo".to_string(),
+            event(
+                "2026-01-01T10:00:00+00:00",
+                "BLOCKED",
+                "cat /etc/shadow",
+                "a",
+            ),
+            event("2026-01-01T11:00:00+00:00", "SUCCESS", "ls /tmp", "a"),
+            event(
+                "2026-01-02T10:00:00+00:00",
+                "ERROR",
+                "systemctl restart nginx",
+                "b",
+            ),
+            event(
+                "2026-01-02T11:00:00+00:00",
+                "BLOCKED",
+                "Cat /root/.ssh/id_rsa",
+                "b",
+            ),
+        ];
+        fs::write(&path, lines.join("\n")).unwrap();
+        let time = |t: &str| DateTime::parse_from_rfc3339(t).ok();
+
+        let query = Query {
+            statuses: vec!["BLOCKED".to_string(), "ERROR".to_string()],
+            limit: 2,
+            ..Default::default()
+        };
+        let found = search(&path, &query).unwrap();
+        assert_eq!(found.total, 3);
+        let commands: Vec<&str> = found.events.iter().map(|e| e.command.as_str()).collect();
+        assert_eq!(
+            commands,
+            ["systemctl restart nginx", "Cat /root/.ssh/id_rsa"]
+        );
+
+        let query = Query {
+            since: time("2026-01-01T10:30:00+00:00"),
+            until: time("2026-01-02T10:00:00+00:00"),
+            limit: 10,
+            ..Default::default()
+        };
+        assert_eq!(search(&path, &query).unwrap().events[0].command, "ls /tmp");
+
+        let query = Query {
+            command: Some("cat /".to_string()),
+            session: Some("b".to_string()),
+            limit: 10,
+            ..Default::default()
+        };
+        let found = search(&path, &query).unwrap();
+        assert_eq!(found.total, 1);
+        assert!(to_json(&found.events[0]).get("hash").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_time() {
+        let now = Local.with_ymd_and_hms(2026, 3, 10, 15, 30, 0).unwrap();
+        let midnight = |day| {
+            Local
+                .with_ymd_and_hms(2026, 3, day, 0, 0, 0)
+                .unwrap()
+                .fixed_offset()
+        };
+        assert_eq!(parse_time("yesterday", now).unwrap(), midnight(9));
+        assert_eq!(parse_time("2026-03-01", now).unwrap(), midnight(1));
+        assert_eq!(
+            parse_time("24h", now).unwrap(),
+            now.fixed_offset() - Duration::hours(24)
+        );
+        assert_eq!(
+            parse_time("2026-03-10T12:00:00Z", now)
+                .unwrap()
+                .to_rfc3339(),
+            "2026-03-10T12:00:00+00:00"
+        );
+        assert!(parse_time("ontem", now).is_err());
+        assert!(parse_time("5w", now).is_err());
+    }
+}
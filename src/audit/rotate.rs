@@ -0,0 +1,210 @@
+//! Rotação, compressão e retenção dos arquivos do audit log
+//!
+//! O arquivo ativo é renomeado para `<log>.<AAAAMMDD-HHMMSS-mmm>` (comprimido
+//! em `.gz` com `compress`) quando passa de `max_size_mb` ou quando o seu
+//! registro mais antigo passa de `max_age_hours`. Só os `retention` arquivos
+//! rotacionados mais recentes são mantidos. A cadeia de hashes continua no
+//! novo arquivo.
+
+use super::Event;
+use crate::config::AuditConfig;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use nix::fcntl::{Flock, FlockArg};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::os::unix::fs::{MetadataExt, OpenOptionsExt};
+use std::path::{Path, PathBuf};
+
+/// Abre o arquivo ativo para acréscimo com um lock exclusivo
+///
+/// Se outro processo rotacionou o arquivo entre o `open` e o lock, o novo
+/// arquivo é aberto.
+pub fn open_locked(path: &Path) -> Result<Flock<File>> {
+    loop {
+        let file = OpenOptions::new()
+            .create(true)
+            .read(true)
+            .append(true)
+            .mode(0o600)
+            .open(path)
+            .with_context(|| format!("Failed to open audit log file {}", path.display()))?;
+        let file = Flock::lock(file, FlockArg::LockExclusive)
+            .map_err(|(_, e)| e)
+            .context("Failed to lock audit log file")?;
+
+        let locked = file.metadata()?;
+        if fs::metadata(path).is_ok_and(|m| m.ino() == locked.ino() && m.dev() == locked.dev()) {
+            return Ok(file);
+        }
+    }
+}
+
+/// Data do registro mais antigo do arquivo (ou da criação do arquivo)
+fn oldest_record(file: &File) -> Result<Option<DateTime<Local>>> {
+    let mut reader = BufReader::new(file.try_clone()?);
+    reader.seek(SeekFrom::Start(0))?;
+    let mut first = String::new();
+    reader.read_line(&mut first)?;
+
+    let timestamp = serde_json::from_str::<Event>(&first)
+        .ok()
+        .and_then(|e| DateTime::parse_from_rfc3339(&e.timestamp).ok())
+        .map(|t| t.with_timezone(&Local));
+    Ok(timestamp.or_else(|| file.metadata().ok()?.created().ok().map(DateTime::from)))
+}
+
+/// Se o arquivo ativo deve ser rotacionado antes do próximo registro
+pub fn due(file: &File, config: &AuditConfig) -> Result<bool> {
+    let len = file.metadata()?.len();
+    if len == 0 {
+        return Ok(false);
+    }
+    if config.max_size_mb > 0 && len >= config.max_size_mb * 1024 * 1024 {
+        return Ok(true);
+    }
+    if config.max_age_hours > 0 {
+        if let Some(oldest) = oldest_record(file)? {
+            let age = Local::now().signed_duration_since(oldest);
+            return Ok(age.num_hours() >= config.max_age_hours as i64);
+        }
+    }
+    Ok(false)
+}
+
+/// Renomeia o arquivo ativo, comprime e aplica a retenção
+pub fn rotate(path: &Path, config: &AuditConfig) -> Result<()> {
+    let mut name = path
+        .file_name()
+        .context("Invalid audit log path")?
+        .to_owned();
+    name.push(format!(".{}", Local::now().format("%Y%m%d-%H%M%S-%3f")));
+    let rotated = path.with_file_name(name);
+    fs::rename(path, &rotated).context("Failed to rotate audit log")?;
+
+    if config.compress {
+        compress(&rotated)?;
+    }
+    for old in rotated_files(path)?.iter().rev().skip(config.retention) {
+        fs::remove_file(old)
+            .with_context(|| format!("Failed to remove old audit log {}", old.display()))?;
+    }
+    Ok(())
+}
+
+fn compress(path: &Path) -> Result<()> {
+    let mut gz = path.as_os_str().to_owned();
+    gz.push(".gz");
+    let mut input = File::open(path)?;
+    let output = OpenOptions::new()
+        .create_new(true)
+        .write(true)
+        .mode(0o600)
+        .open(PathBuf::from(gz))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder
+        .finish()
+        .context("Failed to compress rotated audit log")?;
+    fs::remove_file(path)?;
+    Ok(())
+}
+
+/// Arquivos rotacionados de um log, do mais antigo para o mais recente
+pub fn rotated_files(path: &Path) -> Result<Vec<PathBuf>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{}.", name.to_string_lossy());
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| {
+            let name = e.file_name().to_string_lossy().to_string();
+            name.strip_prefix(&prefix).is_some_and(is_rotation_suffix)
+        })
+        .map(|e| e.path())
+        .collect();
+    // O nome contém a data da rotação, então a ordem alfabética é a cronológica
+    files.sort();
+    Ok(files)
+}
+
+/// `AAAAMMDD-HHMMSS-mmm`, opcionalmente com `.gz`
+fn is_rotation_suffix(suffix: &str) -> bool {
+    let stamp = suffix.strip_suffix(".gz").unwrap_or(suffix);
+    stamp.len() == 19
+        && stamp.chars().enumerate().all(|(i, c)| {
+            if i == 8 || i == 15 {
+                c == '-'
+            } else {
+                c.is_ascii_digit()
+            }
+        })
+}
+
+/// Arquivos rotacionados e o ativo, em ordem cronológica
+pub fn files(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = rotated_files(path)?;
+    if path.exists() {
+        files.push(path.to_path_buf());
+    }
+    Ok(files)
+}
+
+/// Lê um arquivo do log, descomprimindo os `.gz`
+pub fn read(path: &Path) -> Result<String> {
+    let file =
+        File::open(path).with_context(|| format!("Failed to read audit log {}", path.display()))?;
+    let mut contents = String::new();
+    if path.extension().is_some_and(|e| e == "gz") {
+        GzDecoder::new(file).read_to_string(&mut contents)?;
+    } else {
+        BufReader::new(file).read_to_string(&mut contents)?;
+    }
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_rotate() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-rotate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("audit.log");
+        let config = AuditConfig {
+            max_size_mb: 1,
+            retention: 2,
+            ..Default::default()
+        };
+
+        for i in 0..3 {
+            let mut file = open_locked(&path).unwrap();
+            assert!(!due(&file, &config).unwrap());
+            writeln!(file, "{}", format!("{}", i).repeat(1024 * 1024)).unwrap();
+            assert!(due(&file, &config).unwrap());
+            rotate(&path, &config).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+
+        // Só os dois mais recentes ficam, comprimidos e legíveis
+        let rotated = rotated_files(&path).unwrap();
+        assert_eq!(rotated.len(), 2);
+        assert!(rotated.iter().all(|f| f.extension().unwrap() == "gz"));
+        assert!(read(&rotated[0]).unwrap().starts_with('1'));
+        assert!(read(&rotated[1]).unwrap().starts_with('2'));
+        assert!(!path.exists());
+        assert!(!is_rotation_suffix("head"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
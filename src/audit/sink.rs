@@ -0,0 +1,191 @@
+//! Destinos adicionais do audit log (`[[audit.sinks]]`)
+//!
+//! Recebem o mesmo registro já encadeado que vai para o arquivo principal.
+
+use super::{log_file_path, rotate};
+use crate::config::{AuditConfig, AuditSink, SyslogFacility};
+use anyhow::{Context, Result};
+use chrono::Local;
+use serde_json::Value;
+use std::io::Write;
+use std::os::unix::net::{UnixDatagram, UnixStream};
+use std::path::Path;
+
+/// Socket do protocolo nativo do journald
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+
+/// Status registrados com prioridade `warning` (os demais são `info`)
+const WARNING_STATUSES: &[&str] = &["BLOCKED", "DENIED", "ERROR", "TIMEOUT", "CONFIG_REJECTED"];
+
+/// Envia um registro (a linha JSON e o mesmo conteúdo já decodificado) a um destino
+pub fn write(sink: &AuditSink, line: &str, record: &Value, config: &AuditConfig) -> Result<()> {
+    match sink {
+        AuditSink::Journald => journald(Path::new(JOURNALD_SOCKET), record),
+        AuditSink::Syslog { socket, facility } => {
+            syslog(Path::new(socket), *facility, severity(record), line)
+        }
+        AuditSink::File { path } => file(&log_file_path(path)?, line, config),
+    }
+}
+
+/// Severidade syslog do registro (4 = warning, 6 = info)
+fn severity(record: &Value) -> u8 {
+    let status = record.get("status").and_then(Value::as_str).unwrap_or("");
+    if WARNING_STATUSES.contains(&status) {
+        4
+    } else {
+        6
+    }
+}
+
+/// Acrescenta um campo no formato nativo do journald
+///
+/// Valores com quebra de linha usam a forma binária (tamanho em 64 bits LE).
+fn journal_field(buf: &mut Vec<u8>, key: &str, value: &str) {
+    buf.extend_from_slice(key.as_bytes());
+    if value.contains('\n') {
+        buf.push(b'\n');
+        buf.extend_from_slice(&(value.len() as u64).to_le_bytes());
+    } else {
+        buf.push(b'=');
+    }
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(b'\n');
+}
+
+/// Campos do registro como `LINUX_MCP_<CAMPO>` (objetos viram `LINUX_MCP_CLIENT_NAME`)
+fn journal_fields(buf: &mut Vec<u8>, prefix: &str, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                journal_fields(buf, &format!("{}_{}", prefix, key.to_uppercase()), value);
+            }
+        }
+        Value::Null => {}
+        Value::String(s) => journal_field(buf, prefix, s),
+        other => journal_field(buf, prefix, &other.to_string()),
+    }
+}
+
+fn journald(socket: &Path, record: &Value) -> Result<()> {
+    let field = |name: &str| record.get(name).and_then(Value::as_str).unwrap_or("");
+    let mut buf = Vec::new();
+    journal_field(
+        &mut buf,
+        "MESSAGE",
+        &format!("{} {}", field("status"), field("command")),
+    );
+    journal_field(&mut buf, "PRIORITY", &severity(record).to_string());
+    journal_field(&mut buf, "SYSLOG_IDENTIFIER", "linux-mcp");
+    journal_fields(&mut buf, "LINUX_MCP", record);
+
+    UnixDatagram::unbound()?
+        .send_to(&buf, socket)
+        .with_context(|| {
+            format!(
+                "Failed to send audit record to journald ({})",
+                socket.display()
+            )
+        })?;
+    Ok(())
+}
+
+fn facility_code(facility: SyslogFacility) -> u8 {
+    match facility {
+        SyslogFacility::User => 1,
+        SyslogFacility::Daemon => 3,
+        SyslogFacility::Auth => 4,
+        SyslogFacility::Authpriv => 10,
+        SyslogFacility::Local0 => 16,
+        SyslogFacility::Local1 => 17,
+        SyslogFacility::Local2 => 18,
+        SyslogFacility::Local3 => 19,
+        SyslogFacility::Local4 => 20,
+        SyslogFacility::Local5 => 21,
+        SyslogFacility::Local6 => 22,
+        SyslogFacility::Local7 => 23,
+    }
+}
+
+/// Mensagem no formato do socket local (RFC 3164, sem hostname)
+fn syslog_message(facility: SyslogFacility, severity: u8, line: &str) -> String {
+    format!(
+        "<{}>{} linux-mcp[{}]: {}",
+        facility_code(facility) * 8 + severity,
+        Local::now().format("%b %e %H:%M:%S"),
+        std::process::id(),
+        line
+    )
+}
+
+fn syslog(socket: &Path, facility: SyslogFacility, severity: u8, line: &str) -> Result<()> {
+    let message = syslog_message(facility, severity, line);
+    let context = || {
+        format!(
+            "Failed to send audit record to syslog ({})",
+            socket.display()
+        )
+    };
+
+    // `/dev/log` costuma ser datagrama; alguns daemons usam stream
+    match UnixDatagram::unbound()?.send_to(message.as_bytes(), socket) {
+        Ok(_) => Ok(()),
+        Err(e) if e.raw_os_error() == Some(nix::libc::EPROTOTYPE) => {
+            let mut stream = UnixStream::connect(socket).with_context(context)?;
+            stream
+                .write_all(format!("{}\n", message).as_bytes())
+                .with_context(context)
+        }
+        Err(e) => Err(e).with_context(context),
+    }
+}
+
+/// Cópia em outro arquivo, com a mesma rotação do log principal
+fn file(path: &Path, line: &str, config: &AuditConfig) -> Result<()> {
+    let mut file = rotate::open_locked(path)?;
+    if rotate::due(&file, config)? {
+        rotate::rotate(path, config)?;
+        file = rotate::open_locked(path)?;
+    }
+    writeln!(file, "{}", line).context("Failed to write to audit file sink")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_local_sockets() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-sink-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let record = json!({
+            "status": "BLOCKED",
+            "command": "cat /etc/shadow",
+            "client": {"name": "inspector", "version": "1.0"},
+            "details": "linha 1\nlinha 2",
+            "exit_code": null,
+        });
+        let mut buf = [0u8; 4096];
+
+        let socket = dir.join("journal.sock");
+        let server = UnixDatagram::bind(&socket).unwrap();
+        journald(&socket, &record).unwrap();
+        let n = server.recv(&mut buf).unwrap();
+        let text = String::from_utf8_lossy(&buf[..n]);
+        assert!(text.starts_with("MESSAGE=BLOCKED cat /etc/shadow\nPRIORITY=4\n"));
+        assert!(text.contains("LINUX_MCP_CLIENT_NAME=inspector\n"));
+        assert!(text.contains("LINUX_MCP_DETAILS\n\x0f\0\0\0\0\0\0\0linha 1\nlinha 2\n"));
+        assert!(!text.contains("EXIT_CODE"));
+
+        let socket = dir.join("log.sock");
+        let server = UnixDatagram::bind(&socket).unwrap();
+        syslog(&socket, SyslogFacility::Auth, 4, "{}").unwrap();
+        let n = server.recv(&mut buf).unwrap();
+        let text = String::from_utf8_lossy(&buf[..n]);
+        assert!(text.starts_with("<36>"), "{}", text);
+        assert!(text.ends_with(&format!("linux-mcp[{}]: {{}}", std::process::id())));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
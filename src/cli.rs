@@ -0,0 +1,377 @@
+//! Linha de comando
+//!
+//! Sem subcomando, o binário serve MCP via stdio (o que os clientes esperam).
+//! Os demais subcomandos permitem verificar políticas e o audit log de um
+//! terminal, sem um cliente MCP.
+
+use crate::config::{self, Config, Layers};
+use crate::{audit, resources, tools};
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand, ValueEnum};
+use regex::Regex;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::os::unix::fs::MetadataExt;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Variável de ambiente equivalente a `--profile`
+pub const PROFILE_ENV: &str = "LINUX_MCP_PROFILE";
+
+#[derive(Parser)]
+#[command(version, about = "Servidor MCP seguro para Linux")]
+pub struct Cli {
+    /// Arquivo de configuração com precedência sobre o do usuário
+    #[arg(long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Perfil de `[profiles]` aplicado (no servidor, às conexões sem perfil
+    /// definido pela credencial)
+    #[arg(long, global = true, value_name = "NAME", env = PROFILE_ENV)]
+    pub profile: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Inicia o servidor MCP (padrão)
+    Serve {
+        /// Transporte usado para falar com o cliente
+        #[arg(long, value_enum, default_value_t = Transport::Stdio)]
+        transport: Transport,
+        /// Endereço de escuta do transporte HTTP (padrão: `[http] bind`)
+        #[arg(long, conflicts_with = "socket")]
+        bind: Option<String>,
+        /// Atende pelo socket Unix informado (padrão: `$XDG_RUNTIME_DIR/linux-mcp.sock`),
+        /// autorizando cada cliente pelo uid/gid do processo (`[[socket.peers]]`)
+        #[arg(long, value_name = "PATH", num_args = 0..=1, conflicts_with = "transport")]
+        socket: Option<Option<PathBuf>>,
+    },
+    /// Verifica se um comando passaria pela allowlist e pelas políticas, sem executá-lo
+    Check {
+        /// Linha de comando (interpretada como `sh -c`) ou, com --argv, programa e argumentos
+        #[arg(required = true, trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+        /// Trata os argumentos como argv, sem shell
+        #[arg(long)]
+        argv: bool,
+        /// Avalia como se `use_polkit` fosse true
+        #[arg(long)]
+        polkit: bool,
+        /// Diretório de trabalho do comando
+        #[arg(long, value_name = "DIR")]
+        cwd: Option<String>,
+    },
+    /// Gerencia a configuração
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+    /// Consulta o audit log
+    Audit {
+        #[command(subcommand)]
+        action: AuditAction,
+    },
+    /// Mostra o manifesto de capacidades (`linux://mcp/capabilities`)
+    Capabilities {
+        /// Em JSON (`linux://mcp/capabilities.json`)
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Transport {
+    /// stdin/stdout
+    Stdio,
+    /// Streamable HTTP, autenticado por token ou certificado de cliente (`[http]`)
+    Http,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Cria o arquivo de configuração com os valores padrão
+    Init {
+        /// Sobrescreve um arquivo existente
+        #[arg(long)]
+        force: bool,
+    },
+    /// Valida a configuração (todas as camadas ou apenas FILE)
+    Validate { file: Option<PathBuf> },
+    /// Mostra os arquivos de configuração em ordem de precedência
+    Show {
+        /// Mostra cada valor efetivo e a camada de onde ele veio
+        #[arg(long)]
+        effective: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum AuditAction {
+    /// Mostra as últimas entradas
+    Tail {
+        /// Quantidade de entradas
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: usize,
+        /// Continua mostrando novas entradas
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Confere a cadeia de hashes (e os HMACs) do audit log
+    Verify {
+        /// Log a conferir (padrão: o log configurado)
+        file: Option<PathBuf>,
+    },
+    /// Procura entradas por regex
+    Search {
+        /// Regex aplicada à entrada inteira
+        pattern: String,
+        /// Filtra pelo status (ALLOWED, BLOCKED, SUCCESS, ...)
+        #[arg(long)]
+        status: Option<String>,
+        /// Máximo de entradas mostradas (as mais recentes)
+        #[arg(short = 'n', long, default_value_t = 100)]
+        limit: usize,
+    },
+}
+
+/// Carrega a configuração com o perfil de `--profile` aplicado
+fn load_with_profile(layers: &Layers, profile: Option<&str>) -> Result<Config> {
+    let config = config::load_readonly(layers)?.config;
+    match profile {
+        Some(name) => {
+            config.require_profile(name)?;
+            Ok(config.with_profile(name))
+        }
+        None => Ok(config),
+    }
+}
+
+pub fn check(
+    layers: &Layers,
+    profile: Option<&str>,
+    command: Vec<String>,
+    argv: bool,
+    polkit: bool,
+    cwd: Option<String>,
+) -> Result<ExitCode> {
+    let config = load_with_profile(layers, profile)?;
+    let args = tools::ExecuteCommandArgs {
+        command: (!argv).then(|| command.join(" ")),
+        argv: argv.then_some(command),
+        use_polkit: Some(polkit),
+        cwd,
+        ..Default::default()
+    };
+
+    match tools::check_command(&args, &config) {
+        tools::CheckOutcome::Allowed {
+            command_line,
+            sandbox,
+            rule,
+            confirm,
+        } => {
+            println!("ALLOWED: {}", command_line);
+            println!("rule: {}", rule);
+            if let Some(profile) = sandbox {
+                println!("sandbox: {}", profile);
+            }
+            if !confirm.is_empty() {
+                println!("confirm: {} (requires user approval)", confirm.join(", "));
+            }
+            Ok(ExitCode::SUCCESS)
+        }
+        tools::CheckOutcome::Blocked { reason, rule } => {
+            println!("BLOCKED: {}", reason);
+            println!("rule: {}", rule);
+            Ok(ExitCode::FAILURE)
+        }
+        tools::CheckOutcome::Invalid(message) => {
+            println!("INVALID: {}", message);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+pub fn config_init(layers: &Layers, force: bool) -> Result<ExitCode> {
+    let path = layers.extra.as_ref().unwrap_or(&layers.user);
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists (use --force to overwrite)",
+            path.display()
+        );
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).context("Failed to create config directory")?;
+    }
+    config::write_default(path)?;
+    println!("{}", path.display());
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn config_validate(layers: &Layers, file: Option<PathBuf>) -> Result<ExitCode> {
+    let result = match &file {
+        Some(file) => config::read(file),
+        None => config::load_readonly(layers).map(|effective| effective.config),
+    };
+    match result {
+        Ok(config) => {
+            for warning in config.warnings() {
+                println!("warning: {}", warning);
+            }
+            println!("OK");
+            Ok(ExitCode::SUCCESS)
+        }
+        Err(e) => {
+            println!("INVALID: {:#}", e);
+            Ok(ExitCode::FAILURE)
+        }
+    }
+}
+
+pub fn config_show(layers: &Layers, effective: bool) -> Result<ExitCode> {
+    if !effective {
+        println!("# Da menor para a maior precedência");
+        for file in layers.files() {
+            let state = if file.exists() { "" } else { " (ausente)" };
+            println!("{}{}", file.display(), state);
+        }
+        println!("LINUX_MCP_* (variáveis de ambiente)");
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    print!("{}", config::load_readonly(layers)?.render()?);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Caminho do audit log, mesmo que a configuração atual esteja inválida
+fn audit_log_path(layers: &Layers) -> Result<PathBuf> {
+    let log_path = match config::load_readonly(layers) {
+        Ok(effective) => effective.config.log_path,
+        Err(e) => {
+            eprintln!("warning: using the default audit log: {:#}", e);
+            config::Config::default().log_path
+        }
+    };
+    audit::log_file_path(&log_path)
+}
+
+pub fn audit_tail(layers: &Layers, lines: usize, follow: bool) -> Result<ExitCode> {
+    let path = audit_log_path(layers)?;
+    let mut file = fs::File::open(&path)
+        .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let entries: Vec<&str> = contents.lines().collect();
+    for entry in &entries[entries.len().saturating_sub(lines)..] {
+        println!("{}", entry);
+    }
+
+    if !follow {
+        return Ok(ExitCode::SUCCESS);
+    }
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let mut appended = String::new();
+        // Log rotacionado: termina o arquivo antigo e passa para o novo
+        let current = file.metadata()?.ino();
+        if fs::metadata(&path).is_ok_and(|m| m.ino() != current) {
+            file.read_to_string(&mut appended)?;
+            file = fs::File::open(&path)?;
+        }
+        // Log truncado: recomeça do início
+        if file.metadata()?.len() < file.stream_position()? {
+            file.seek(SeekFrom::Start(0))?;
+        }
+        file.read_to_string(&mut appended)?;
+        print!("{}", appended);
+    }
+}
+
+pub fn audit_verify(layers: &Layers, file: Option<PathBuf>) -> Result<ExitCode> {
+    let config = config::load_readonly(layers)?.config;
+    let path = match file {
+        Some(file) => file,
+        None => audit::log_file_path(&config.log_path)?,
+    };
+    let key = audit::hmac_key(&config)?;
+    let report = audit::chain::verify(&path, key.as_deref())?;
+
+    if report.legacy > 0 {
+        println!(
+            "warning: {} entries from before the hash chain were not verified",
+            report.legacy
+        );
+    }
+    if report.first_seq > 1 {
+        println!(
+            "warning: records before seq {} were removed by [audit] retention",
+            report.first_seq
+        );
+    }
+    if !report.hmac_checked {
+        println!("warning: no [audit] hmac_key_file configured; HMACs were not checked");
+    }
+    if !report.problems.is_empty() {
+        for problem in &report.problems {
+            println!("TAMPERED: {}", problem);
+        }
+        return Ok(ExitCode::FAILURE);
+    }
+    println!("OK: {} records", report.records);
+    Ok(ExitCode::SUCCESS)
+}
+
+pub fn audit_search(
+    layers: &Layers,
+    pattern: &str,
+    status: Option<String>,
+    limit: usize,
+) -> Result<ExitCode> {
+    let regex = Regex::new(pattern).context("Invalid search pattern")?;
+    let status = status.map(|s| s.to_uppercase());
+    let path = audit_log_path(layers)?;
+    // Arquivos rotacionados (inclusive `.gz`) e o ativo, do mais antigo ao mais recente
+    let mut contents = String::new();
+    for file in audit::rotate::files(&path)? {
+        contents.push_str(&audit::rotate::read(&file)?);
+    }
+    if contents.is_empty() && !path.exists() {
+        anyhow::bail!("Failed to read audit log {}: not found", path.display());
+    }
+
+    // O status é comparado com o campo do JSON; linhas em outro formato só
+    // casam sem --status
+    let matches: Vec<&str> = contents
+        .lines()
+        .filter(|entry| regex.is_match(entry))
+        .filter(|entry| {
+            status
+                .as_ref()
+                .is_none_or(|s| audit::parse(entry).is_some_and(|event| event.status == *s))
+        })
+        .collect();
+    for entry in &matches[matches.len().saturating_sub(limit)..] {
+        println!("{}", entry);
+    }
+    Ok(if matches.is_empty() {
+        ExitCode::FAILURE
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+pub fn capabilities(layers: &Layers, profile: Option<&str>, json: bool) -> Result<ExitCode> {
+    let config = load_with_profile(layers, profile)?;
+    let manifest = resources::Manifest::new(&config);
+    if json {
+        println!("{}", manifest.json());
+    } else {
+        println!("{}", manifest.markdown());
+    }
+    Ok(ExitCode::SUCCESS)
+}
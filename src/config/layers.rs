@@ -0,0 +1,431 @@
+//! Configuração em camadas
+//!
+//! Da menor para a maior precedência: valores padrão,
+//! `/etc/linux-mcp/config.toml` (administrador), `~/.config/linux-mcp/config.toml`
+//! (usuário), o arquivo de `--config` e variáveis `LINUX_MCP_*`.
+//!
+//! Tabelas são mescladas chave a chave; listas e valores simples são
+//! substituídos. As chaves em `locked_keys` do arquivo de sistema não podem
+//! ser afrouxadas pelas camadas acima: números ficam com o menor valor,
+//! `require_argv` com o `true`, listas de bloqueio com a união, outras listas
+//! com a interseção, e demais valores com o do administrador.
+
+use super::Config;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/linux-mcp/config.toml";
+
+/// Prefixo das variáveis de ambiente (`LINUX_MCP_MAX_TIMEOUT_SECS=30`,
+/// `LINUX_MCP_SANDBOX__DEFAULT_PROFILE=isolated`)
+const ENV_PREFIX: &str = "LINUX_MCP_";
+
+/// Listas em que mais itens significam mais restrição
+const ADDITIVE_LISTS: &[&str] = &["denied_paths", "env_denylist"];
+
+const DEFAULT_SOURCE: &str = "default";
+
+/// Arquivos de configuração, em ordem de precedência
+#[derive(Clone, Debug)]
+pub struct Layers {
+    pub system: PathBuf,
+    pub user: PathBuf,
+    /// Arquivo passado em `--config`
+    pub extra: Option<PathBuf>,
+}
+
+impl Layers {
+    pub fn new(extra: Option<PathBuf>) -> Result<Self> {
+        let extra = extra
+            .map(|p| std::path::absolute(&p))
+            .transpose()
+            .context("Failed to resolve --config path")?;
+        Ok(Self {
+            system: PathBuf::from(SYSTEM_CONFIG_PATH),
+            user: super::get_config_path()?,
+            extra,
+        })
+    }
+
+    /// Todos os arquivos que compõem a configuração (existentes ou não)
+    pub fn files(&self) -> Vec<&Path> {
+        let mut files = vec![self.system.as_path(), self.user.as_path()];
+        files.extend(self.extra.as_deref());
+        files
+    }
+}
+
+/// Configuração resultante e a origem de cada valor
+#[derive(Debug)]
+pub struct Effective {
+    pub config: Config,
+    sources: BTreeMap<String, String>,
+}
+
+impl Effective {
+    /// Origem de um valor (`commands.find.forbidden_flags`, por exemplo)
+    pub fn source(&self, key: &str) -> &str {
+        let mut key = key;
+        loop {
+            if let Some(source) = self.sources.get(key) {
+                return source;
+            }
+            match key.rfind('.') {
+                Some(i) => key = &key[..i],
+                None => return DEFAULT_SOURCE,
+            }
+        }
+    }
+
+    /// TOML com chaves pontuadas, uma por linha, comentadas com a origem
+    pub fn render(&self) -> Result<String> {
+        let table = Table::try_from(&self.config)?;
+        let mut leaves = Vec::new();
+        flatten(&table, "", &mut leaves);
+
+        let mut out = String::new();
+        for (key, value) in leaves {
+            out.push_str(&format!("{} = {}  # {}\n", key, value, self.source(&key)));
+        }
+        Ok(out)
+    }
+}
+
+/// Lê e mescla todas as camadas
+pub fn load(layers: &Layers) -> Result<Effective> {
+    let files = [(&layers.system, true), (&layers.user, false)]
+        .into_iter()
+        .chain(layers.extra.iter().map(|p| (p, false)));
+
+    let mut system = Table::new();
+    let mut merged = Table::new();
+    let mut sources = BTreeMap::new();
+    for (path, is_system) in files {
+        // Só o arquivo de `--config` precisa existir
+        if !path.exists() && Some(path) != layers.extra.as_ref() {
+            continue;
+        }
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file {}", path.display()))?;
+        let mut table: Table = toml::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file {}", path.display()))?;
+        // Arquivos antigos valem como já migrados, mesmo sem serem regravados
+        let migration = super::migrate::migrate(&mut table)
+            .with_context(|| format!("Invalid config file {}", path.display()))?;
+        // Valida o arquivo isolado (já migrado); sem mudanças da migração, o
+        // texto original aponta linha, coluna e chave de erros
+        if migration.is_some_and(|m| m.changed) {
+            table.clone().try_into::<Config>().map(drop)
+        } else {
+            toml::from_str::<Config>(&contents).map(drop)
+        }
+        .with_context(|| format!("Invalid config file {}", path.display()))?;
+        // A versão vale para o arquivo, não para a configuração mesclada
+        table.remove("version");
+        if is_system {
+            system = table.clone();
+        } else if table.contains_key("locked_keys") {
+            anyhow::bail!(
+                "locked_keys can only be set in {} (found in {})",
+                SYSTEM_CONFIG_PATH,
+                path.display()
+            );
+        }
+        merge(
+            &mut merged,
+            table,
+            "",
+            &path.display().to_string(),
+            &mut sources,
+        );
+    }
+
+    let env = env_overrides(std::env::vars())?;
+    for (name, table) in env {
+        merge(
+            &mut merged,
+            table,
+            "",
+            &format!("env {}", name),
+            &mut sources,
+        );
+    }
+
+    lock(&mut merged, &system, &layers.system, &mut sources)?;
+
+    let mut config: Config = merged
+        .try_into()
+        .context("Failed to parse merged configuration")?;
+    config.validate()?;
+    config.config_files = layers.files().into_iter().map(PathBuf::from).collect();
+    Ok(Effective { config, sources })
+}
+
+/// Mescla `src` em `dst`, registrando a origem de cada chave alterada
+fn merge(
+    dst: &mut Table,
+    src: Table,
+    prefix: &str,
+    source: &str,
+    sources: &mut BTreeMap<String, String>,
+) {
+    for (key, value) in src {
+        let path = join(prefix, &key);
+        if value.is_table() && !dst.contains_key(&key) {
+            dst.insert(key.clone(), Value::Table(Table::new()));
+        }
+        match (dst.get_mut(&key), value) {
+            (Some(Value::Table(dst)), Value::Table(src)) => merge(dst, src, &path, source, sources),
+            (_, value) => {
+                let nested = format!("{}.", path);
+                sources.retain(|k, _| !k.starts_with(&nested));
+                sources.insert(path, source.to_string());
+                dst.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Converte `LINUX_MCP_*` em tabelas TOML de uma chave
+///
+/// Variáveis que não correspondem a uma chave do config são ignoradas.
+fn env_overrides<I>(vars: I) -> Result<Vec<(String, Table)>>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    let known = Table::try_from(Config::default())?;
+    let mut overrides = Vec::new();
+    for (name, raw) in vars {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        // Escolhe o perfil da sessão, não é uma chave do config
+        if name == crate::cli::PROFILE_ENV {
+            continue;
+        }
+        let path: Vec<String> = key.to_lowercase().split("__").map(str::to_string).collect();
+        // Variáveis de outras ferramentas com o mesmo prefixo não derrubam o servidor
+        if path.iter().any(|p| p.is_empty()) || !known.contains_key(&path[0]) {
+            eprintln!(
+                "warning: ignoring environment variable {}: unknown configuration key",
+                name
+            );
+            continue;
+        }
+
+        // Valores TOML (`30`, `true`, `["ls", "cat"]`); o resto vira string
+        let value = toml::from_str::<Table>(&format!("value = {}", raw))
+            .ok()
+            .and_then(|mut t| t.remove("value"))
+            .unwrap_or(Value::String(raw));
+        let table = path.iter().rev().fold(value, |value, key| {
+            Value::Table(Table::from_iter([(key.clone(), value)]))
+        });
+        if let Value::Table(table) = table {
+            overrides.push((name, table));
+        }
+    }
+    overrides.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(overrides)
+}
+
+/// Impede que as camadas acima do sistema afrouxem as chaves travadas
+fn lock(
+    merged: &mut Table,
+    system: &Table,
+    system_path: &Path,
+    sources: &mut BTreeMap<String, String>,
+) -> Result<()> {
+    let defaults = Table::try_from(Config::default())?;
+    let locked: Vec<String> = match system.get("locked_keys") {
+        Some(value) => value
+            .clone()
+            .try_into()
+            .context("locked_keys must be a list of key names")?,
+        None => return Ok(()),
+    };
+
+    for key in &locked {
+        let Some(admin) = system.get(key).or_else(|| defaults.get(key)) else {
+            anyhow::bail!("Unknown key '{}' in locked_keys", key);
+        };
+        let value = match merged.get(key) {
+            Some(user) => restrict(key, admin, user),
+            None => admin.clone(),
+        };
+        if merged.get(key) != Some(&value) || !sources.contains_key(key) {
+            let nested = format!("{}.", key);
+            sources.retain(|k, _| !k.starts_with(&nested));
+            sources.insert(key.clone(), format!("{} (locked)", system_path.display()));
+        }
+        merged.insert(key.clone(), value);
+    }
+    Ok(())
+}
+
+/// O valor mais restrito entre o do administrador e o das camadas acima
+fn restrict(key: &str, admin: &Value, user: &Value) -> Value {
+    match (admin, user) {
+        (Value::Integer(a), Value::Integer(u)) => Value::Integer(*a.min(u)),
+        (Value::Boolean(a), Value::Boolean(u)) => Value::Boolean(*a || *u),
+        (Value::Array(a), Value::Array(u)) if ADDITIVE_LISTS.contains(&key) => {
+            let mut items = a.clone();
+            items.extend(u.iter().filter(|v| !a.contains(v)).cloned());
+            Value::Array(items)
+        }
+        (Value::Array(a), Value::Array(u)) => {
+            Value::Array(u.iter().filter(|v| a.contains(v)).cloned().collect())
+        }
+        _ => admin.clone(),
+    }
+}
+
+fn join(prefix: &str, key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    let key = if bare {
+        key.to_string()
+    } else {
+        Value::String(key.to_string()).to_string()
+    };
+    if prefix.is_empty() {
+        key
+    } else {
+        format!("{}.{}", prefix, key)
+    }
+}
+
+fn flatten(table: &Table, prefix: &str, out: &mut Vec<(String, Value)>) {
+    for (key, value) in table {
+        let path = join(prefix, key);
+        match value {
+            Value::Table(inner) if !inner.is_empty() => flatten(inner, &path, out),
+            value => out.push((path, value.clone())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-layers-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let layers = Layers {
+            system: dir.join("system.toml"),
+            user: dir.join("user.toml"),
+            extra: Some(dir.join("extra.toml")),
+        };
+        fs::write(
+            &layers.system,
+            r#"
+            locked_keys = ["allowed_commands", "max_timeout_secs", "require_argv", "denied_paths"]
+            allowed_commands = ["ls", "cat", "grep"]
+            max_timeout_secs = 120
+            require_argv = true
+            default_timeout_secs = 30
+            denied_paths = ["/etc/shadow"]
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            &layers.user,
+            r#"
+            allowed_commands = ["ls", "rm"]
+            max_timeout_secs = 9000
+            require_argv = false
+            denied_paths = ["~/.ssh"]
+            [commands.find]
+            forbidden_flags = ["-delete"]
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            layers.extra.as_ref().unwrap(),
+            "default_timeout_secs = 45\n",
+        )
+        .unwrap();
+
+        let effective = load(&layers).unwrap();
+        let config = &effective.config;
+        assert_eq!(config.allowed_commands, ["ls"]);
+        assert_eq!(config.max_timeout_secs, 120);
+        assert!(config.require_argv);
+        assert_eq!(config.denied_paths, ["/etc/shadow", "~/.ssh"]);
+        assert_eq!(config.default_timeout_secs, 45);
+
+        let system = layers.system.display().to_string();
+        assert_eq!(
+            effective.source("allowed_commands"),
+            format!("{} (locked)", system)
+        );
+        assert_eq!(
+            effective.source("default_timeout_secs"),
+            layers.extra.as_ref().unwrap().display().to_string()
+        );
+        assert_eq!(
+            effective.source("commands.find.forbidden_flags"),
+            layers.user.display().to_string()
+        );
+        assert_eq!(effective.source("max_output_bytes"), "default");
+        assert!(effective
+            .render()
+            .unwrap()
+            .contains("max_timeout_secs = 120  # "));
+
+        // Só o arquivo de sistema pode travar chaves
+        fs::write(layers.extra.as_ref().unwrap(), "locked_keys = []\n").unwrap();
+        assert!(load(&layers).is_err());
+
+        // Chaves desconhecidas são erros, com a posição no arquivo
+        fs::write(
+            layers.extra.as_ref().unwrap(),
+            "default_timeout_secs = 45\nmax_timout_secs = 10\n",
+        )
+        .unwrap();
+        let error = format!("{:#}", load(&layers).unwrap_err());
+        assert!(error.contains("line 2, column 1"), "{}", error);
+        assert!(error.contains("unknown field `max_timout_secs`"), "{}", error);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_env_overrides() {
+        let vars = [
+            ("LINUX_MCP_MAX_TIMEOUT_SECS", "30"),
+            ("LINUX_MCP_SANDBOX__DEFAULT_PROFILE", "isolated"),
+            ("LINUX_MCP_ALLOWED_COMMANDS", r#"["ls", "cat"]"#),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+        let overrides = env_overrides(vars).unwrap();
+
+        let mut merged = Table::new();
+        let mut sources = BTreeMap::new();
+        for (name, table) in overrides {
+            merge(&mut merged, table, "", &name, &mut sources);
+        }
+        let config: Config = merged.try_into().unwrap();
+        assert_eq!(config.max_timeout_secs, 30);
+        assert_eq!(config.allowed_commands, ["ls", "cat"]);
+        assert_eq!(config.sandbox.default_profile.as_deref(), Some("isolated"));
+        assert_eq!(
+            sources["sandbox.default_profile"],
+            "LINUX_MCP_SANDBOX__DEFAULT_PROFILE"
+        );
+
+        let unknown = [
+            ("LINUX_MCP_BOGUS".to_string(), "1".to_string()),
+            ("LINUX_MCP__".to_string(), "1".to_string()),
+        ];
+        assert!(env_overrides(unknown).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+//! Migração de arquivos de configuração antigos
+//!
+//! Cada passo converte a versão N na N+1. Todas as camadas são migradas em
+//! memória antes de validadas. Só o arquivo do usuário é regravado (com cópia
+//! do original), e apenas quando algum passo muda suas chaves: um arquivo em
+//! que só a versão mudaria fica como está, com comentários e ordem.
+
+use super::CONFIG_VERSION;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml::{Table, Value};
+
+/// Versão de um arquivo (sem `version`, é da versão 1)
+pub fn version_of(table: &Table) -> Result<u32> {
+    let version = match table.get("version") {
+        None => 1,
+        Some(Value::Integer(v)) => u32::try_from(*v).context("Invalid config version")?,
+        Some(_) => anyhow::bail!("version must be an integer"),
+    };
+    if version > CONFIG_VERSION {
+        anyhow::bail!(
+            "Config version {} is newer than the supported version {}",
+            version,
+            CONFIG_VERSION
+        );
+    }
+    Ok(version)
+}
+
+/// Resultado de `migrate` para um arquivo de versão antiga
+#[derive(Debug, PartialEq)]
+pub struct Migration {
+    /// Versão original do arquivo
+    pub from: u32,
+    /// Se algum passo alterou chaves além de `version`
+    pub changed: bool,
+}
+
+/// Aplica os passos de migração a um arquivo de versão antiga
+pub fn migrate(table: &mut Table) -> Result<Option<Migration>> {
+    let from = version_of(table)?;
+    if from == CONFIG_VERSION {
+        return Ok(None);
+    }
+    table.remove("version");
+    let original = table.clone();
+    for version in from..CONFIG_VERSION {
+        match version {
+            1 => v1_to_v2(table)?,
+            _ => unreachable!("missing migration from config version {}", version),
+        }
+    }
+    let changed = *table != original;
+    table.insert("version".to_string(), Value::Integer(CONFIG_VERSION.into()));
+    Ok(Some(Migration { from, changed }))
+}
+
+/// Versão 1: um único arquivo com `allowed_commands` e `log_path`.
+///
+/// As duas chaves mantêm o significado na versão 2 (as raízes do antigo Safe
+/// RM são os padrões de `[commands.rm]`), então só a versão é gravada. Chaves
+/// ausentes continuam implícitas: gravar os padrões no arquivo do usuário
+/// sobrescreveria os valores de `/etc/linux-mcp/config.toml`.
+fn v1_to_v2(_table: &mut Table) -> Result<()> {
+    Ok(())
+}
+
+/// Migra o arquivo do usuário, guardando o original em `config.toml.v<N>.bak`
+pub fn migrate_file(path: &Path) -> Result<Option<PathBuf>> {
+    let Ok(contents) = fs::read_to_string(path) else {
+        return Ok(None);
+    };
+    // Erros de sintaxe são reportados na leitura das camadas
+    let Ok(mut table) = toml::from_str::<Table>(&contents) else {
+        return Ok(None);
+    };
+    let Some(Migration {
+        from,
+        changed: true,
+    }) = migrate(&mut table)?
+    else {
+        return Ok(None);
+    };
+
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".v{}.bak", from));
+    let backup = PathBuf::from(backup);
+    fs::copy(path, &backup).context("Failed to back up config file")?;
+
+    let migrated = format!(
+        "# Migrado da versão {} (original em {})\n{}",
+        from,
+        backup.display(),
+        toml::to_string_pretty(&table)?
+    );
+    fs::write(path, migrated).context("Failed to write migrated config")?;
+    Ok(Some(backup))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{layers, load_readonly, Config, Layers};
+    use super::*;
+
+    #[test]
+    fn test_migrate() {
+        let mut table: Table = toml::from_str(
+            r#"
+            allowed_commands = ["ls"]
+            log_path = "audit.log"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            migrate(&mut table).unwrap(),
+            Some(Migration {
+                from: 1,
+                changed: false
+            })
+        );
+        let config: Config = table.clone().try_into().unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.allowed_commands, ["ls"]);
+        assert!(!table.contains_key("commands"));
+        assert!(!table.contains_key("sandbox"));
+        assert_eq!(migrate(&mut table).unwrap(), None);
+
+        let mut newer: Table = toml::from_str("version = 99").unwrap();
+        assert!(migrate(&mut newer).is_err());
+    }
+
+    #[test]
+    fn test_migrate_keeps_system_layer() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-migrate-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let layers = Layers {
+            system: dir.join("system.toml"),
+            user: dir.join("user.toml"),
+            extra: None,
+        };
+        fs::write(
+            &layers.system,
+            "max_timeout_secs = 120\ndenied_paths = [\"/srv/secret\"]\n",
+        )
+        .unwrap();
+        let v1 = "# comentário\nallowed_commands = [\"ls\"]\n";
+        fs::write(&layers.user, v1).unwrap();
+
+        // Nenhum passo muda as chaves da versão 1: o arquivo não é regravado
+        assert_eq!(migrate_file(&layers.user).unwrap(), None);
+        assert_eq!(fs::read_to_string(&layers.user).unwrap(), v1);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 2);
+        let config = layers::load(&layers).unwrap().config;
+        assert_eq!(config.allowed_commands, ["ls"]);
+        assert_eq!(config.max_timeout_secs, 120);
+        assert_eq!(config.denied_paths, ["/srv/secret"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_load_readonly() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-readonly-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let layers = Layers {
+            system: dir.join("system.toml"),
+            user: dir.join("user.toml"),
+            extra: None,
+        };
+        let v1 = "allowed_commands = [\"ls\"]\n";
+        fs::write(&layers.user, v1).unwrap();
+
+        let config = load_readonly(&layers).unwrap().config;
+        assert_eq!(config.allowed_commands, ["ls"]);
+        assert_eq!(fs::read_to_string(&layers.user).unwrap(), v1);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
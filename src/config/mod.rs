@@ -0,0 +1,1068 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+mod layers;
+mod migrate;
+
+pub use layers::{Effective, Layers, SYSTEM_CONFIG_PATH};
+
+/// Versão atual do formato do `config.toml`
+pub const CONFIG_VERSION: u32 = 2;
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Versão do formato (arquivos sem `version` são da versão 1 e são migrados)
+    #[serde(default = "default_version")]
+    pub version: u32,
+    #[serde(default = "default_allowed_commands")]
+    pub allowed_commands: Vec<String>,
+    #[serde(default = "default_log_path")]
+    pub log_path: String,
+    /// Integridade do audit log (`[audit]`)
+    #[serde(default)]
+    pub audit: AuditConfig,
+    /// Mascaramento de segredos na saída, nos resources e no audit log (`[redaction]`)
+    #[serde(default)]
+    pub redaction: RedactionConfig,
+    /// Comandos que exigem aprovação humana antes de executar (`[confirm]`)
+    #[serde(default)]
+    pub confirm: ConfirmConfig,
+    /// Exige que `execute_command` receba `argv` (execução direta, sem `sh -c`)
+    #[serde(default)]
+    pub require_argv: bool,
+    /// Chaves que as camadas acima de `/etc/linux-mcp/config.toml` não podem afrouxar
+    /// (só é aceita no arquivo de sistema)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub locked_keys: Vec<String>,
+    /// Timeout padrão de `execute_command` em segundos
+    #[serde(default = "default_timeout_secs")]
+    pub default_timeout_secs: u64,
+    /// Maior timeout que um cliente pode solicitar em `timeout_secs`
+    #[serde(default = "default_max_timeout_secs")]
+    pub max_timeout_secs: u64,
+    /// Máximo de bytes de stdout/stderr mostrados no resultado
+    #[serde(default = "default_max_output_bytes")]
+    pub max_output_bytes: usize,
+    /// Máximo de linhas de stdout/stderr mostradas no resultado
+    #[serde(default = "default_max_output_lines")]
+    pub max_output_lines: usize,
+    /// Parte mostrada quando a saída excede os limites ("head" ou "tail")
+    #[serde(default)]
+    pub output_truncation: crate::tools::Truncation,
+    /// Máximo de bytes guardados em memória por stream (o excedente é descartado)
+    #[serde(default = "default_max_captured_bytes")]
+    pub max_captured_bytes: usize,
+    /// Quantas saídas truncadas ficam disponíveis para `get_command_output`
+    #[serde(default = "default_output_retention")]
+    pub output_retention: usize,
+    /// Máximo de jobs em background rodando ao mesmo tempo
+    #[serde(default = "default_max_concurrent_jobs")]
+    pub max_concurrent_jobs: usize,
+    /// Quantos jobs finalizados ficam disponíveis para consulta
+    #[serde(default = "default_job_retention")]
+    pub job_retention: usize,
+    /// Timeout padrão e máximo (em segundos) de um job em background
+    #[serde(default = "default_job_timeout_secs")]
+    pub job_timeout_secs: u64,
+    /// Variáveis de ambiente do servidor repassadas aos comandos (aceita globs, ex: `LC_*`)
+    #[serde(default = "default_env_allowlist")]
+    pub env_allowlist: Vec<String>,
+    /// Variáveis nunca repassadas, mesmo se permitidas (comparação sem diferenciar maiúsculas)
+    #[serde(default = "default_env_denylist")]
+    pub env_denylist: Vec<String>,
+    /// Tamanho máximo do `stdin` enviado a um comando
+    #[serde(default = "default_max_stdin_bytes")]
+    pub max_stdin_bytes: usize,
+    /// Caminhos sensíveis bloqueados para qualquer comando (aceita `~` e globs)
+    #[serde(default = "default_denied_paths")]
+    pub denied_paths: Vec<String>,
+    /// Destinos permitidos para redirecionamentos de escrita (`>`, `>>`)
+    #[serde(default = "default_writable_paths")]
+    pub writable_paths: Vec<String>,
+    /// Perfis de sandbox (`[sandbox.profiles.<nome>]`)
+    #[serde(default = "default_sandbox")]
+    pub sandbox: SandboxConfig,
+    /// Políticas de argumentos por comando (`[commands.<nome>]`)
+    #[serde(default = "default_command_policies")]
+    pub commands: BTreeMap<String, CommandPolicy>,
+    /// Perfis de acesso atribuídos às credenciais dos transportes (`[profiles.<nome>]`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, Profile>,
+    /// Transporte HTTP (`linux-mcp serve --transport http`)
+    #[serde(default)]
+    pub http: HttpConfig,
+    /// Transporte por socket Unix (`linux-mcp serve --socket`)
+    #[serde(default)]
+    pub socket: SocketConfig,
+    /// Perfil aplicado por [`Config::with_profile`] (não vem dos arquivos)
+    #[serde(skip)]
+    pub active_profile: Option<String>,
+    /// Políticas gerais de `[commands]` que o perfil ativo redefine; continuam
+    /// valendo junto com as do perfil (ver [`Config::command_policies`])
+    #[serde(skip)]
+    pub base_commands: BTreeMap<String, CommandPolicy>,
+    /// Arquivos das camadas que compõem esta configuração (ver [`Layers::files`])
+    #[serde(skip)]
+    pub config_files: Vec<PathBuf>,
+}
+
+/// Perfil de acesso de uma conexão
+///
+/// Os campos definidos substituem os do config para as sessões com o perfil;
+/// os ausentes mantêm os valores gerais.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Profile {
+    /// Tools que a conexão pode listar e chamar (ausente libera todas)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+    /// Allowlist de comandos do perfil, no lugar de `allowed_commands`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allowed_commands: Option<Vec<String>>,
+    /// Políticas de argumentos que substituem as de `[commands.<nome>]`
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub commands: BTreeMap<String, CommandPolicy>,
+    /// Raízes fora das quais nenhum caminho pode ser usado (aceita `~` e globs;
+    /// ausente: sem restrição além de `denied_paths`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_roots: Option<Vec<String>>,
+    /// Se comandos podem ser executados com `use_polkit`
+    pub allow_polkit: bool,
+    /// Nomes de cliente MCP (`clientInfo.name` do `initialize`, aceita globs)
+    /// que recebem o perfil quando a conexão não tem outro
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            tools: None,
+            allowed_commands: None,
+            commands: BTreeMap::new(),
+            path_roots: None,
+            allow_polkit: true,
+            clients: Vec::new(),
+        }
+    }
+}
+
+/// Opções do transporte HTTP (streamable HTTP do MCP)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    /// Endereço e porta de escuta
+    pub bind: String,
+    /// Caminho do endpoint MCP
+    pub path: String,
+    /// Certificado e chave do servidor (PEM); com eles o transporte usa HTTPS
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_key: Option<String>,
+    /// CA que assina os certificados de cliente aceitos (mTLS, exige `tls_cert`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_ca: Option<String>,
+    /// Tokens aceitos em `Authorization: Bearer` (`[[http.tokens]]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tokens: Vec<HttpToken>,
+    /// Certificados de cliente aceitos (`[[http.clients]]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub clients: Vec<HttpClient>,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            bind: "127.0.0.1:8080".to_string(),
+            path: "/mcp".to_string(),
+            tls_cert: None,
+            tls_key: None,
+            client_ca: None,
+            tokens: Vec::new(),
+            clients: Vec::new(),
+        }
+    }
+}
+
+/// Token de acesso ao transporte HTTP
+///
+/// Só o SHA-256 do token fica no config (ex: `printf %s "$TOKEN" | sha256sum`).
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpToken {
+    /// Nome registrado no audit log
+    pub name: String,
+    /// SHA-256 do token, em hexadecimal
+    pub sha256: String,
+    /// Perfil de `[profiles]` (ausente: acesso sem restrição de perfil)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Certificado de cliente aceito no mTLS
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct HttpClient {
+    /// Nome registrado no audit log
+    pub name: String,
+    /// SHA-256 do certificado em DER (ex: `openssl x509 -in client.crt -outform der | sha256sum`)
+    pub cert_sha256: String,
+    /// Perfil de `[profiles]` (ausente: acesso sem restrição de perfil)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+/// Opções do transporte por socket Unix
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SocketConfig {
+    /// Permissões do arquivo do socket, em octal
+    pub mode: String,
+    /// Processos de outros usuários aceitos (`[[socket.peers]]`); o usuário do
+    /// próprio servidor é aceito sem perfil se nenhuma entrada o descrever
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub peers: Vec<SocketPeer>,
+}
+
+impl Default for SocketConfig {
+    fn default() -> Self {
+        Self {
+            mode: "0600".to_string(),
+            peers: Vec::new(),
+        }
+    }
+}
+
+/// Cliente do socket, identificado pelas credenciais do processo (SO_PEERCRED)
+///
+/// A entrada vale se o uid e o gid informados forem os do processo; vale a
+/// primeira da lista.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+pub struct SocketPeer {
+    /// Nome registrado no audit log
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uid: Option<u32>,
+    /// Grupo primário do processo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gid: Option<u32>,
+    /// Perfil de `[profiles]` (ausente: acesso sem restrição de perfil)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<String>,
+}
+
+impl SocketConfig {
+    /// Permissões de `mode`
+    pub fn mode(&self) -> Result<u32> {
+        u32::from_str_radix(&self.mode, 8)
+            .ok()
+            .filter(|mode| *mode <= 0o777)
+            .with_context(|| format!("Invalid [socket] mode '{}'", self.mode))
+    }
+}
+
+/// Opções do audit log
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuditConfig {
+    /// Chave secreta do HMAC de cada registro (relativo ao diretório de config).
+    /// Deve ficar fora do alcance dos comandos, ex: arquivo de root legível só pelo servidor
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hmac_key_file: Option<String>,
+    /// Rotaciona o log ao atingir este tamanho em MiB (0 desativa)
+    pub max_size_mb: u64,
+    /// Rotaciona o log quando o registro mais antigo passa desta idade em horas (0 desativa)
+    pub max_age_hours: u64,
+    /// Quantos arquivos rotacionados são mantidos
+    pub retention: usize,
+    /// Comprime os arquivos rotacionados com gzip
+    pub compress: bool,
+    /// Recusa executar o comando se o registro ALLOWED não puder ser gravado
+    pub fail_closed: bool,
+    /// Destinos adicionais dos registros (`[[audit.sinks]]`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub sinks: Vec<AuditSink>,
+}
+
+impl Default for AuditConfig {
+    fn default() -> Self {
+        Self {
+            hmac_key_file: None,
+            max_size_mb: 10,
+            max_age_hours: 0,
+            retention: 10,
+            compress: true,
+            fail_closed: false,
+            sinks: Vec::new(),
+        }
+    }
+}
+
+/// Aprovação humana via elicitation do MCP
+///
+/// Além destas regex, `[commands.<nome>]` pode exigir aprovação com `confirm`
+/// e `confirm_subcommands`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfirmConfig {
+    /// Regex aplicadas a cada comando simples, normalizado e também sem flags,
+    /// e à linha inteira (ex: `^(apt|dnf) install`)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+    /// Tempo máximo de espera pela resposta do usuário; sem resposta, o comando é negado
+    pub timeout_secs: u64,
+}
+
+impl Default for ConfirmConfig {
+    fn default() -> Self {
+        Self {
+            patterns: Vec::new(),
+            timeout_secs: 120,
+        }
+    }
+}
+
+/// Mascaramento de segredos (ver `crate::redact`)
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionConfig {
+    pub enabled: bool,
+    /// Detectores embutidos ativos: private_key, aws_access_key, aws_secret_key,
+    /// jwt, bearer_token, password e url_credentials
+    pub detectors: Vec<String>,
+    /// Regexes adicionais; com um grupo `secret`, só ele é mascarado
+    pub patterns: Vec<String>,
+}
+
+impl Default for RedactionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            detectors: crate::redact::detector_names(),
+            patterns: Vec::new(),
+        }
+    }
+}
+
+/// Destino adicional do audit log
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+pub enum AuditSink {
+    /// journald, com cada campo do registro como `LINUX_MCP_<CAMPO>`
+    Journald,
+    /// syslog local, com o registro em JSON como mensagem
+    Syslog {
+        #[serde(default = "default_syslog_socket")]
+        socket: String,
+        #[serde(default)]
+        facility: SyslogFacility,
+    },
+    /// Cópia em outro arquivo (relativo ao diretório de config), com a mesma rotação
+    File { path: String },
+}
+
+/// Facility usada nas mensagens de syslog
+#[derive(Clone, Copy, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyslogFacility {
+    User,
+    Daemon,
+    #[default]
+    Auth,
+    Authpriv,
+    Local0,
+    Local1,
+    Local2,
+    Local3,
+    Local4,
+    Local5,
+    Local6,
+    Local7,
+}
+
+/// Configuração do sandbox de comandos executados sem elevação
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Perfil aplicado a comandos sem `sandbox` em `[commands.<nome>]`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+    pub profiles: BTreeMap<String, SandboxProfile>,
+}
+
+/// Camadas de isolamento aplicadas ao processo filho
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SandboxProfile {
+    /// Novos namespaces de usuário, mount e PID (com `/proc` próprio)
+    pub namespaces: bool,
+    /// Se false (e `namespaces` ativo), o comando roda em um namespace de rede vazio
+    pub network: bool,
+    /// Landlock: escrita só dentro de `writable_paths`
+    pub landlock: bool,
+    /// Raízes graváveis pelo Landlock (padrão: `writable_paths` global)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub writable_paths: Option<Vec<String>>,
+    /// Filtro seccomp que bloqueia syscalls perigosas (ptrace, mount, bpf, kexec...)
+    pub seccomp: bool,
+    /// Executa mesmo se o kernel não suportar alguma camada (ex: Landlock desabilitado)
+    pub best_effort: bool,
+    /// RLIMIT_CPU em segundos
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cpu_secs: Option<u64>,
+    /// RLIMIT_AS em MiB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_memory_mb: Option<u64>,
+    /// RLIMIT_FSIZE em MiB
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_file_size_mb: Option<u64>,
+    /// RLIMIT_NPROC (conta todos os processos do usuário, não só os do comando)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_processes: Option<u64>,
+}
+
+impl Default for SandboxProfile {
+    fn default() -> Self {
+        Self {
+            namespaces: true,
+            network: false,
+            landlock: true,
+            writable_paths: None,
+            seccomp: true,
+            best_effort: false,
+            max_cpu_secs: None,
+            max_memory_mb: None,
+            max_file_size_mb: None,
+            max_processes: None,
+        }
+    }
+}
+
+/// Restrições de argumentos para um comando da allowlist
+///
+/// O comando continua precisando estar em `allowed_commands`; a política só restringe.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CommandPolicy {
+    /// Se não vazia, o subcomando (primeiro argumento que não é flag) precisa estar nesta lista
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_subcommands: Vec<String>,
+    /// Subcomandos sempre bloqueados (ex: `poweroff` para o systemctl)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub denied_subcommands: Vec<String>,
+    /// Flags proibidas (ex: `-exec`, `-delete` para o find)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub forbidden_flags: Vec<String>,
+    /// Se não vazia, todo argumento precisa casar com ao menos uma destas regex
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_arg_patterns: Vec<String>,
+    /// Nenhum argumento pode casar com estas regex
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub denied_arg_patterns: Vec<String>,
+    /// Se não vazia, os caminhos alvo do comando precisam estar dentro destas raízes
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_paths: Vec<String>,
+    /// Caminhos bloqueados para este comando, além de `denied_paths`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub denied_paths: Vec<String>,
+    /// O comando só pode ser executado com `use_polkit = true`
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub requires_polkit: bool,
+    /// Perfil de `[sandbox.profiles]` aplicado ao comando
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sandbox: Option<String>,
+    /// Toda execução do comando exige aprovação do usuário
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub confirm: bool,
+    /// Subcomandos que exigem aprovação do usuário (ex: `restart` para o systemctl)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub confirm_subcommands: Vec<String>,
+}
+
+fn to_vec(items: &[&str]) -> Vec<String> {
+    items.iter().map(|s| s.to_string()).collect()
+}
+
+fn default_version() -> u32 {
+    CONFIG_VERSION
+}
+
+fn default_allowed_commands() -> Vec<String> {
+    to_vec(crate::tools::ALLOWED_COMMANDS)
+}
+
+fn default_log_path() -> String {
+    "audit.log".to_string() // Relativo ao diretório de config
+}
+
+fn default_syslog_socket() -> String {
+    "/dev/log".to_string()
+}
+
+fn default_timeout_secs() -> u64 {
+    60
+}
+
+fn default_max_timeout_secs() -> u64 {
+    600
+}
+
+fn default_max_output_bytes() -> usize {
+    32 * 1024
+}
+
+fn default_max_output_lines() -> usize {
+    500
+}
+
+fn default_max_captured_bytes() -> usize {
+    8 * 1024 * 1024
+}
+
+fn default_output_retention() -> usize {
+    20
+}
+
+fn default_max_concurrent_jobs() -> usize {
+    4
+}
+
+fn default_job_retention() -> usize {
+    20
+}
+
+fn default_job_timeout_secs() -> u64 {
+    6 * 60 * 60
+}
+
+fn default_env_allowlist() -> Vec<String> {
+    to_vec(&[
+        "PATH",
+        "HOME",
+        "USER",
+        "LOGNAME",
+        "SHELL",
+        "LANG",
+        "LANGUAGE",
+        "LC_*",
+        "TERM",
+        "TZ",
+        "TMPDIR",
+        // Necessárias para o diálogo gráfico do pkexec
+        "DISPLAY",
+        "WAYLAND_DISPLAY",
+        "XAUTHORITY",
+        "XDG_RUNTIME_DIR",
+        "DBUS_SESSION_BUS_ADDRESS",
+    ])
+}
+
+fn default_env_denylist() -> Vec<String> {
+    to_vec(&[
+        "*TOKEN*",
+        "*SECRET*",
+        "*PASSWORD*",
+        "*PASSWD*",
+        "*API_KEY*",
+        "*ACCESS_KEY*",
+        "*CREDENTIAL*",
+        "AWS_*",
+        "SSH_AUTH_SOCK",
+    ])
+}
+
+fn default_max_stdin_bytes() -> usize {
+    1024 * 1024
+}
+
+fn default_denied_paths() -> Vec<String> {
+    to_vec(&[
+        "/etc/shadow",
+        "/etc/gshadow",
+        "/etc/sudoers",
+        "/etc/sudoers.d",
+        "/etc/ssh/ssh_host_*_key",
+        "/proc/*/environ",
+        "~/.ssh",
+        "~/.gnupg",
+        "~/.aws",
+    ])
+}
+
+fn default_writable_paths() -> Vec<String> {
+    to_vec(&["/tmp", "/var/tmp", "/dev/null"])
+}
+
+/// Perfil `isolated`: sem rede, sem escrita fora de `/tmp` e com limites de recursos
+fn default_sandbox() -> SandboxConfig {
+    SandboxConfig {
+        default_profile: None,
+        profiles: BTreeMap::from([(
+            "isolated".to_string(),
+            SandboxProfile {
+                writable_paths: Some(to_vec(&["/tmp", "/dev/null"])),
+                max_cpu_secs: Some(300),
+                max_memory_mb: Some(2048),
+                max_file_size_mb: Some(256),
+                ..Default::default()
+            },
+        )]),
+    }
+}
+
+fn default_command_policies() -> BTreeMap<String, CommandPolicy> {
+    BTreeMap::from([
+        (
+            "find".to_string(),
+            CommandPolicy {
+                forbidden_flags: to_vec(&[
+                    "-exec", "-execdir", "-ok", "-okdir", "-delete", "-fprint", "-fprint0",
+                    "-fprintf", "-fls",
+                ]),
+                ..Default::default()
+            },
+        ),
+        (
+            "rm".to_string(),
+            CommandPolicy {
+                allowed_paths: to_vec(&[
+                    "/tmp",
+                    "/var/tmp",
+                    "/var/log",
+                    "~/.cache",
+                    "~/.local/share/Trash",
+                ]),
+                ..Default::default()
+            },
+        ),
+        (
+            "systemctl".to_string(),
+            CommandPolicy {
+                denied_subcommands: to_vec(&[
+                    "poweroff", "reboot", "halt", "kexec", "emergency", "rescue", "isolate",
+                ]),
+                ..Default::default()
+            },
+        ),
+    ])
+}
+
+impl Config {
+    /// Valida campos que o serde não consegue verificar (ex: regex)
+    pub fn validate(&self) -> Result<()> {
+        if self.max_output_bytes == 0 || self.max_output_lines == 0 {
+            anyhow::bail!("max_output_bytes and max_output_lines must be greater than zero");
+        }
+
+        let path_rules = self
+            .commands
+            .values()
+            .flat_map(|p| p.allowed_paths.iter().chain(&p.denied_paths))
+            .chain(&self.denied_paths)
+            .chain(&self.writable_paths);
+        for rule in path_rules {
+            glob::Pattern::new(rule)
+                .with_context(|| format!("Invalid path rule '{}'", rule))?;
+        }
+        for pattern in self.env_allowlist.iter().chain(&self.env_denylist) {
+            glob::Pattern::new(pattern)
+                .with_context(|| format!("Invalid environment variable pattern '{}'", pattern))?;
+        }
+
+        crate::redact::Redactor::new(&self.redaction)?;
+        for pattern in &self.confirm.patterns {
+            regex::Regex::new(pattern)
+                .with_context(|| format!("Invalid confirm pattern '{}'", pattern))?;
+        }
+        if self.confirm.timeout_secs == 0 {
+            anyhow::bail!("[confirm] timeout_secs must be greater than zero");
+        }
+
+        self.validate_commands()?;
+        self.validate_profiles()?;
+        self.validate_http()?;
+        self.validate_socket()?;
+
+        for (name, profile) in &self.sandbox.profiles {
+            // O Landlock precisa de caminhos reais, não padrões
+            let paths = profile.writable_paths.as_ref().unwrap_or(&self.writable_paths);
+            if let Some(path) = paths.iter().find(|p| p.contains(['*', '?', '['])) {
+                anyhow::bail!(
+                    "Sandbox profile '{}' cannot use glob '{}' as a writable path",
+                    name,
+                    path
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Políticas de `[commands.<nome>]` e perfis de sandbox referenciados
+    fn validate_commands(&self) -> Result<()> {
+        for (name, policy) in &self.commands {
+            for pattern in policy
+                .allowed_arg_patterns
+                .iter()
+                .chain(&policy.denied_arg_patterns)
+            {
+                regex::Regex::new(pattern).with_context(|| {
+                    format!("Invalid argument pattern '{}' in [commands.{}]", pattern, name)
+                })?;
+            }
+        }
+
+        let profile_refs = self
+            .commands
+            .values()
+            .filter_map(|p| p.sandbox.as_ref())
+            .chain(&self.sandbox.default_profile);
+        for profile in profile_refs {
+            if !self.sandbox.profiles.contains_key(profile) {
+                anyhow::bail!("Unknown sandbox profile '{}'", profile);
+            }
+        }
+        Ok(())
+    }
+
+    /// Perfis de acesso (`[profiles.<nome>]`), já aplicados sobre o config
+    fn validate_profiles(&self) -> Result<()> {
+        for (name, profile) in &self.profiles {
+            let patterns = profile
+                .commands
+                .values()
+                .flat_map(|p| p.allowed_paths.iter().chain(&p.denied_paths))
+                .chain(profile.path_roots.iter().flatten())
+                .chain(&profile.clients);
+            for pattern in patterns {
+                glob::Pattern::new(pattern).with_context(|| {
+                    format!("Invalid pattern '{}' in [profiles.{}]", pattern, name)
+                })?;
+            }
+            self.with_profile(name)
+                .validate_commands()
+                .with_context(|| format!("Invalid [profiles.{}]", name))?;
+        }
+        Ok(())
+    }
+
+    /// Config efetivo das sessões com o perfil `name`
+    ///
+    /// Um perfil só restringe a configuração geral (inclusive as chaves
+    /// travadas em `locked_keys`): sua allowlist é a interseção com a geral, e
+    /// as políticas gerais dos comandos que ele redefine continuam valendo.
+    ///
+    /// Um perfil inexistente (ex: removido numa recarga) não altera as regras,
+    /// mas fica registrado em `active_profile`; as tools já são todas negadas
+    /// para ele (ver [`crate::transport::Access::allows_tool`]).
+    pub fn with_profile(&self, name: &str) -> Config {
+        let mut config = self.clone();
+        if let Some(profile) = self.profiles.get(name) {
+            if let Some(commands) = &profile.allowed_commands {
+                config.allowed_commands = commands
+                    .iter()
+                    .filter(|command| self.allowed_commands.contains(command))
+                    .cloned()
+                    .collect();
+            }
+            for (command, policy) in &profile.commands {
+                if let Some(base) = config.commands.insert(command.clone(), policy.clone()) {
+                    config.base_commands.insert(command.clone(), base);
+                }
+            }
+        }
+        config.active_profile = Some(name.to_string());
+        config
+    }
+
+    /// Políticas de `[commands.<name>]` que valem para o comando, com o nome da
+    /// regra de cada uma (`commands.<name>` ou `profiles.<perfil>.commands.<name>`)
+    ///
+    /// O comando precisa passar por todas: a do perfil ativo e a geral que ela
+    /// redefine.
+    pub fn command_policies(&self, name: &str) -> Vec<(String, &CommandPolicy)> {
+        let key = format!("commands.{}", name);
+        let mut policies: Vec<_> = self
+            .commands
+            .get(name)
+            .map(|policy| (self.rule_key(&key), policy))
+            .into_iter()
+            .collect();
+        policies.extend(self.base_commands.get(name).map(|policy| (key, policy)));
+        policies
+    }
+
+    /// Falha se o perfil não existir em `[profiles]`
+    pub fn require_profile(&self, name: &str) -> Result<()> {
+        if !self.profiles.contains_key(name) {
+            anyhow::bail!("Unknown profile '{}' (not defined in [profiles])", name);
+        }
+        Ok(())
+    }
+
+    /// Perfil ativo, se houver
+    pub fn profile(&self) -> Option<(&str, &Profile)> {
+        let name = self.active_profile.as_deref()?;
+        Some((name, self.profiles.get(name)?))
+    }
+
+    /// Se a allowlist do perfil ativo libera o `rm`
+    ///
+    /// Sem perfil, o `rm` não precisa estar em `allowed_commands` (Safe RM);
+    /// um perfil com allowlist própria só o libera se ele estiver nela.
+    pub fn profile_allows_rm(&self) -> bool {
+        self.profile()
+            .and_then(|(_, profile)| profile.allowed_commands.as_ref())
+            .is_none_or(|commands| commands.iter().any(|c| c == "rm"))
+    }
+
+    /// Perfil de `[profiles]` associado ao nome do cliente MCP (o primeiro, em
+    /// ordem alfabética, cujo `clients` casa com o nome)
+    pub fn client_profile(&self, client: &str) -> Option<String> {
+        self.profiles
+            .iter()
+            .find(|(_, profile)| {
+                profile.clients.iter().any(|pattern| {
+                    glob::Pattern::new(pattern).is_ok_and(|p| p.matches(client))
+                })
+            })
+            .map(|(name, _)| name.clone())
+    }
+
+    /// Nome da regra no audit log: `profiles.<perfil>.<chave>` quando o perfil
+    /// ativo redefine a chave (`allowed_commands` ou `commands.<nome>...`)
+    pub fn rule_key(&self, key: &str) -> String {
+        let Some((name, profile)) = self.profile() else {
+            return key.to_string();
+        };
+        let overridden = match key.strip_prefix("commands.") {
+            Some(rest) => {
+                let command = rest.split('.').next().unwrap_or(rest);
+                profile.commands.contains_key(command)
+            }
+            None => key == "allowed_commands" && profile.allowed_commands.is_some(),
+        };
+        if overridden {
+            format!("profiles.{}.{}", name, key)
+        } else {
+            key.to_string()
+        }
+    }
+
+    /// Credenciais e perfis do transporte HTTP
+    fn validate_http(&self) -> Result<()> {
+        let http = &self.http;
+        if !http.path.starts_with('/') {
+            anyhow::bail!("[http] path must start with '/'");
+        }
+        if http.tls_cert.is_some() != http.tls_key.is_some() {
+            anyhow::bail!("[http] tls_cert and tls_key must be set together");
+        }
+        if http.client_ca.is_some() && http.tls_cert.is_none() {
+            anyhow::bail!("[http] client_ca requires tls_cert and tls_key");
+        }
+        let credentials = http
+            .tokens
+            .iter()
+            .map(|t| (&t.name, &t.sha256, &t.profile))
+            .chain(
+                http.clients
+                    .iter()
+                    .map(|c| (&c.name, &c.cert_sha256, &c.profile)),
+            );
+        let mut names = Vec::new();
+        for (name, digest, profile) in credentials {
+            if names.contains(&name) {
+                anyhow::bail!("Duplicate [http] credential name '{}'", name);
+            }
+            names.push(name);
+            if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                anyhow::bail!(
+                    "[http] credential '{}' must have a 64-digit hex SHA-256",
+                    name
+                );
+            }
+            if let Some(profile) = profile {
+                if !self.profiles.contains_key(profile) {
+                    anyhow::bail!(
+                        "Unknown profile '{}' for [http] credential '{}'",
+                        profile,
+                        name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_socket(&self) -> Result<()> {
+        self.socket.mode()?;
+        let mut names = Vec::new();
+        for peer in &self.socket.peers {
+            if names.contains(&&peer.name) {
+                anyhow::bail!("Duplicate [[socket.peers]] name '{}'", peer.name);
+            }
+            names.push(&peer.name);
+            if peer.uid.is_none() && peer.gid.is_none() {
+                anyhow::bail!("[[socket.peers]] '{}' must set uid or gid", peer.name);
+            }
+            if let Some(profile) = &peer.profile {
+                if !self.profiles.contains_key(profile) {
+                    anyhow::bail!(
+                        "Unknown profile '{}' for [[socket.peers]] '{}'",
+                        profile,
+                        peer.name
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Problemas que não impedem o uso da configuração
+    pub fn warnings(&self) -> Vec<String> {
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let dirs: Vec<PathBuf> = std::env::split_paths(&path).collect();
+        let mut warnings = Vec::new();
+        for command in &self.allowed_commands {
+            let found = if command.contains('/') {
+                Path::new(command).is_file()
+            } else {
+                dirs.iter().any(|dir| dir.join(command).is_file())
+            };
+            if !found {
+                warnings.push(format!("allowed command '{}' was not found on PATH", command));
+            }
+        }
+        // `rm` não precisa estar na allowlist: é liberado pela política Safe RM
+        for name in self.commands.keys().filter(|n| *n != "rm") {
+            if !self.allowed_commands.contains(name) {
+                warnings.push(format!(
+                    "[commands.{}] has no effect: '{}' is not in allowed_commands",
+                    name, name
+                ));
+            }
+        }
+        let tools = crate::LinuxMcpServer::tool_names();
+        for (name, profile) in &self.profiles {
+            for tool in profile.tools.iter().flatten() {
+                if !tools.contains(tool) {
+                    warnings.push(format!("[profiles.{}] lists unknown tool '{}'", name, tool));
+                }
+            }
+            // Perfis só restringem: o resto da allowlist do perfil é ignorado
+            let commands = profile.allowed_commands.iter().flatten();
+            for command in commands.filter(|c| *c != "rm" && !self.allowed_commands.contains(c)) {
+                warnings.push(format!(
+                    "[profiles.{}] allows '{}', which is not in allowed_commands; it stays blocked",
+                    name, command
+                ));
+            }
+        }
+        warnings
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            version: CONFIG_VERSION,
+            allowed_commands: default_allowed_commands(),
+            log_path: default_log_path(),
+            audit: AuditConfig::default(),
+            redaction: RedactionConfig::default(),
+            confirm: ConfirmConfig::default(),
+            require_argv: false,
+            locked_keys: Vec::new(),
+            default_timeout_secs: default_timeout_secs(),
+            max_timeout_secs: default_max_timeout_secs(),
+            max_output_bytes: default_max_output_bytes(),
+            max_output_lines: default_max_output_lines(),
+            output_truncation: Default::default(),
+            max_captured_bytes: default_max_captured_bytes(),
+            output_retention: default_output_retention(),
+            max_concurrent_jobs: default_max_concurrent_jobs(),
+            job_retention: default_job_retention(),
+            job_timeout_secs: default_job_timeout_secs(),
+            env_allowlist: default_env_allowlist(),
+            env_denylist: default_env_denylist(),
+            max_stdin_bytes: default_max_stdin_bytes(),
+            denied_paths: default_denied_paths(),
+            writable_paths: default_writable_paths(),
+            sandbox: default_sandbox(),
+            commands: default_command_policies(),
+            profiles: BTreeMap::new(),
+            http: HttpConfig::default(),
+            socket: SocketConfig::default(),
+            active_profile: None,
+            base_commands: BTreeMap::new(),
+            config_files: Vec::new(),
+        }
+    }
+}
+
+pub fn get_config_dir() -> Result<PathBuf> {
+    let mut path = dirs::config_dir().context("Failed to get config directory")?;
+    path.push("linux-mcp");
+    Ok(path)
+}
+
+pub fn get_config_path() -> Result<PathBuf> {
+    Ok(get_config_dir()?.join("config.toml"))
+}
+
+/// Cria um arquivo de configuração com os valores padrão
+pub fn write_default(path: &Path) -> Result<()> {
+    let toml_str = toml::to_string_pretty(&Config::default())?;
+    fs::write(path, toml_str).context("Failed to write default config")
+}
+
+/// Lê e valida um único arquivo, sem as demais camadas
+pub fn read(path: &Path) -> Result<Config> {
+    let contents = fs::read_to_string(path).context("Failed to read config file")?;
+    let mut table: toml::Table =
+        toml::from_str(&contents).context("Failed to parse config file")?;
+    let config: Config = match migrate::migrate(&mut table)? {
+        Some(migration) if migration.changed => table.try_into(),
+        _ => toml::from_str(&contents),
+    }
+    .context("Failed to parse config file")?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// Carrega a configuração do servidor, criando o arquivo padrão do usuário
+/// quando não há nenhum e migrando o existente
+pub fn load(layers: &Layers) -> Result<Effective> {
+    let config_dir = get_config_dir()?;
+    if !config_dir.exists() {
+        fs::create_dir_all(&config_dir).context("Failed to create config directory")?;
+    }
+
+    // Com uma configuração de sistema, os padrões do usuário a sobrescreveriam
+    if !layers.user.exists() && !layers.system.exists() {
+        write_default(&layers.user)?;
+    }
+    if let Some(backup) = migrate::migrate_file(&layers.user)? {
+        eprintln!(
+            "{} migrated to config version {} (original saved as {})",
+            layers.user.display(),
+            CONFIG_VERSION,
+            backup.display()
+        );
+    }
+
+    layers::load(layers)
+}
+
+/// Carrega a configuração sem alterar nada no disco (subcomandos de consulta)
+///
+/// Sem arquivos, valem os padrões; arquivos antigos são migrados só em memória.
+pub fn load_readonly(layers: &Layers) -> Result<Effective> {
+    layers::load(layers)
+}
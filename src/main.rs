@@ -1,36 +1,60 @@
 mod audit;
+mod cli;
 mod config;
 mod prompts;
+mod redact;
+mod reload;
 mod resources;
 mod tools;
+mod transport;
 
 use anyhow::Result;
-use rmcp::handler::server::tool::ToolRouter;
+use clap::Parser;
+use rmcp::handler::server::tool::{ToolCallContext, ToolRouter};
 use rmcp::handler::server::wrapper::Parameters;
 use rmcp::model::*;
-use rmcp::service::RequestContext;
-use rmcp::{tool, tool_handler, tool_router, ErrorData, RoleServer, ServerHandler, ServiceExt};
+use rmcp::service::{NotificationContext, RequestContext};
+use rmcp::{tool, tool_router, ErrorData, Peer, RoleServer, ServerHandler, ServiceExt};
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, OnceLock};
 use sysinfo::System;
 use tokio::sync::Mutex;
-use config::Config;
+use tokio_util::sync::CancellationToken;
 
 /// Servidor MCP Linux
 #[derive(Clone)]
 pub struct LinuxMcpServer {
     tool_router: ToolRouter<Self>,
     system: Arc<Mutex<System>>,
-    config: Arc<Config>,
+    config: Arc<reload::ConfigHandle>,
+    outputs: tools::OutputStore,
+    jobs: tools::JobManager,
+    /// Identifica esta conexão no audit log
+    session: String,
+    /// Perfil escolhido ao iniciar o servidor (`--profile` ou `LINUX_MCP_PROFILE`)
+    profile: Option<String>,
+    /// Credencial já identificada pelo transporte antes do `initialize` (socket Unix)
+    credential: Option<transport::Access>,
+    /// Credencial e perfil da conexão, definidos no `initialize`; enquanto
+    /// vazio, nenhuma tool pode ser chamada
+    access: Arc<OnceLock<transport::Access>>,
 }
 
 #[tool_router]
 impl LinuxMcpServer {
-    fn new(config: Config) -> Self {
+    fn new(config: Arc<reload::ConfigHandle>, profile: Option<String>) -> Self {
         Self {
             tool_router: Self::tool_router(),
             system: Arc::new(Mutex::new(System::new_all())),
-            config: Arc::new(config),
+            config,
+            outputs: tools::OutputStore::default(),
+            jobs: tools::JobManager::default(),
+            session: audit::new_session_id(),
+            profile,
+            credential: None,
+            access: Arc::new(OnceLock::new()),
         }
     }
 
@@ -45,29 +69,302 @@ impl LinuxMcpServer {
         tools::get_system_info(self.system.clone(), args).await
     }
 
+    /// Lista processos em execução
+    #[tool(
+        description = "Lista processos com pid, ppid, usuário, estado, linha de comando, uso de CPU (%), memória residente (RSS), início, descritores abertos e cgroup, sem executar ps. \
+        Filtra por regex do nome (name), usuário (nome ou UID), min_cpu_percent e min_memory_mb; ordena por 'cpu' (padrão), 'memory', 'pid', 'start_time' ou 'name'; limit padrão 50. \
+        O uso de CPU é a média desde a consulta anterior."
+    )]
+    async fn list_processes(
+        &self,
+        Parameters(args): Parameters<tools::ListProcessesArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tools::list_processes(self.system.clone(), args, self.config()).await
+    }
+
+    /// Mostra a árvore de processos
+    #[tool(
+        description = "Mostra a árvore de processos a partir de um PID (padrão: 1), com os mesmos campos de list_processes em cada nó e os filhos em children. \
+        max_depth limita a profundidade; children_omitted indica filhos deixados de fora."
+    )]
+    async fn process_tree(
+        &self,
+        Parameters(args): Parameters<tools::ProcessTreeArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tools::process_tree(self.system.clone(), args, self.config()).await
+    }
+
     /// Executa um comando no terminal
     #[tool(
         description = "Executa um comando no terminal e retorna o resultado incluindo stdout, stderr e código de saída. ATENÇÃO: Use com cuidado, pois pode executar qualquer comando no sistema. \
+        \n\nFormas de execução:\
+        \n- command: linha de comando interpretada por 'sh -c'\
+        \n- argv: lista [programa, argumentos...] executada diretamente, sem shell (recomendado)\
         \n\nMétodos de autenticação:\
         \n- Normal (padrão): executa com permissões do usuário atual\
-        \n- use_polkit=true: usa PolicyKit/pkexec com diálogo gráfico nativo do sistema para autenticação (recomendado para comandos que precisam de root)"
+        \n- use_polkit=true: usa PolicyKit/pkexec com diálogo gráfico nativo do sistema para autenticação (recomendado para comandos que precisam de root)\
+        \n\nO comando é encerrado após timeout_secs (padrão configurável) ou quando o cliente cancela a requisição.\
+        \n\nSaídas grandes são truncadas (truncate: 'head' ou 'tail'); use get_command_output com o execution_id para ler o restante.\
+        \n\nSe a requisição trouxer um progressToken, cada linha de stdout/stderr é enviada como notificação de progresso durante a execução.\
+        \n\nComandos marcados como 'confirm' no config só executam após o usuário aprovar (elicitation); clientes sem suporte a elicitation recebem um erro."
     )]
     async fn execute_command(
         &self,
         Parameters(args): Parameters<tools::ExecuteCommandArgs>,
+        meta: Meta,
+        peer: Peer<RoleServer>,
+        ct: CancellationToken,
+    ) -> Result<CallToolResult, ErrorData> {
+        let origin = self.origin(&peer);
+        let progress = tools::ProgressReporter::new(&meta, peer.clone());
+        tools::execute_command(
+            args,
+            self.config(),
+            self.outputs.clone(),
+            progress,
+            ct,
+            origin,
+            Some(peer),
+        )
+        .await
+    }
+
+    /// Lê o restante da saída de um comando truncado
+    #[tool(
+        description = "Lê em partes a saída completa de um execute_command cujo resultado foi truncado (truncated: true). \
+        Informe o execution_id retornado, a stream ('stdout' ou 'stderr') e o cursor de next_cursor para continuar a leitura."
+    )]
+    async fn get_command_output(
+        &self,
+        Parameters(args): Parameters<tools::GetCommandOutputArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tools::get_command_output(args, self.config(), self.outputs.clone()).await
+    }
+
+    /// Inicia um comando em background
+    #[tool(
+        description = "Inicia um comando em background e retorna imediatamente um job_id. Use para comandos longos \
+        (ex: upgrades de pacotes, diagnósticos demorados) que excederiam o timeout de execute_command. \
+        Aceita command ou argv, use_polkit e timeout_secs, com as mesmas regras de segurança de execute_command."
+    )]
+    async fn job_start(
+        &self,
+        Parameters(args): Parameters<tools::JobStartArgs>,
+        peer: Peer<RoleServer>,
+        ct: CancellationToken,
+    ) -> Result<CallToolResult, ErrorData> {
+        tools::job_start(
+            args,
+            self.config(),
+            self.jobs.clone(),
+            self.origin(&peer),
+            Some(peer),
+            ct,
+        )
+        .await
+    }
+
+    /// Consulta o estado de um job
+    #[tool(
+        description = "Retorna o estado de um job (running, exited, timed_out, killed ou failed), código de saída e tamanho da saída."
+    )]
+    async fn job_status(
+        &self,
+        Parameters(args): Parameters<tools::JobStatusArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tools::job_status(args, self.jobs.clone()).await
+    }
+
+    /// Lê a saída de um job
+    #[tool(
+        description = "Lê em partes a saída de um job, inclusive enquanto ele roda. Informe job_id, stream ('stdout' ou 'stderr') \
+        e offset (use next_offset da leitura anterior). complete: true indica que o job terminou e toda a saída foi lida."
+    )]
+    async fn job_output(
+        &self,
+        Parameters(args): Parameters<tools::JobOutputArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tools::job_output(args, self.config(), self.jobs.clone()).await
+    }
+
+    /// Encerra um job
+    #[tool(description = "Encerra um job em execução e todos os seus processos filhos.")]
+    async fn job_kill(
+        &self,
+        Parameters(args): Parameters<tools::JobKillArgs>,
+    ) -> Result<CallToolResult, ErrorData> {
+        tools::job_kill(args, self.jobs.clone()).await
+    }
+
+    /// Consulta o audit log
+    #[tool(
+        description = "Busca no audit log os comandos executados ou bloqueados por este servidor. \
+        Filtra por intervalo de tempo (since/until: RFC 3339, AAAA-MM-DD, 'today', 'yesterday' ou '24h'), \
+        status (ex: ['BLOCKED', 'ERROR']), trecho do comando e sessão ('current' para esta conexão). \
+        Consulte antes de repetir um comando: se ele já foi BLOCKED, a política não mudou e ele será bloqueado de novo."
+    )]
+    async fn audit_search(
+        &self,
+        Parameters(args): Parameters<tools::AuditSearchArgs>,
     ) -> Result<CallToolResult, ErrorData> {
-        tools::execute_command(args, self.config.clone()).await
+        tools::audit_search(args, self.config(), self.session.clone()).await
+    }
+}
+
+impl LinuxMcpServer {
+    /// Nova sessão de um transporte com várias conexões
+    ///
+    /// O cache do `sysinfo` e a configuração são compartilhados; saídas, jobs
+    /// e o acesso (definido no `initialize`) são da sessão.
+    fn session(&self) -> Self {
+        Self {
+            outputs: tools::OutputStore::default(),
+            jobs: tools::JobManager::default(),
+            session: audit::new_session_id(),
+            access: Arc::new(OnceLock::new()),
+            ..self.clone()
+        }
+    }
+
+    /// Sessão cujo transporte já identificou o cliente
+    fn with_credential(self, credential: transport::Access) -> Self {
+        Self {
+            credential: Some(credential),
+            ..self
+        }
+    }
+
+    /// Config efetivo da sessão, com o perfil da conexão aplicado
+    fn config(&self) -> Arc<config::Config> {
+        let config = self.config.load();
+        let profile = self
+            .access
+            .get()
+            .and_then(|access| access.profile.as_deref());
+        match profile {
+            Some(profile) => Arc::new(config.with_profile(profile)),
+            None => config,
+        }
+    }
+
+    /// Nomes das tools do servidor
+    pub fn tool_names() -> Vec<String> {
+        Self::tool_router()
+            .list_all()
+            .into_iter()
+            .map(|tool| tool.name.to_string())
+            .collect()
+    }
+
+    fn allows_tool(&self, config: &config::Config, tool: &str) -> bool {
+        self.access
+            .get()
+            .is_some_and(|access| access.allows_tool(config, tool))
+    }
+
+    fn allows_resource(&self, config: &config::Config, uri: &str) -> bool {
+        self.access
+            .get()
+            .is_some_and(|access| access.allows_resource(config, uri))
+    }
+
+    /// Cliente (`clientInfo` do `initialize`), sessão e credencial, para o audit log
+    fn origin(&self, peer: &Peer<RoleServer>) -> audit::Event {
+        let access = self.access.get().cloned().unwrap_or_default();
+        audit::Event {
+            client: peer.peer_info().map(|info| audit::Client {
+                name: info.client_info.name.clone(),
+                version: info.client_info.version.clone(),
+            }),
+            session: Some(self.session.clone()),
+            principal: access.principal,
+            profile: access.profile,
+            ..Default::default()
+        }
     }
 }
 
-#[tool_handler]
 impl ServerHandler for LinuxMcpServer {
+    async fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<InitializeResult, ErrorData> {
+        // Nos transportes HTTP, a credencial vem junto com as partes da requisição
+        let credential = self.credential.clone().or_else(|| {
+            context
+                .extensions
+                .get::<http::request::Parts>()
+                .and_then(|parts| parts.extensions.get::<transport::Access>())
+                .cloned()
+        });
+        let access = transport::Access::resolve(
+            credential,
+            self.profile.as_deref(),
+            &request.client_info.name,
+            &self.config.load(),
+        );
+        let _ = self.access.set(access);
+        if context.peer.peer_info().is_none() {
+            context.peer.set_peer_info(request);
+        }
+        Ok(self.get_info())
+    }
+
+    async fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, ErrorData> {
+        let config = self.config.load();
+        if !self.allows_tool(&config, &request.name) {
+            let event = audit::Event {
+                status: "BLOCKED".to_string(),
+                tool: Some(request.name.to_string()),
+                command: format!("tools/call {}", request.name),
+                rule: Some("profiles.tools".to_string()),
+                details: Some("Tool not allowed for the connection profile".to_string()),
+                ..self.origin(&context.peer)
+            };
+            if let Err(e) = audit::log(&config, event) {
+                eprintln!("warning: failed to write audit record: {:#}", e);
+            }
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_REQUEST,
+                format!(
+                    "A tool '{}' não é permitida para o perfil desta conexão",
+                    request.name
+                ),
+                None,
+            ));
+        }
+        let tcc = ToolCallContext::new(self, request, context);
+        self.tool_router.call(tcc).await
+    }
+
+    async fn list_tools(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> Result<ListToolsResult, ErrorData> {
+        let config = self.config.load();
+        let tools = self
+            .tool_router
+            .list_all()
+            .into_iter()
+            .filter(|tool| self.allows_tool(&config, &tool.name))
+            .collect();
+        Ok(ListToolsResult::with_all_items(tools))
+    }
+
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             protocol_version: ProtocolVersion::LATEST,
             capabilities: ServerCapabilities::builder()
                 .enable_tools()
+                .enable_tool_list_changed()
                 .enable_resources()
+                .enable_resources_list_changed()
                 .enable_prompts()
                 .build(),
             server_info: Implementation::from_build_env(),
@@ -76,13 +373,19 @@ impl ServerHandler for LinuxMcpServer {
                  e executar comandos no terminal.\n\n\
                  Ferramentas disponíveis:\n\
                  - get_system_info: Obtém informações sobre CPU, memória, discos ou sistema operacional\n\
-                 - execute_command: Executa comandos no terminal e retorna o resultado\n\n\
+                 - list_processes, process_tree: Processos em execução, com filtros, ordenação e árvore\n\
+                 - execute_command: Executa comandos no terminal e retorna o resultado\n\
+                 - get_command_output: Lê em partes a saída de um comando truncado\n\
+                 - job_start, job_status, job_output, job_kill: Comandos longos em background\n\
+                 - audit_search: Busca no histórico de comandos executados e bloqueados\n\n\
                  Resources disponíveis:\n\
                  - linux://logs/system: Logs do sistema\n\
                  - linux://logs/auth: Logs de autenticação\n\
                  - linux://config/network: Configuração de rede\n\
                  - linux://processes/top: Processos usando mais recursos\n\
-                 - linux://system/status: Status geral do sistema\n\n\
+                 - linux://system/status: Status geral do sistema\n\
+                 - linux://jobs: Jobs em background e seus estados\n\
+                 - linux://audit/recent: Últimas entradas do audit log\n\n\
                  Prompts disponíveis:\n\
                  - system_troubleshooting: Guia para solução de problemas\n\
                  - security_audit: Auditoria básica de segurança\n\
@@ -96,13 +399,22 @@ impl ServerHandler for LinuxMcpServer {
         }
     }
 
+    async fn on_initialized(&self, context: NotificationContext<RoleServer>) {
+        self.config.subscribe(context.peer);
+    }
+
     async fn list_resources(
         &self,
         _pagination: Option<PaginatedRequestParam>,
         _context: RequestContext<RoleServer>,
     ) -> Result<ListResourcesResult, ErrorData> {
+        let config = self.config.load();
+        let resources = resources::list_resources()
+            .into_iter()
+            .filter(|resource| self.allows_resource(&config, &resource.raw.uri))
+            .collect();
         Ok(ListResourcesResult {
-            resources: resources::list_resources(),
+            resources,
             next_cursor: None,
         })
     }
@@ -110,9 +422,31 @@ impl ServerHandler for LinuxMcpServer {
     async fn read_resource(
         &self,
         request: ReadResourceRequestParam,
-        _context: RequestContext<RoleServer>,
+        context: RequestContext<RoleServer>,
     ) -> Result<ReadResourceResult, ErrorData> {
-        let text = resources::read_resource(&request.uri).await.map_err(|e| {
+        let config = self.config.load();
+        if !self.allows_resource(&config, &request.uri) {
+            let event = audit::Event {
+                status: "BLOCKED".to_string(),
+                command: format!("resources/read {}", request.uri),
+                rule: Some("profiles.tools".to_string()),
+                details: Some("Resource not allowed for the connection profile".to_string()),
+                ..self.origin(&context.peer)
+            };
+            if let Err(e) = audit::log(&config, event) {
+                eprintln!("warning: failed to write audit record: {:#}", e);
+            }
+            return Err(ErrorData::new(
+                ErrorCode::INVALID_REQUEST,
+                format!(
+                    "O resource '{}' não é permitido para o perfil desta conexão",
+                    request.uri
+                ),
+                None,
+            ));
+        }
+        let config = self.config();
+        let text = resources::read_resource(&request.uri, &config, &self.jobs).await.map_err(|e| {
             ErrorData::new(
                 ErrorCode::INTERNAL_ERROR,
                 format!("Failed to read resource: {}", e),
@@ -122,8 +456,8 @@ impl ServerHandler for LinuxMcpServer {
 
         Ok(ReadResourceResult {
             contents: vec![ResourceContents::TextResourceContents {
+                mime_type: Some(resources::mime_type(&request.uri).to_string()),
                 uri: request.uri,
-                mime_type: Some("text/plain".to_string()),
                 text,
                 meta: None,
             }],
@@ -170,21 +504,86 @@ impl ServerHandler for LinuxMcpServer {
 }
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> Result<ExitCode> {
+    let cli = cli::Cli::parse();
+    let layers = config::Layers::new(cli.config)?;
+
+    match cli.command {
+        None => serve(layers, cli.profile, cli::Transport::Stdio, None, None).await,
+        Some(cli::Command::Serve {
+            transport,
+            bind,
+            socket,
+        }) => serve(layers, cli.profile, transport, bind, socket).await,
+        Some(cli::Command::Check {
+            command,
+            argv,
+            polkit,
+            cwd,
+        }) => cli::check(&layers, cli.profile.as_deref(), command, argv, polkit, cwd),
+        Some(cli::Command::Config { action }) => match action {
+            cli::ConfigAction::Init { force } => cli::config_init(&layers, force),
+            cli::ConfigAction::Validate { file } => cli::config_validate(&layers, file),
+            cli::ConfigAction::Show { effective } => cli::config_show(&layers, effective),
+        },
+        Some(cli::Command::Audit { action }) => match action {
+            cli::AuditAction::Tail { lines, follow } => cli::audit_tail(&layers, lines, follow),
+            cli::AuditAction::Verify { file } => cli::audit_verify(&layers, file),
+            cli::AuditAction::Search {
+                pattern,
+                status,
+                limit,
+            } => cli::audit_search(&layers, &pattern, status, limit),
+        },
+        Some(cli::Command::Capabilities { json }) => {
+            cli::capabilities(&layers, cli.profile.as_deref(), json)
+        }
+    }
+}
+
+async fn serve(
+    layers: config::Layers,
+    profile: Option<String>,
+    transport: cli::Transport,
+    bind: Option<String>,
+    socket: Option<Option<PathBuf>>,
+) -> Result<ExitCode> {
     // Carregar configuração
-    let config = config::load()?;
+    let config = config::load(&layers)?.config;
+    for warning in config.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+    if let Some(profile) = &profile {
+        config.require_profile(profile)?;
+    }
+    // Sem a chave os registros sairiam sem HMAC
+    audit::hmac_key(&config)?;
+    let config = Arc::new(reload::ConfigHandle::new(config, layers));
 
-    // Criar o servidor
-    let server = LinuxMcpServer::new(config);
+    // Recarregar a configuração quando um dos arquivos for editado
+    config.clone().watch()?;
 
-    // Criar transporte stdio (stdin/stdout)
-    let transport = (tokio::io::stdin(), tokio::io::stdout());
+    // Criar o servidor
+    let server = LinuxMcpServer::new(config.clone(), profile);
 
-    // Executar o servidor
-    let service = server.serve(transport).await?;
+    if let Some(path) = socket {
+        let path = match path {
+            Some(path) => path,
+            None => transport::unix::default_path()?,
+        };
+        transport::unix::serve(server, config, path).await?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    match transport {
+        cli::Transport::Stdio => {
+            // Criar transporte stdio (stdin/stdout)
+            let transport = (tokio::io::stdin(), tokio::io::stdout());
 
-    // Aguardar até o servidor terminar
-    service.waiting().await?;
+            // Executar o servidor e aguardar até ele terminar
+            server.serve(transport).await?.waiting().await?;
+        }
+        cli::Transport::Http => transport::http::serve(server, config, bind).await?,
+    }
 
-    Ok(())
+    Ok(ExitCode::SUCCESS)
 }
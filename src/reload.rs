@@ -0,0 +1,245 @@
+//! Recarga do `config.toml` sem reiniciar o servidor
+//!
+//! O diretório de configuração é observado via inotify. Cada alteração do
+//! arquivo é lida e validada; se for válida, a configuração ativa é trocada
+//! de forma atômica e os clientes conectados recebem
+//! `notifications/tools/list_changed` e `notifications/resources/list_changed`
+//! quando as capacidades (allowlist, políticas, caminhos, sandbox) mudam.
+//! Edições inválidas são recusadas e a configuração anterior continua ativa.
+
+use crate::audit;
+use crate::config::{self, Config, Layers};
+use anyhow::{Context, Result};
+use arc_swap::ArcSwap;
+use notify::{RecursiveMode, Watcher};
+use rmcp::{Peer, RoleServer};
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Chaves do config que alteram o que o servidor expõe aos clientes
+const CAPABILITY_KEYS: &[&str] = &[
+    "allowed_commands",
+    "require_argv",
+    "denied_paths",
+    "writable_paths",
+    "sandbox",
+    "commands",
+    "confirm",
+    "profiles",
+];
+
+/// Espera após um evento antes de ler o arquivo (editores gravam em etapas)
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// Resultado de uma tentativa de recarga
+#[derive(Debug, PartialEq)]
+pub enum Reload {
+    /// O conteúdo efetivo não mudou
+    Unchanged,
+    /// Nova configuração ativa, com as chaves alteradas
+    Applied(Vec<String>),
+}
+
+/// Configuração ativa, compartilhada entre as requisições
+pub struct ConfigHandle {
+    current: ArcSwap<Config>,
+    layers: Layers,
+    peers: Mutex<Vec<Peer<RoleServer>>>,
+}
+
+impl ConfigHandle {
+    pub fn new(config: Config, layers: Layers) -> Self {
+        Self {
+            current: ArcSwap::from_pointee(config),
+            layers,
+            peers: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Configuração vigente (requisições em andamento mantêm a versão que leram)
+    pub fn load(&self) -> Arc<Config> {
+        self.current.load_full()
+    }
+
+    /// Registra um cliente para receber notificações de mudança
+    pub fn subscribe(&self, peer: Peer<RoleServer>) {
+        let mut peers = self.peers.lock().unwrap();
+        peers.retain(|p| !p.is_transport_closed());
+        peers.push(peer);
+    }
+
+    /// Relê as camadas e troca a configuração ativa se o resultado for válido
+    pub fn reload(&self) -> Result<Reload> {
+        let new = config::load(&self.layers)?.config;
+        let old = self.current.load();
+        let changed = changed_keys(&old, &new)?;
+        if changed.is_empty() {
+            return Ok(Reload::Unchanged);
+        }
+        self.current.store(Arc::new(new));
+        Ok(Reload::Applied(changed))
+    }
+
+    /// Observa os arquivos de configuração até o servidor terminar
+    pub fn watch(self: Arc<Self>) -> Result<()> {
+        let files: BTreeSet<PathBuf> = self.layers.files().into_iter().map(PathBuf::from).collect();
+        // Os diretórios, e não os arquivos: editores costumam salvar via rename
+        let dirs: BTreeSet<PathBuf> = files
+            .iter()
+            .filter_map(|f| f.parent())
+            .filter(|d| d.is_dir())
+            .map(PathBuf::from)
+            .collect();
+
+        // Capacidade 1: vários eventos seguidos viram uma única recarga
+        let (tx, mut rx) = mpsc::channel(1);
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                // Ignora eventos de acesso, gerados também pela própria leitura do arquivo
+                if let Ok(event) = event {
+                    if !event.kind.is_access() && event.paths.iter().any(|p| files.contains(p)) {
+                        let _ = tx.try_send(());
+                    }
+                }
+            })
+            .context("Failed to create config watcher")?;
+        for dir in &dirs {
+            watcher
+                .watch(dir, RecursiveMode::NonRecursive)
+                .with_context(|| format!("Failed to watch {}", dir.display()))?;
+        }
+
+        tokio::spawn(async move {
+            let _watcher = watcher;
+            while rx.recv().await.is_some() {
+                tokio::time::sleep(DEBOUNCE).await;
+                let _ = rx.try_recv();
+                self.apply().await;
+            }
+        });
+        Ok(())
+    }
+
+    async fn apply(&self) {
+        let source = self
+            .layers
+            .files()
+            .iter()
+            .filter(|f| f.exists())
+            .map(|f| f.display().to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let changed = match self.reload() {
+            Ok(Reload::Unchanged) => return,
+            Ok(Reload::Applied(changed)) => changed,
+            Err(e) => {
+                let _ = audit::log_command(
+                    &self.load(),
+                    &source,
+                    "CONFIG_REJECTED",
+                    Some(&summary(&e)),
+                );
+                return;
+            }
+        };
+
+        let _ = audit::log_command(
+            &self.load(),
+            &source,
+            "CONFIG_RELOADED",
+            Some(&format!("changed: {}", changed.join(", "))),
+        );
+
+        if changed
+            .iter()
+            .any(|k| CAPABILITY_KEYS.contains(&k.as_str()))
+        {
+            self.notify_list_changed().await;
+        }
+    }
+
+    async fn notify_list_changed(&self) {
+        let peers: Vec<_> = self.peers.lock().unwrap().clone();
+        for peer in peers {
+            // Clientes desconectados são removidos no próximo `subscribe`
+            let _ = peer.notify_tool_list_changed().await;
+            let _ = peer.notify_resource_list_changed().await;
+        }
+    }
+}
+
+/// Erro em uma linha: o erro do TOML aponta o trecho inválido em várias linhas
+fn summary(e: &anyhow::Error) -> String {
+    let text = format!("{:#}", e);
+    let lines: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .collect();
+    match lines.as_slice() {
+        [first, .., last] => format!("{} ({})", first, last),
+        _ => text.trim().to_string(),
+    }
+}
+
+/// Chaves de primeiro nível com valores diferentes entre duas configurações
+fn changed_keys(old: &Config, new: &Config) -> Result<Vec<String>> {
+    let old = toml::Table::try_from(old)?;
+    let new = toml::Table::try_from(new)?;
+    let mut keys: Vec<String> = old
+        .keys()
+        .chain(new.keys())
+        .filter(|k| old.get(*k) != new.get(*k))
+        .cloned()
+        .collect();
+    keys.sort();
+    keys.dedup();
+    Ok(keys)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reload() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let layers = Layers {
+            system: dir.join("system.toml"),
+            user: path.clone(),
+            extra: None,
+        };
+        let handle = ConfigHandle::new(Config::default(), layers);
+
+        let mut config = Config::default();
+        std::fs::write(&path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(handle.reload().unwrap(), Reload::Unchanged);
+
+        config.allowed_commands.push("htop".to_string());
+        config.default_timeout_secs = 5;
+        std::fs::write(&path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert_eq!(
+            handle.reload().unwrap(),
+            Reload::Applied(vec![
+                "allowed_commands".to_string(),
+                "default_timeout_secs".to_string()
+            ])
+        );
+        assert_eq!(handle.load().default_timeout_secs, 5);
+
+        // Edição inválida: a configuração anterior continua ativa
+        std::fs::write(&path, "allowed_commands = [\"ls\"\n").unwrap();
+        assert!(handle.reload().is_err());
+        config.max_output_bytes = 0;
+        std::fs::write(&path, toml::to_string_pretty(&config).unwrap()).unwrap();
+        assert!(handle.reload().is_err());
+        assert!(handle.load().allowed_commands.contains(&"htop".to_string()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,559 @@
+//! Manifesto de capacidades (`linux://mcp/capabilities`)
+//!
+//! Montado a partir do config efetivo da sessão (com o perfil ativo aplicado),
+//! o mesmo usado pelo motor de políticas, para que o agente planeje com as
+//! regras reais. A versão JSON (`linux://mcp/capabilities.json`) tem os mesmos
+//! dados da versão em Markdown.
+
+use crate::config::{CommandPolicy, Config, SandboxProfile};
+use crate::tools::{self, Truncation};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Manifesto completo
+#[derive(Debug, Serialize)]
+pub struct Manifest {
+    /// Perfil de `[profiles]` aplicado à sessão
+    pub profile: Option<ProfileInfo>,
+    /// Tools que a sessão pode chamar
+    pub tools: Vec<String>,
+    pub commands: Commands,
+    pub paths: Paths,
+    pub elevation: Elevation,
+    pub approval: Approval,
+    pub limits: Limits,
+    pub environment: Environment,
+    pub redaction: Redaction,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProfileInfo {
+    pub name: String,
+    /// Falso se o perfil deixou de existir no config (todas as tools bloqueadas)
+    pub defined: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Commands {
+    /// Allowlist, em ordem alfabética
+    pub allowed: Vec<String>,
+    /// Se `execute_command` exige `argv` (sem `sh -c`)
+    pub require_argv: bool,
+    /// Diretórios de onde um binário pode ser chamado por caminho absoluto
+    pub trusted_bin_dirs: Vec<String>,
+    /// Políticas de argumentos por comando (`[commands.<nome>]`)
+    pub policies: BTreeMap<String, CommandPolicy>,
+    /// Políticas gerais redefinidas pelo perfil, que continuam valendo junto com as dele
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile_base_policies: BTreeMap<String, CommandPolicy>,
+    /// Raízes em que `rm` pode remover arquivos (vazia: `rm` bloqueado)
+    pub rm_allowed_paths: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Paths {
+    /// Caminhos bloqueados para qualquer comando (`denied_paths`)
+    pub denied: Vec<String>,
+    /// Caminhos do próprio servidor nunca alcançáveis (audit log, chaves)
+    pub protected: Vec<String>,
+    /// Destinos permitidos para redirecionamentos de escrita
+    pub writable: Vec<String>,
+    /// Raízes do perfil fora das quais nenhum caminho pode ser usado
+    pub roots: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Elevation {
+    /// Se `use_polkit` (pkexec) é permitido
+    pub polkit_allowed: bool,
+    /// Comandos que só executam com `use_polkit`
+    pub requires_polkit: Vec<String>,
+    /// Perfil de sandbox dos comandos sem perfil próprio
+    pub default_sandbox: Option<String>,
+    pub sandbox_profiles: BTreeMap<String, SandboxProfile>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Approval {
+    /// Comandos cuja execução sempre exige aprovação
+    pub commands: Vec<String>,
+    /// Subcomandos que exigem aprovação, por comando
+    pub subcommands: BTreeMap<String, Vec<String>>,
+    /// Regexes sobre cada comando simples (e a linha inteira) que exigem aprovação
+    pub patterns: Vec<String>,
+    pub timeout_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Limits {
+    pub default_timeout_secs: u64,
+    pub max_timeout_secs: u64,
+    pub max_output_bytes: usize,
+    pub max_output_lines: usize,
+    pub output_truncation: Truncation,
+    pub max_captured_bytes: usize,
+    pub output_retention: usize,
+    pub max_stdin_bytes: usize,
+    pub max_concurrent_jobs: usize,
+    pub job_retention: usize,
+    pub job_timeout_secs: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Environment {
+    /// Variáveis do servidor repassadas aos comandos
+    pub allowlist: Vec<String>,
+    /// Variáveis nunca repassadas
+    pub denylist: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Redaction {
+    pub enabled: bool,
+    pub detectors: Vec<String>,
+    /// Quantidade de regexes próprias do config
+    pub custom_patterns: usize,
+}
+
+impl Manifest {
+    pub fn new(config: &Config) -> Self {
+        let profile = config.profile().map(|(_, profile)| profile);
+        let defined = config.active_profile.is_none() || profile.is_some();
+        let mut tools = crate::LinuxMcpServer::tool_names()
+            .into_iter()
+            .filter(|tool| {
+                defined
+                    && profile
+                        .and_then(|p| p.tools.as_ref())
+                        .is_none_or(|tools| tools.contains(tool))
+            })
+            .collect::<Vec<_>>();
+        tools.sort();
+
+        let mut allowed = config.allowed_commands.clone();
+        allowed.sort();
+        allowed.dedup();
+        let rm_allowed_paths = config
+            .commands
+            .get("rm")
+            .filter(|_| config.profile_allows_rm())
+            .map(|policy| policy.allowed_paths.clone())
+            .unwrap_or_default();
+        let policies_with = |flag: fn(&CommandPolicy) -> bool| {
+            let mut names = config
+                .commands
+                .iter()
+                .chain(&config.base_commands)
+                .filter(|(_, policy)| flag(policy))
+                .map(|(name, _)| name.clone())
+                .collect::<Vec<_>>();
+            names.sort();
+            names.dedup();
+            names
+        };
+
+        Self {
+            profile: config.active_profile.clone().map(|name| ProfileInfo {
+                name,
+                defined: profile.is_some(),
+            }),
+            tools,
+            commands: Commands {
+                allowed,
+                require_argv: config.require_argv,
+                trusted_bin_dirs: tools::TRUSTED_BIN_DIRS
+                    .iter()
+                    .map(|dir| dir.to_string())
+                    .collect(),
+                policies: config.commands.clone(),
+                profile_base_policies: config.base_commands.clone(),
+                rm_allowed_paths,
+            },
+            paths: Paths {
+                denied: config.denied_paths.clone(),
+                // Só falha sem diretório de config, quando os comandos também falham
+                protected: tools::protected_paths(config)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|(_, path)| path)
+                    .collect(),
+                writable: config.writable_paths.clone(),
+                roots: profile.and_then(|p| p.path_roots.clone()),
+            },
+            elevation: Elevation {
+                polkit_allowed: profile.is_none_or(|p| p.allow_polkit),
+                requires_polkit: policies_with(|p| p.requires_polkit),
+                default_sandbox: config.sandbox.default_profile.clone(),
+                sandbox_profiles: config.sandbox.profiles.clone(),
+            },
+            approval: Approval {
+                commands: policies_with(|p| p.confirm),
+                subcommands: config
+                    .commands
+                    .iter()
+                    .chain(&config.base_commands)
+                    .filter(|(_, policy)| !policy.confirm_subcommands.is_empty())
+                    .fold(BTreeMap::new(), |mut subcommands, (name, policy)| {
+                        let list: &mut Vec<String> = subcommands.entry(name.clone()).or_default();
+                        for sub in &policy.confirm_subcommands {
+                            if !list.contains(sub) {
+                                list.push(sub.clone());
+                            }
+                        }
+                        subcommands
+                    }),
+                patterns: config.confirm.patterns.clone(),
+                timeout_secs: config.confirm.timeout_secs,
+            },
+            limits: Limits {
+                default_timeout_secs: config.default_timeout_secs,
+                max_timeout_secs: config.max_timeout_secs,
+                max_output_bytes: config.max_output_bytes,
+                max_output_lines: config.max_output_lines,
+                output_truncation: config.output_truncation,
+                max_captured_bytes: config.max_captured_bytes,
+                output_retention: config.output_retention,
+                max_stdin_bytes: config.max_stdin_bytes,
+                max_concurrent_jobs: config.max_concurrent_jobs,
+                job_retention: config.job_retention,
+                job_timeout_secs: config.job_timeout_secs,
+            },
+            environment: Environment {
+                allowlist: config.env_allowlist.clone(),
+                denylist: config.env_denylist.clone(),
+            },
+            redaction: Redaction {
+                enabled: config.redaction.enabled,
+                detectors: if config.redaction.enabled {
+                    config.redaction.detectors.clone()
+                } else {
+                    Vec::new()
+                },
+                custom_patterns: config.redaction.patterns.len(),
+            },
+        }
+    }
+
+    /// Versão em Markdown
+    pub fn markdown(&self) -> String {
+        let mut out = String::from("# Linux MCP Server Capabilities\n\n");
+        out.push_str(
+            "Generated from the active configuration. Anything not listed here is blocked; \
+             blocked attempts are recorded in the audit log.\n\n",
+        );
+
+        if let Some(profile) = &self.profile {
+            out.push_str(&format!("## 👤 Active Profile: `{}`\n", profile.name));
+            if !profile.defined {
+                out.push_str(
+                    "The profile no longer exists in the configuration: every tool is blocked.\n",
+                );
+            }
+            out.push('\n');
+        }
+
+        out.push_str("## 🧰 Tools\n");
+        out.push_str(&format!("{}\n\n", or_none(&self.tools)));
+
+        let commands = &self.commands;
+        out.push_str("## 💻 Commands\n");
+        out.push_str(&format!("- Allowed: {}\n", or_none(&commands.allowed)));
+        out.push_str(
+            "- Command lines are parsed as shell: every program in pipelines, lists, subshells \
+             and substitutions must be allowed.\n",
+        );
+        out.push_str(&format!(
+            "- Absolute program paths only from: {}\n",
+            format_list(&commands.trusted_bin_dirs)
+        ));
+        if commands.require_argv {
+            out.push_str("- `argv` is required (no `sh -c` command lines)\n");
+        }
+        if commands.rm_allowed_paths.is_empty() {
+            out.push_str("- `rm`: blocked\n");
+        } else {
+            out.push_str(&format!(
+                "- `rm`: only for files inside {}\n",
+                format_list(&commands.rm_allowed_paths)
+            ));
+        }
+        out.push_str("\n### Argument Policies\n");
+        out.push_str(&format_command_policies(&commands.policies));
+        if !commands.profile_base_policies.is_empty() {
+            out.push_str(
+                "\nThe profile redefines these general policies; commands must pass both:\n",
+            );
+            out.push_str(&format_command_policies(&commands.profile_base_policies));
+        }
+        out.push('\n');
+
+        let paths = &self.paths;
+        out.push_str("## 📁 Paths\n");
+        out.push_str(&format!(
+            "- Denied (after resolving `~`, globs, `..` and symlinks): {}\n",
+            or_none(&paths.denied)
+        ));
+        out.push_str(&format!(
+            "- Protected server files: {}\n",
+            or_none(&paths.protected)
+        ));
+        out.push_str(&format!(
+            "- Write redirections (`>`, `>>`) only into: {}\n",
+            or_none(&paths.writable)
+        ));
+        if let Some(roots) = &paths.roots {
+            out.push_str(&format!(
+                "- Every path must be inside: {}\n",
+                or_none(roots)
+            ));
+        }
+        out.push('\n');
+
+        let elevation = &self.elevation;
+        out.push_str("## 🔐 Elevation\n");
+        out.push_str(if elevation.polkit_allowed {
+            "- `use_polkit`: allowed (pkexec, asks for authentication)\n"
+        } else {
+            "- `use_polkit`: not allowed\n"
+        });
+        if !elevation.requires_polkit.is_empty() {
+            out.push_str(&format!(
+                "- Require `use_polkit`: {}\n",
+                format_list(&elevation.requires_polkit)
+            ));
+        }
+        if let Some(profile) = &elevation.default_sandbox {
+            out.push_str(&format!(
+                "- Default sandbox profile: `{}` (cannot be combined with `use_polkit`)\n",
+                profile
+            ));
+        }
+        for (name, profile) in &elevation.sandbox_profiles {
+            out.push_str(&format!(
+                "- Sandbox `{}`: {}\n",
+                name,
+                format_sandbox(profile)
+            ));
+        }
+        out.push('\n');
+
+        let approval = &self.approval;
+        out.push_str("## ✋ Human Approval\n");
+        if approval.commands.is_empty()
+            && approval.subcommands.is_empty()
+            && approval.patterns.is_empty()
+        {
+            out.push_str("- No command requires approval\n");
+        } else {
+            if !approval.commands.is_empty() {
+                out.push_str(&format!(
+                    "- Commands: {}\n",
+                    format_list(&approval.commands)
+                ));
+            }
+            for (name, subcommands) in &approval.subcommands {
+                out.push_str(&format!(
+                    "- `{}` subcommands: {}\n",
+                    name,
+                    format_list(subcommands)
+                ));
+            }
+            if !approval.patterns.is_empty() {
+                out.push_str(&format!(
+                    "- Commands (each simple command, also without flags) matching: {}\n",
+                    format_list(&approval.patterns)
+                ));
+            }
+            out.push_str(&format!(
+                "- Requires a client with elicitation support; denied after {}s without an answer\n",
+                approval.timeout_secs
+            ));
+        }
+        out.push('\n');
+
+        let limits = &self.limits;
+        out.push_str("## ⏱️ Limits\n");
+        out.push_str(&format!(
+            "- Timeout: {}s by default, up to {}s (`timeout_secs`)\n\
+             - Output shown: {} bytes / {} lines per stream ({} kept on truncation); \
+             up to {} bytes captured, last {} truncated outputs kept for `get_command_output`\n\
+             - `stdin`: up to {} bytes\n\
+             - Background jobs: {} running at once, {} finished kept, {}s timeout\n\n",
+            limits.default_timeout_secs,
+            limits.max_timeout_secs,
+            limits.max_output_bytes,
+            limits.max_output_lines,
+            match limits.output_truncation {
+                Truncation::Head => "head",
+                Truncation::Tail => "tail",
+            },
+            limits.max_captured_bytes,
+            limits.output_retention,
+            limits.max_stdin_bytes,
+            limits.max_concurrent_jobs,
+            limits.job_retention,
+            limits.job_timeout_secs,
+        ));
+
+        out.push_str("## 🌱 Environment\n");
+        out.push_str(&format!(
+            "- Passed to commands: {}\n- Never passed: {}\n\n",
+            or_none(&self.environment.allowlist),
+            or_none(&self.environment.denylist)
+        ));
+
+        out.push_str("## 🙈 Secret Redaction\n");
+        if self.redaction.enabled {
+            out.push_str(&format!(
+                "- Output, resources and audit records are masked by: {}",
+                or_none(&self.redaction.detectors)
+            ));
+            if self.redaction.custom_patterns > 0 {
+                out.push_str(&format!(
+                    " and {} custom pattern(s)",
+                    self.redaction.custom_patterns
+                ));
+            }
+            out.push('\n');
+        } else {
+            out.push_str("- Disabled\n");
+        }
+        out
+    }
+
+    /// Versão JSON
+    pub fn json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+}
+
+fn format_list(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|v| format!("`{}`", v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn or_none(values: &[String]) -> String {
+    if values.is_empty() {
+        "(none)".to_string()
+    } else {
+        format_list(values)
+    }
+}
+
+/// Formata as políticas de argumentos de `[commands.<nome>]` em Markdown
+fn format_command_policies(policies: &BTreeMap<String, CommandPolicy>) -> String {
+    if policies.is_empty() {
+        return "No per-command argument policies configured.\n".to_string();
+    }
+
+    let mut out = String::new();
+    for (name, policy) in policies {
+        out.push_str(&format!("- `{}`\n", name));
+        let rules = [
+            ("Allowed subcommands", &policy.allowed_subcommands),
+            ("Denied subcommands", &policy.denied_subcommands),
+            ("Forbidden flags", &policy.forbidden_flags),
+            ("Allowed argument patterns", &policy.allowed_arg_patterns),
+            ("Denied argument patterns", &policy.denied_arg_patterns),
+            ("Allowed paths", &policy.allowed_paths),
+            ("Denied paths", &policy.denied_paths),
+        ];
+        for (label, values) in rules {
+            if !values.is_empty() {
+                out.push_str(&format!("  - {}: {}\n", label, format_list(values)));
+            }
+        }
+        if policy.requires_polkit {
+            out.push_str("  - Requires PolicyKit (`use_polkit: true`)\n");
+        }
+        if let Some(profile) = &policy.sandbox {
+            out.push_str(&format!("  - Sandbox profile: `{}`\n", profile));
+        }
+    }
+    out
+}
+
+/// Camadas e limites de um perfil de sandbox, em uma linha
+fn format_sandbox(profile: &SandboxProfile) -> String {
+    let mut layers = Vec::new();
+    if profile.namespaces {
+        layers.push("namespaces".to_string());
+        if !profile.network {
+            layers.push("no network".to_string());
+        }
+    }
+    if profile.landlock {
+        layers.push("Landlock".to_string());
+    }
+    if profile.seccomp {
+        layers.push("seccomp".to_string());
+    }
+    let limits = [
+        ("cpu", profile.max_cpu_secs, "s"),
+        ("memory", profile.max_memory_mb, " MiB"),
+        ("file size", profile.max_file_size_mb, " MiB"),
+        ("processes", profile.max_processes, ""),
+    ];
+    for (label, value, unit) in limits {
+        if let Some(value) = value {
+            layers.push(format!("{} ≤ {}{}", label, value, unit));
+        }
+    }
+    if layers.is_empty() {
+        "no isolation".to_string()
+    } else {
+        layers.join(", ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    #[test]
+    fn test_manifest() {
+        let mut config = Config {
+            allowed_commands: vec!["ls".to_string(), "df".to_string(), "cat".to_string()],
+            ..Default::default()
+        };
+        config.profiles.insert(
+            "readonly".to_string(),
+            Profile {
+                tools: Some(vec!["get_system_info".to_string()]),
+                allowed_commands: Some(vec!["cat".to_string()]),
+                path_roots: Some(vec!["/var/log".to_string()]),
+                allow_polkit: false,
+                ..Default::default()
+            },
+        );
+
+        let manifest = Manifest::new(&config);
+        assert_eq!(manifest.commands.allowed, ["cat", "df", "ls"]);
+        assert!(manifest.tools.contains(&"execute_command".to_string()));
+        assert!(manifest.elevation.polkit_allowed);
+        assert!(!manifest.markdown().contains("`curl`"));
+
+        let manifest = Manifest::new(&config.with_profile("readonly"));
+        assert_eq!(manifest.tools, ["get_system_info"]);
+        assert_eq!(manifest.commands.allowed, ["cat"]);
+        assert!(manifest.commands.rm_allowed_paths.is_empty());
+        assert!(!manifest.elevation.polkit_allowed);
+        let json: serde_json::Value = serde_json::from_str(&manifest.json()).unwrap();
+        assert_eq!(json["profile"]["name"], "readonly");
+        assert_eq!(json["paths"]["roots"][0], "/var/log");
+        assert_eq!(
+            json["commands"]["policies"]["systemctl"]["denied_subcommands"][0],
+            "poweroff"
+        );
+        assert!(manifest
+            .markdown()
+            .contains("- Every path must be inside: `/var/log`"));
+
+        let manifest = Manifest::new(&config.with_profile("removed"));
+        assert!(manifest.tools.is_empty());
+    }
+}
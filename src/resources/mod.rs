@@ -1,8 +1,19 @@
+use crate::audit;
+use crate::config::Config;
+use crate::redact::Redactor;
+use crate::tools::JobManager;
 use anyhow::{Context, Result};
 use rmcp::model::{Annotated, RawResource};
 use std::process::Command;
 use sysinfo::System;
 
+mod capabilities;
+
+pub use capabilities::Manifest;
+
+/// Entradas mostradas em `linux://audit/recent`
+const RECENT_AUDIT_ENTRIES: usize = 50;
+
 /// Lista todos os resources disponíveis
 pub fn list_resources() -> Vec<Annotated<RawResource>> {
     vec![
@@ -73,8 +84,55 @@ pub fn list_resources() -> Vec<Annotated<RawResource>> {
                 uri: "linux://mcp/capabilities".to_string(),
                 name: "MCP Capabilities".to_string(),
                 title: Some("MCP Capabilities & Security Rules".to_string()),
-                description: Some("Lista de comandos permitidos e regras de segurança".to_string()),
-                mime_type: Some("text/plain".to_string()),
+                description: Some(
+                    "Comandos, regras de argumentos e caminhos, elevação e limites em vigor nesta sessão"
+                        .to_string(),
+                ),
+                mime_type: Some("text/markdown".to_string()),
+                size: None,
+                icons: None,
+            },
+            None,
+        ),
+        Annotated::new(
+            RawResource {
+                uri: "linux://mcp/capabilities.json".to_string(),
+                name: "MCP Capabilities (JSON)".to_string(),
+                title: Some("MCP Capabilities & Security Rules (JSON)".to_string()),
+                description: Some(
+                    "Mesmo conteúdo de linux://mcp/capabilities em JSON, para planejamento automático"
+                        .to_string(),
+                ),
+                mime_type: Some("application/json".to_string()),
+                size: None,
+                icons: None,
+            },
+            None,
+        ),
+        Annotated::new(
+            RawResource {
+                uri: "linux://jobs".to_string(),
+                name: "Background Jobs".to_string(),
+                title: Some("Background Jobs".to_string()),
+                description: Some(
+                    "Jobs em background (job_start) em execução e finalizados recentemente".to_string(),
+                ),
+                mime_type: Some("application/json".to_string()),
+                size: None,
+                icons: None,
+            },
+            None,
+        ),
+        Annotated::new(
+            RawResource {
+                uri: "linux://audit/recent".to_string(),
+                name: "Recent Audit Entries".to_string(),
+                title: Some("Recent Audit Entries".to_string()),
+                description: Some(format!(
+                    "Últimas {} entradas do audit log (use audit_search para filtrar)",
+                    RECENT_AUDIT_ENTRIES
+                )),
+                mime_type: Some("application/json".to_string()),
                 size: None,
                 icons: None,
             },
@@ -83,8 +141,39 @@ pub fn list_resources() -> Vec<Annotated<RawResource>> {
     ]
 }
 
-/// Lê o conteúdo de um resource
-pub async fn read_resource(uri: &str) -> Result<String> {
+/// Tool que o resource espelha: um perfil sem ela em `tools` também não vê
+/// nem lê o resource (os manifestos de capacidades valem para todos)
+pub fn mirrored_tool(uri: &str) -> Option<&'static str> {
+    match uri {
+        "linux://logs/system" | "linux://logs/auth" | "linux://config/network" => {
+            Some("execute_command")
+        }
+        "linux://processes/top" => Some("list_processes"),
+        "linux://system/status" => Some("get_system_info"),
+        "linux://jobs" => Some("job_status"),
+        "linux://audit/recent" => Some("audit_search"),
+        _ => None,
+    }
+}
+
+/// Tipo MIME do conteúdo de um resource
+pub fn mime_type(uri: &str) -> &'static str {
+    match uri {
+        "linux://jobs" | "linux://audit/recent" | "linux://mcp/capabilities.json" => {
+            "application/json"
+        }
+        "linux://mcp/capabilities" => "text/markdown",
+        _ => "text/plain",
+    }
+}
+
+/// Lê o conteúdo de um resource, com os segredos mascarados
+pub async fn read_resource(uri: &str, config: &Config, jobs: &JobManager) -> Result<String> {
+    let text = read(uri, config, jobs).await?;
+    Ok(Redactor::from_config(config).text(&text))
+}
+
+async fn read(uri: &str, config: &Config, jobs: &JobManager) -> Result<String> {
     match uri {
         "linux://logs/system" => {
             let output = Command::new("journalctl")
@@ -148,31 +237,17 @@ pub async fn read_resource(uri: &str) -> Result<String> {
                 System::uptime()
             ))
         }
-        "linux://mcp/capabilities" => {
-            let mut allowed = crate::tools::ALLOWED_COMMANDS.to_vec();
-            allowed.sort();
-
-            Ok(format!(
-               "# Linux MCP Server Security & Capabilities\n\n\
-                ## 🛡️ Security Rules\n\
-                This server operates in a secure mode. Arbitrary command execution is BLOCKED.\n\n\
-                ### 1. Allowed Commands\n\
-                Only the following commands can be executed:\n\
-                {}\n\n\
-                ### 2. Forbidden Actions\n\
-                - ❌ `rm` (Blocked by default, see Safe RM exceptions below)\n\
-                - ❌ `curl`, `wget`, `ssh` (Network exfiltration blocked)\n\
-                - ❌ `/etc/shadow`, `~/.ssh` (Sensitive data access blocked)\n\n\
-                ### 3. Safe RM Policy\n\
-                The `rm` command is ALLOWED only for recursively deleting files in:\n\
-                - ✅ `/tmp/*`\n\
-                - ✅ `/var/tmp/*`\n\
-                - ✅ `/var/log/*`\n\
-                - ✅ `~/.cache/*`\n\
-                - ✅ `~/.local/share/Trash/*`\n\n\
-                **Any other `rm` usage will be rejected.**",
-                allowed.join(", ")
-            ))
+        "linux://mcp/capabilities" => Ok(Manifest::new(config).markdown()),
+        "linux://mcp/capabilities.json" => Ok(Manifest::new(config).json()),
+        "linux://jobs" => Ok(serde_json::to_string_pretty(&jobs.list().await)?),
+        "linux://audit/recent" => {
+            let query = audit::query::Query {
+                limit: RECENT_AUDIT_ENTRIES,
+                ..Default::default()
+            };
+            let found = audit::query::search(&audit::log_file_path(&config.log_path)?, &query)?;
+            let entries: Vec<_> = found.events.iter().map(audit::query::to_json).collect();
+            Ok(serde_json::to_string_pretty(&entries)?)
         }
         _ => Err(anyhow::anyhow!("Unknown resource: {}", uri)),
     }
@@ -0,0 +1,300 @@
+//! Aprovação humana de comandos marcados como `confirm`
+//!
+//! Antes de executar, o servidor pede ao cliente MCP (elicitation) que o
+//! usuário aprove o comando exato, com os caminhos resolvidos e o método de
+//! elevação. A decisão vai para o audit log (`APPROVED` ou `DENIED`); se o
+//! cliente não suporta elicitation, o comando é bloqueado.
+
+use super::shell::{quote_argv, Word};
+use super::{paths, record, Authorized, Invocation};
+use crate::audit;
+use crate::config::Config;
+use regex::Regex;
+use rmcp::model::ErrorCode;
+use rmcp::schemars::JsonSchema;
+use rmcp::service::ElicitationError;
+use rmcp::{ErrorData, Peer, RoleServer, ServiceError};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+/// Resposta pedida ao usuário
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct Approval {
+    /// Marque para executar o comando
+    pub approve: bool,
+}
+
+rmcp::elicit_safe!(Approval);
+
+/// Regras que exigem aprovação da invocação (vazia se nenhuma)
+pub(super) fn required(invocation: &Invocation, config: &Config) -> Vec<String> {
+    let mut rules: Vec<String> = Vec::new();
+    let mut push = |rule: String| {
+        if !rules.contains(&rule) {
+            rules.push(rule);
+        }
+    };
+    // Padrões inválidos já são recusados pelo `validate`; se ainda assim
+    // aparecerem, exigem aprovação em vez de liberar o comando
+    let patterns: Vec<Option<Regex>> = config
+        .confirm
+        .patterns
+        .iter()
+        .map(|p| Regex::new(p).ok())
+        .collect();
+    let matches_pattern = |subject: &str| {
+        patterns
+            .iter()
+            .any(|re| re.as_ref().is_none_or(|re| re.is_match(subject)))
+    };
+    if matches_pattern(&invocation.display()) {
+        push("confirm.patterns".to_string());
+    }
+
+    for command in invocation.commands() {
+        let Some((first, args)) = command.words.split_first() else {
+            continue;
+        };
+        let name = Path::new(&first.text)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&first.text);
+        let subjects = pattern_subjects(name, args);
+        if subjects.iter().any(|s| matches_pattern(s)) {
+            push("confirm.patterns".to_string());
+        }
+        let subcommand = args.iter().find(|arg| !arg.text.starts_with('-'));
+        for (rule, policy) in config.command_policies(name) {
+            if policy.confirm {
+                push(format!("{}.confirm", rule));
+            }
+            if subcommand.is_some_and(|sub| policy.confirm_subcommands.contains(&sub.text)) {
+                push(format!("{}.confirm_subcommands", rule));
+            }
+        }
+    }
+    rules
+}
+
+/// Textos de um comando simples comparados com `[confirm] patterns`
+///
+/// Cada comando de listas, pipelines e subshells é comparado sozinho, com o
+/// programa sem o diretório e os espaços normalizados (`apt install x`), e
+/// também sem as flags, para que `apt -y install x` case com `^apt install`.
+fn pattern_subjects(name: &str, args: &[Word]) -> [String; 2] {
+    let argv: Vec<String> = std::iter::once(name.to_string())
+        .chain(args.iter().map(|arg| arg.text.clone()))
+        .collect();
+    let operands: Vec<String> = argv
+        .iter()
+        .enumerate()
+        .filter(|(i, arg)| *i == 0 || !arg.starts_with('-'))
+        .map(|(_, arg)| arg.clone())
+        .collect();
+    [quote_argv(&argv), quote_argv(&operands)]
+}
+
+/// Texto da pergunta mostrada ao usuário
+fn message(authorized: &Authorized, use_polkit: bool) -> String {
+    let mut targets = Vec::new();
+    for command in authorized.invocation.commands() {
+        for path in paths::targets(&command, &authorized.process.cwd) {
+            let path = path.display().to_string();
+            if !targets.contains(&path) {
+                targets.push(path);
+            }
+        }
+    }
+
+    let mut text = format!(
+        "Aprovar a execução deste comando?\n\n    {}\n\nElevação: {}\nDiretório: {}\n",
+        authorized.invocation.display(),
+        if use_polkit {
+            "pkexec (PolicyKit, como root)"
+        } else {
+            "nenhuma (usuário do servidor)"
+        },
+        authorized.process.cwd.display()
+    );
+    if targets.is_empty() {
+        text.push_str("Caminhos: nenhum identificado\n");
+    } else {
+        text.push_str("Caminhos:\n");
+        for path in &targets {
+            text.push_str(&format!("    {}\n", path));
+        }
+    }
+    if let Some(sandbox) = &authorized.process.sandbox {
+        text.push_str(&format!("Sandbox: {}\n", sandbox.name));
+    }
+    text.push_str(&format!(
+        "Exige aprovação por: {}",
+        authorized.confirm.join(", ")
+    ));
+    text
+}
+
+/// Pede a aprovação do usuário e registra a decisão no audit log
+///
+/// `started` é a entrada ALLOWED que será gravada se o comando for aprovado.
+pub(super) async fn confirm(
+    authorized: &Authorized,
+    peer: Option<&Peer<RoleServer>>,
+    config: &Config,
+    started: &audit::Event,
+    use_polkit: bool,
+    ct: &CancellationToken,
+) -> Result<(), ErrorData> {
+    let rules = authorized.confirm.join(", ");
+    let decision = |status: &str, details: String| audit::Event {
+        status: status.to_string(),
+        rule: Some(rules.clone()),
+        details: Some(details),
+        ..started.clone()
+    };
+    let refused = |message: String| ErrorData::new(ErrorCode::INVALID_PARAMS, message, None);
+
+    let Some(peer) = peer.filter(|p| p.supports_elicitation()) else {
+        let _ = record(
+            config,
+            decision(
+                "BLOCKED",
+                "Approval required but the MCP client does not support elicitation".to_string(),
+            ),
+        );
+        return Err(refused(format!(
+            "Comando não executado: ele exige aprovação do usuário ({}), mas o cliente MCP não \
+             suporta elicitation. Use um cliente com suporte a elicitation ou execute o comando \
+             manualmente.",
+            rules
+        )));
+    };
+
+    let timeout_secs = config.confirm.timeout_secs;
+    let request = peer.elicit_with_timeout::<Approval>(
+        message(authorized, use_polkit),
+        Some(Duration::from_secs(timeout_secs)),
+    );
+    let answer = tokio::select! {
+        answer = request => answer,
+        _ = ct.cancelled() => {
+            let _ = record(config, decision("DENIED", "Cancelled by the MCP client".to_string()));
+            return Err(refused(
+                "Comando não executado: a requisição foi cancelada antes da aprovação".to_string(),
+            ));
+        }
+    };
+
+    let (details, reason) = match answer {
+        Ok(Some(Approval { approve: true })) => {
+            return record(
+                config,
+                decision("APPROVED", "Approved by the user".to_string()),
+            );
+        }
+        Ok(_) | Err(ElicitationError::UserDeclined) => (
+            "Declined by the user".to_string(),
+            "o usuário recusou a execução".to_string(),
+        ),
+        Err(ElicitationError::UserCancelled) => (
+            "Dismissed by the user".to_string(),
+            "o usuário fechou o pedido de aprovação".to_string(),
+        ),
+        Err(ElicitationError::Service(ServiceError::Timeout { .. })) => (
+            format!("No answer after {}s", timeout_secs),
+            format!("sem resposta do usuário em {}s", timeout_secs),
+        ),
+        Err(e) => (
+            format!("Elicitation failed: {}", e),
+            format!("falha ao pedir aprovação ({})", e),
+        ),
+    };
+    let _ = record(config, decision("DENIED", details));
+    Err(refused(format!("Comando não executado: {}.", reason)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CommandPolicy;
+
+    #[test]
+    fn test_required() {
+        let mut config = Config::default();
+        config.confirm.patterns = vec![r"^(apt|dnf) install\b".to_string()];
+        config.commands.insert(
+            "rm".to_string(),
+            CommandPolicy {
+                confirm: true,
+                ..config.commands["rm"].clone()
+            },
+        );
+        config.commands.insert(
+            "systemctl".to_string(),
+            CommandPolicy {
+                confirm_subcommands: vec!["restart".to_string()],
+                ..config.commands["systemctl"].clone()
+            },
+        );
+        let rules = |command: &str| required(&Invocation::Shell(command.to_string()), &config);
+
+        assert!(rules("systemctl status nginx").is_empty());
+        assert_eq!(
+            rules("systemctl --user restart nginx"),
+            ["commands.systemctl.confirm_subcommands"]
+        );
+        assert_eq!(
+            rules("ls /tmp && /usr/bin/rm /tmp/x"),
+            ["commands.rm.confirm"]
+        );
+        assert_eq!(rules("apt install htop"), ["confirm.patterns"]);
+        assert!(rules("apt list --installed").is_empty());
+        // Listas, pipelines, subshells, flags antes do subcomando e espaços extras
+        assert_eq!(rules("ls && apt install htop"), ["confirm.patterns"]);
+        assert_eq!(rules("echo y | dnf install htop"), ["confirm.patterns"]);
+        assert_eq!(rules("(true; apt install htop)"), ["confirm.patterns"]);
+        assert_eq!(rules("apt -y install htop"), ["confirm.patterns"]);
+        assert_eq!(rules("apt  install   htop"), ["confirm.patterns"]);
+        assert_eq!(rules("/usr/bin/apt install htop"), ["confirm.patterns"]);
+        assert!(rules("ls && apt list --installed").is_empty());
+        let argv = Invocation::Argv(vec![
+            "apt".to_string(),
+            "-y".to_string(),
+            "install".to_string(),
+            "htop".to_string(),
+        ]);
+        assert_eq!(required(&argv, &config), ["confirm.patterns"]);
+        let argv = Invocation::Argv(vec!["rm".to_string(), "/tmp/x".to_string()]);
+        assert_eq!(required(&argv, &config), ["commands.rm.confirm"]);
+    }
+
+    #[test]
+    fn test_targets() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-approval-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("a.log"), "").unwrap();
+        let cwd = super::paths::canonicalize(&dir, true);
+
+        let command = |line: &str| {
+            super::super::shell::parse(line)
+                .unwrap()
+                .into_iter()
+                .next()
+                .unwrap()
+        };
+        assert_eq!(
+            paths::targets(&command("rm -f a.log ./b.log"), &cwd),
+            [cwd.join("a.log"), cwd.join("b.log")]
+        );
+        assert!(paths::targets(&command("systemctl restart nginx"), &cwd).is_empty());
+        assert_eq!(
+            paths::targets(&command("echo x > out.txt 2>&1"), &cwd),
+            [cwd.join("out.txt")]
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,60 @@
+//! Ambiente dos processos filhos
+//!
+//! Os comandos não herdam o ambiente completo do servidor: apenas as
+//! variáveis em `env_allowlist` são repassadas, e as que casam com
+//! `env_denylist` (tokens, senhas, chaves de API) nunca são.
+
+use crate::config::Config;
+use glob::{MatchOptions, Pattern};
+
+fn matches_any(patterns: &[String], name: &str, case_sensitive: bool) -> bool {
+    let options = MatchOptions {
+        case_sensitive,
+        ..Default::default()
+    };
+    patterns
+        .iter()
+        .filter_map(|p| Pattern::new(p).ok())
+        .any(|p| p.matches_with(name, options))
+}
+
+/// Filtra as variáveis de ambiente repassadas aos comandos
+pub fn child_env<I>(config: &Config, vars: I) -> Vec<(String, String)>
+where
+    I: IntoIterator<Item = (String, String)>,
+{
+    vars.into_iter()
+        .filter(|(name, _)| {
+            matches_any(&config.env_allowlist, name, true)
+                && !matches_any(&config.env_denylist, name, false)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_child_env() {
+        let config = Config {
+            env_allowlist: vec!["PATH".to_string(), "LC_*".to_string(), "MY_*".to_string()],
+            env_denylist: vec!["*token*".to_string()],
+            ..Default::default()
+        };
+        let vars = [
+            ("PATH", "/usr/bin"),
+            ("LC_ALL", "C"),
+            ("MY_API_TOKEN", "secret"),
+            ("MY_SETTING", "1"),
+            ("GITHUB_TOKEN", "secret"),
+        ]
+        .map(|(k, v)| (k.to_string(), v.to_string()));
+
+        let names: Vec<String> = child_env(&config, vars)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["PATH", "LC_ALL", "MY_SETTING"]);
+    }
+}
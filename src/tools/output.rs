@@ -0,0 +1,296 @@
+//! Limites de saída, truncamento e paginação dos resultados de comandos
+//!
+//! Quando stdout/stderr excedem os limites configurados, o resultado mostra
+//! apenas o início (ou o fim) da saída e a saída completa fica guardada em
+//! memória, identificada pelo `execution_id`, para ser lida em partes com a
+//! tool `get_command_output`.
+
+use rmcp::schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Qual parte da saída mostrar quando ela excede os limites
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+#[serde(rename_all = "lowercase")]
+pub enum Truncation {
+    #[default]
+    Head,
+    Tail,
+}
+
+/// Limites de exibição de uma stream
+#[derive(Clone, Copy, Debug)]
+pub struct Limits {
+    pub max_bytes: usize,
+    pub max_lines: usize,
+}
+
+/// Ajusta `pos` para trás até um limite de caractere UTF-8
+pub fn char_boundary(data: &[u8], mut pos: usize) -> usize {
+    while pos > 0 && pos < data.len() && (data[pos] & 0xC0) == 0x80 {
+        pos -= 1;
+    }
+    pos
+}
+
+/// Retorna o fim do trecho que começa em `start` e respeita os limites
+///
+/// O trecho tem ao menos um caractere inteiro, mesmo que ele sozinho passe de
+/// `max_bytes`; sem isso a paginação não avançaria.
+pub fn page_end(data: &[u8], start: usize, limits: Limits) -> usize {
+    let mut end = start;
+    let mut lines = 0;
+    while end < data.len() && lines < limits.max_lines {
+        let line_end = data[end..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map(|p| end + p + 1)
+            .unwrap_or(data.len());
+        if line_end - start > limits.max_bytes {
+            if end == start {
+                // Uma única linha maior que o limite é cortada no meio
+                end = char_boundary(data, start + limits.max_bytes);
+                if end == start {
+                    end += 1;
+                    while end < data.len() && (data[end] & 0xC0) == 0x80 {
+                        end += 1;
+                    }
+                }
+            }
+            break;
+        }
+        end = line_end;
+        lines += 1;
+    }
+    end
+}
+
+/// Retorna o início do trecho final de `data` que respeita os limites
+fn tail_start(data: &[u8], limits: Limits) -> usize {
+    let mut start = data.len();
+    let mut lines = 0;
+    while start > 0 && lines < limits.max_lines {
+        // A linha anterior termina na quebra de linha logo antes de `start`
+        // (a quebra de linha final da saída não conta como uma linha vazia)
+        let search_to = if start == data.len() {
+            start - usize::from(data.ends_with(b"\n"))
+        } else {
+            start - 1
+        };
+        let line_start = data[..search_to]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map(|p| p + 1)
+            .unwrap_or(0);
+        if data.len() - line_start > limits.max_bytes {
+            if lines == 0 {
+                // Uma única linha maior que o limite é cortada no meio
+                start = data.len() - limits.max_bytes;
+                while start < data.len() && (data[start] & 0xC0) == 0x80 {
+                    start += 1;
+                }
+            }
+            break;
+        }
+        start = line_start;
+        lines += 1;
+    }
+    start
+}
+
+/// Trecho de uma stream mostrado no resultado
+pub struct Excerpt {
+    pub text: String,
+    pub truncated: bool,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Seleciona a parte da saída a ser exibida
+pub fn excerpt(data: &[u8], limits: Limits, mode: Truncation) -> Excerpt {
+    let (start, end) = match mode {
+        Truncation::Head => (0, page_end(data, 0, limits)),
+        Truncation::Tail => (tail_start(data, limits), data.len()),
+    };
+    Excerpt {
+        text: String::from_utf8_lossy(&data[start..end]).to_string(),
+        truncated: start > 0 || end < data.len(),
+        start,
+        end,
+    }
+}
+
+/// Saída completa de uma execução guardada para paginação
+struct StoredOutput {
+    execution_id: String,
+    command: String,
+    stdout: Vec<u8>,
+    stderr: Vec<u8>,
+}
+
+/// Armazena em memória as saídas truncadas das últimas execuções
+#[derive(Clone, Default)]
+pub struct OutputStore {
+    entries: Arc<Mutex<VecDeque<StoredOutput>>>,
+}
+
+static EXECUTION_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// Gera um identificador único para uma execução
+pub fn new_execution_id() -> String {
+    format!(
+        "{}-{}",
+        chrono::Local::now().format("%Y%m%d%H%M%S"),
+        EXECUTION_COUNTER.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+impl OutputStore {
+    /// Guarda a saída, descartando as execuções mais antigas além de `retention`
+    pub async fn insert(
+        &self,
+        execution_id: &str,
+        command: &str,
+        stdout: Vec<u8>,
+        stderr: Vec<u8>,
+        retention: usize,
+    ) {
+        let mut entries = self.entries.lock().await;
+        entries.push_back(StoredOutput {
+            execution_id: execution_id.to_string(),
+            command: command.to_string(),
+            stdout,
+            stderr,
+        });
+        while entries.len() > retention {
+            entries.pop_front();
+        }
+    }
+
+    /// Lê um trecho da saída a partir do cursor (offset em bytes)
+    pub async fn page(
+        &self,
+        execution_id: &str,
+        stream: &str,
+        cursor: usize,
+        limits: Limits,
+    ) -> Result<Value, String> {
+        let entries = self.entries.lock().await;
+        let entry = entries
+            .iter()
+            .find(|e| e.execution_id == execution_id)
+            .ok_or_else(|| {
+                format!(
+                    "Execução '{}' não encontrada (a saída pode ter expirado)",
+                    execution_id
+                )
+            })?;
+
+        let data = match stream {
+            "stdout" => &entry.stdout,
+            "stderr" => &entry.stderr,
+            _ => {
+                return Err(format!(
+                    "Stream inválida '{}': use stdout ou stderr",
+                    stream
+                ))
+            }
+        };
+        if cursor > data.len() {
+            return Err(format!(
+                "Cursor {} fora da saída ({} bytes)",
+                cursor,
+                data.len()
+            ));
+        }
+
+        let start = char_boundary(data, cursor);
+        let end = page_end(data, start, limits);
+        Ok(json!({
+            "execution_id": entry.execution_id,
+            "command": entry.command,
+            "stream": stream,
+            "text": String::from_utf8_lossy(&data[start..end]),
+            "range": [start, end],
+            "total_bytes": data.len(),
+            "next_cursor": (end < data.len()).then(|| end.to_string()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIMITS: Limits = Limits {
+        max_bytes: 10,
+        max_lines: 2,
+    };
+
+    #[test]
+    fn test_excerpt_head_and_tail() {
+        let data = b"one\ntwo\nthree\nfour\n";
+
+        let head = excerpt(data, LIMITS, Truncation::Head);
+        assert_eq!(head.text, "one\ntwo\n");
+        assert!(head.truncated);
+
+        let tail = excerpt(data, LIMITS, Truncation::Tail);
+        assert_eq!(tail.text, "four\n");
+        assert!(tail.truncated);
+
+        let small = excerpt(b"ok\n", LIMITS, Truncation::Head);
+        assert_eq!(small.text, "ok\n");
+        assert!(!small.truncated);
+    }
+
+    #[test]
+    fn test_page_splits_long_lines_on_char_boundary() {
+        let data = "ééééééééé\n".as_bytes();
+        let end = page_end(data, 0, LIMITS);
+        assert_eq!(end, 10);
+        assert!(std::str::from_utf8(&data[..end]).is_ok());
+
+        // Limite menor que um caractere: a página ainda avança um caractere
+        let tiny = Limits {
+            max_bytes: 1,
+            max_lines: 2,
+        };
+        assert_eq!(page_end(data, 0, tiny), 2);
+        assert_eq!(page_end(data, 2, tiny), 4);
+        assert_eq!(page_end("€".as_bytes(), 0, tiny), 3);
+    }
+
+    #[tokio::test]
+    async fn test_store_pagination() {
+        let store = OutputStore::default();
+        store
+            .insert("x", "cmd", b"a\nb\nc\nd\ne\n".to_vec(), Vec::new(), 5)
+            .await;
+
+        let first = store.page("x", "stdout", 0, LIMITS).await.unwrap();
+        assert_eq!(first["text"], "a\nb\n");
+        assert_eq!(first["next_cursor"], "4");
+
+        let last = store.page("x", "stdout", 8, LIMITS).await.unwrap();
+        assert_eq!(last["text"], "e\n");
+        assert!(last["next_cursor"].is_null());
+
+        assert!(store.page("y", "stdout", 0, LIMITS).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_store_retention() {
+        let store = OutputStore::default();
+        for id in ["a", "b", "c"] {
+            store.insert(id, "cmd", Vec::new(), Vec::new(), 2).await;
+        }
+        assert!(store.page("a", "stdout", 0, LIMITS).await.is_err());
+        assert!(store.page("c", "stdout", 0, LIMITS).await.is_ok());
+    }
+}
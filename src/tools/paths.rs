@@ -0,0 +1,872 @@
+//! Política de caminhos
+//!
+//! Resolve os alvos de um comando (`~`, globs, caminhos relativos e symlinks) para
+//! caminhos canônicos e os compara com as raízes permitidas/negadas no config.toml.
+//! Isso substitui a antiga verificação por prefixo de string do "Safe RM".
+
+use super::shell::{Redirect, SimpleCommand, Word};
+use super::Violation;
+use crate::config::Config;
+use std::path::{Component, Path, PathBuf};
+
+/// Comandos que não abrem arquivos a partir dos argumentos, então podem receber expansões
+const NO_FILE_ARGS: &[&str] = &[
+    "echo", "date", "whoami", "id", "hostname", "uname", "uptime", "free", "ping",
+];
+
+/// Flags curtas que consomem o próximo argumento, por comando
+const VALUE_FLAGS: &[(&str, &[&str])] = &[
+    ("tail", &["-n", "-c", "-s", "--pid"]),
+    ("head", &["-n", "-c"]),
+    ("du", &["-d", "-t", "-B", "-X"]),
+    ("grep", &["-e", "-f", "-m", "-A", "-B", "-C", "-d", "-D"]),
+    ("sort", &["-o", "-k", "-t", "-S", "-T"]),
+    ("uniq", &["-f", "-s", "-w"]),
+    (
+        "journalctl",
+        &["-u", "-n", "-p", "-t", "-b", "-S", "-U", "-o"],
+    ),
+];
+
+/// Operadores de redirecionamento que escrevem em arquivos
+const WRITE_REDIRECTS: &[&str] = &[">", ">>", ">|", "<>", ">&"];
+
+/// Expande `~` para o diretório home e torna o caminho absoluto a partir de `cwd`
+pub fn expand(arg: &str, cwd: &Path) -> Result<PathBuf, String> {
+    let path = if arg == "~" || arg.starts_with("~/") {
+        let home = dirs::home_dir().ok_or("Não foi possível determinar o diretório home")?;
+        home.join(arg.trim_start_matches('~').trim_start_matches('/'))
+    } else if arg.starts_with('~') {
+        return Err(format!("Expansão '~usuario' não é suportada: '{}'", arg));
+    } else {
+        PathBuf::from(arg)
+    };
+
+    Ok(if path.is_absolute() {
+        path
+    } else {
+        cwd.join(path)
+    })
+}
+
+/// Canonicaliza um caminho mesmo que ele (ou parte dele) ainda não exista
+///
+/// O maior prefixo existente é resolvido pelo sistema (seguindo symlinks) e o
+/// restante é normalizado lexicamente. Com `follow_final = false`, o último
+/// componente não é seguido (ex: `rm` remove o symlink, não o alvo).
+pub fn canonicalize(path: &Path, follow_final: bool) -> PathBuf {
+    if !follow_final {
+        if let (Some(parent), Some(name)) = (path.parent(), path.file_name()) {
+            return canonicalize(parent, true).join(name);
+        }
+    }
+
+    let mut existing = path.to_path_buf();
+    let mut rest: Vec<Component> = Vec::new();
+    let components: Vec<Component> = path.components().collect();
+    let mut idx = components.len();
+    loop {
+        if let Ok(resolved) = std::fs::canonicalize(&existing) {
+            existing = resolved;
+            break;
+        }
+        if idx == 0 {
+            existing = PathBuf::from("/");
+            break;
+        }
+        idx -= 1;
+        rest.insert(0, components[idx]);
+        existing = components[..idx].iter().collect();
+    }
+
+    for component in rest {
+        match component {
+            Component::ParentDir => {
+                existing.pop();
+            }
+            Component::Normal(name) => existing.push(name),
+            _ => {}
+        }
+    }
+    existing
+}
+
+/// Resolve um argumento para todos os caminhos que ele representa (expandindo globs)
+fn resolve(word: &Word, cwd: &Path, follow_final: bool) -> Result<Vec<PathBuf>, String> {
+    let path = expand(&word.text, cwd)?;
+
+    if word.glob {
+        // O crate glob lê `[^x]` como os caracteres '^' e 'x' e não conhece as
+        // classes POSIX; o shell os trata como negação e classes
+        if ["[^", "[[:", "[[=", "[[."]
+            .iter()
+            .any(|bracket| word.text.contains(bracket))
+        {
+            return Err(format!(
+                "O glob '{}' usa uma expressão entre colchetes que não pode ser verificada (use [!...])",
+                word.text
+            ));
+        }
+        let pattern = path.to_string_lossy().to_string();
+        let matches: Vec<PathBuf> = glob::glob(&pattern)
+            .map_err(|e| format!("Glob inválido '{}': {}", word.text, e))?
+            .filter_map(|m| m.ok())
+            .collect();
+        // Sem correspondência o shell passaria o texto literal, que o
+        // comando pode interpretar de outra forma
+        if matches.is_empty() {
+            return Err(format!(
+                "O glob '{}' não corresponde a nenhum arquivo",
+                word.text
+            ));
+        }
+        return Ok(matches
+            .iter()
+            .map(|m| canonicalize(m, follow_final))
+            .collect());
+    }
+
+    Ok(vec![canonicalize(&path, follow_final)])
+}
+
+/// Regra de caminho do config.toml (caminho simples ou padrão glob)
+enum Rule {
+    Prefix(PathBuf),
+    Pattern(glob::Pattern),
+}
+
+impl Rule {
+    fn parse(rule: &str) -> Result<Self, String> {
+        let path = expand(rule, Path::new("/"))?;
+        if rule.contains(['*', '?', '[']) {
+            let pattern = glob::Pattern::new(&path.to_string_lossy())
+                .map_err(|e| format!("Regra de caminho inválida '{}': {}", rule, e))?;
+            Ok(Rule::Pattern(pattern))
+        } else {
+            Ok(Rule::Prefix(canonicalize(&path, true)))
+        }
+    }
+
+    /// O caminho é a própria regra ou está dentro dela
+    fn contains(&self, path: &Path) -> bool {
+        match self {
+            Rule::Prefix(root) => path.starts_with(root),
+            Rule::Pattern(pattern) => path.ancestors().any(|p| pattern.matches_path(p)),
+        }
+    }
+
+    /// O caminho é um ancestral da regra (ex: `grep -r /etc` alcança `/etc/shadow`)
+    ///
+    /// Para padrões, basta que cada componente do caminho corresponda ao
+    /// componente do padrão na mesma posição (ex: `/etc/ssh` e `/proc/1`
+    /// alcançam `/etc/ssh/ssh_host_*_key` e `/proc/*/environ`).
+    fn is_inside(&self, path: &Path) -> bool {
+        match self {
+            Rule::Prefix(root) => root.starts_with(path),
+            Rule::Pattern(pattern) => {
+                let rule: Vec<&str> = pattern.as_str().split('/').collect();
+                let path: Vec<_> = path.components().skip(1).collect();
+                path.len() < rule.len()
+                    && path.iter().zip(&rule[1..]).all(|(component, rule)| {
+                        glob::Pattern::new(rule)
+                            .is_ok_and(|p| p.matches(&component.as_os_str().to_string_lossy()))
+                    })
+            }
+        }
+    }
+}
+
+fn parse_rules(rules: &[String]) -> Result<Vec<Rule>, String> {
+    rules.iter().map(|r| Rule::parse(r)).collect()
+}
+
+fn is_flag(arg: &str) -> bool {
+    arg.starts_with('-') && arg != "-"
+}
+
+/// Flag de `value_flags` presente no argumento e o valor grudado a ela, como
+/// o getopt lê: `-n5` é `-n 5` e `-rf/x` é `-r -f /x`
+///
+/// `Some((flag, None))` indica que o valor é o próximo argumento.
+fn value_flag<'a>(arg: &'a str, value_flags: &[&str]) -> Option<(String, Option<&'a str>)> {
+    if value_flags.contains(&arg) {
+        return Some((arg.to_string(), None));
+    }
+    if arg.starts_with("--") || !is_flag(arg) {
+        return None;
+    }
+    for (i, letter) in arg.char_indices().skip(1) {
+        let flag = format!("-{}", letter);
+        if value_flags.contains(&flag.as_str()) {
+            let rest = &arg[i + letter.len_utf8()..];
+            return Some((flag, (!rest.is_empty()).then_some(rest)));
+        }
+    }
+    None
+}
+
+/// Possíveis valores grudados em flags curtas (`-f/etc/shadow`, `-rf/etc/shadow`)
+///
+/// Sem saber quais letras do comando recebem valor, todo sufixo após a
+/// primeira letra é um candidato.
+fn attached_values(arg: &str) -> Vec<&str> {
+    if arg.starts_with("--") || !is_flag(arg) {
+        return Vec::new();
+    }
+    arg.char_indices().skip(2).map(|(i, _)| &arg[i..]).collect()
+}
+
+/// Verifica se o comando percorre diretórios recursivamente
+fn is_recursive(name: &str, args: &[Word]) -> bool {
+    let letters: &[char] = match name {
+        "grep" | "rm" | "cp" | "chmod" | "chown" => &['r', 'R'],
+        "ls" => &['R'],
+        "find" => return true,
+        _ => return false,
+    };
+    let flag = args.iter().any(|a| {
+        a.text == "--recursive"
+            || a.text == "--dereference-recursive"
+            || (a.text.starts_with('-') && !a.text.starts_with("--") && a.text.contains(letters))
+    });
+    if flag || name != "grep" {
+        return flag;
+    }
+
+    // grep -d recurse, -drecurse e --directories=recurse equivalem a -r
+    args.iter().enumerate().any(|(i, a)| {
+        let next = || args.get(i + 1).map(|w| w.text.as_str());
+        match a.text.split_once('=') {
+            Some(("--directories", value)) => value == "recurse",
+            _ if a.text == "--directories" => next() == Some("recurse"),
+            _ => match value_flag(&a.text, &["-d"]) {
+                Some((_, Some(value))) => value == "recurse",
+                Some((_, None)) => next() == Some("recurse"),
+                None => false,
+            },
+        }
+    })
+}
+
+/// Extrai os operandos de caminho dos argumentos de um comando
+fn path_operands(name: &str, args: &[Word]) -> Vec<Word> {
+    if name == "find" {
+        // Caminhos vêm antes da expressão (primeiro argumento iniciado por -, ( ou !)
+        return args
+            .iter()
+            .skip_while(|a| matches!(a.text.as_str(), "-H" | "-L" | "-P"))
+            .take_while(|a| !is_flag(&a.text) && a.text != "(" && a.text != "!")
+            .cloned()
+            .collect();
+    }
+
+    let value_flags = VALUE_FLAGS
+        .iter()
+        .find(|(cmd, _)| *cmd == name)
+        .map(|(_, flags)| *flags)
+        .unwrap_or(&[]);
+
+    let mut operands = Vec::new();
+    let mut iter = args.iter();
+    let mut pattern_given = false;
+    while let Some(arg) = iter.next() {
+        if arg.text == "--" {
+            operands.extend(iter.by_ref().cloned());
+            break;
+        }
+        if is_flag(&arg.text) {
+            if let Some((flag, attached)) = value_flag(&arg.text, value_flags) {
+                let value = match attached {
+                    Some(text) => Some(Word {
+                        text: text.to_string(),
+                        ..arg.clone()
+                    }),
+                    None => iter.next().cloned(),
+                };
+                match flag.as_str() {
+                    "-e" if name == "grep" => pattern_given = true,
+                    "-f" if name == "grep" => {
+                        pattern_given = true;
+                        operands.extend(value);
+                    }
+                    _ => {}
+                }
+            }
+            continue;
+        }
+        operands.push(arg.clone());
+    }
+
+    // No grep, o primeiro operando é o padrão (a menos que -e/-f tenham sido usados)
+    if name == "grep" && !pattern_given && !operands.is_empty() {
+        operands.remove(0);
+    }
+    operands
+}
+
+/// Se o `rm` alcança o alvo do symlink em vez do próprio symlink
+///
+/// `rm -r link/` e `rm -r link/.` esvaziam o diretório apontado. Com `rm`
+/// recursivo, um symlink também é verificado pelo seu alvo.
+fn rm_follows(word: &Word, recursive: bool) -> bool {
+    word.text.ends_with('/') || word.text.ends_with("/.") || recursive
+}
+
+/// Regras de uma chave do config, com o nome da chave nos erros
+fn parse_key(key: &str, rules: &[String]) -> Result<Vec<(String, Rule)>, Violation> {
+    let parsed = parse_rules(rules).map_err(|reason| Violation::new(key, reason))?;
+    Ok(parsed
+        .into_iter()
+        .map(|rule| (key.to_string(), rule))
+        .collect())
+}
+
+/// Arquivos que o comando escreve por conta própria, fora de redirecionamentos
+/// (`sort -o arquivo`, segundo operando do `uniq`)
+fn write_operands(name: &str, args: &[Word]) -> Vec<Word> {
+    match name {
+        "sort" => {
+            let mut targets = Vec::new();
+            let mut iter = args.iter();
+            while let Some(arg) = iter.next() {
+                if arg.text == "--" {
+                    break;
+                }
+                let value = match arg.text.split_once('=') {
+                    Some(("--output", value)) => Some(value.to_string()),
+                    _ if arg.text == "--output" => iter.next().map(|w| w.text.clone()),
+                    _ => match value_flag(&arg.text, &["-o", "-k", "-t", "-S", "-T"]) {
+                        Some((flag, attached)) => {
+                            let value = match attached {
+                                Some(text) => Some(text.to_string()),
+                                None => iter.next().map(|w| w.text.clone()),
+                            };
+                            value.filter(|_| flag == "-o")
+                        }
+                        None => None,
+                    },
+                };
+                targets.extend(value.map(|text| Word {
+                    text,
+                    ..arg.clone()
+                }));
+            }
+            targets
+        }
+        "uniq" => path_operands(name, args)
+            .into_iter()
+            .skip(1)
+            .take(1)
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Caminhos que nenhum comando alcança, além de `denied_paths`, com a regra
+/// de cada um: os arquivos do audit log, a chave do HMAC (`audit`), os
+/// arquivos de configuração e seus diretórios (`config`) e a chave privada do
+/// transporte HTTP (`http.tls_key`)
+pub fn protected_paths(config: &Config) -> anyhow::Result<Vec<(&'static str, String)>> {
+    let mut paths: Vec<(&str, String)> = crate::audit::protected_paths(config)?
+        .into_iter()
+        .map(|path| ("audit", path))
+        .collect();
+    // Com o hot-reload, escrever na configuração seria mudar a própria política
+    let mut files = vec![
+        PathBuf::from(crate::config::SYSTEM_CONFIG_PATH),
+        crate::config::get_config_path()?,
+    ];
+    files.extend(config.config_files.iter().cloned());
+    for file in files {
+        let dir = file.parent().map(|dir| dir.display().to_string());
+        paths.extend(dir.into_iter().map(|dir| ("config", dir)));
+        paths.push(("config", file.display().to_string()));
+    }
+    if let Some(key) = &config.http.tls_key {
+        let key = crate::config::get_config_dir()?.join(key);
+        paths.push(("http.tls_key", key.display().to_string()));
+    }
+    Ok(paths)
+}
+
+/// Resolve um argumento; `~usuario` e globs inválidos não podem ser verificados
+fn resolve_checked(word: &Word, cwd: &Path, follow_final: bool) -> Result<Vec<PathBuf>, Violation> {
+    resolve(word, cwd, follow_final).map_err(|reason| Violation::new("expansions", reason))
+}
+
+/// Aplica a política de caminhos a um comando simples
+pub fn check_paths(
+    name: Option<&str>,
+    command: &SimpleCommand,
+    config: &Config,
+    cwd: &Path,
+) -> Result<(), Violation> {
+    let args = if command.words.is_empty() {
+        &[][..]
+    } else {
+        &command.words[1..]
+    };
+    let policies = name.map(|n| config.command_policies(n)).unwrap_or_default();
+
+    let mut denied = parse_key("denied_paths", &config.denied_paths)?;
+    let protected = protected_paths(config).map_err(|e| Violation::new("audit", e.to_string()))?;
+    for (key, path) in protected {
+        denied.extend(parse_key(key, &[path])?);
+    }
+    for (rule, policy) in &policies {
+        denied.extend(parse_key(
+            &format!("{}.denied_paths", rule),
+            &policy.denied_paths,
+        )?);
+    }
+
+    let check_denied = |word: &Word, path: &Path, recursive: bool| -> Result<(), Violation> {
+        for (key, rule) in &denied {
+            if rule.contains(path) || (recursive && rule.is_inside(path)) {
+                return Err(Violation::new(
+                    key.as_str(),
+                    format!(
+                        "O caminho '{}' ({}) é protegido pela política de caminhos",
+                        word.text,
+                        path.display()
+                    ),
+                ));
+            }
+        }
+        Ok(())
+    };
+
+    let writable = parse_rules(&config.writable_paths)
+        .map_err(|reason| Violation::new("writable_paths", reason))?;
+    let check_writable = |path: &Path| -> Result<(), Violation> {
+        if writable.iter().any(|r| r.contains(path)) {
+            return Ok(());
+        }
+        Err(Violation::new(
+            "writable_paths",
+            format!(
+                "Escrita em '{}' não é permitida (veja 'writable_paths')",
+                path.display()
+            ),
+        ))
+    };
+
+    if let Some(name) = name {
+        if !NO_FILE_ARGS.contains(&name) {
+            if let Some(arg) = args.iter().find(|a| a.dynamic) {
+                return Err(Violation::new(
+                    "expansions",
+                    format!(
+                        "O argumento '{}' de '{}' depende de expansões e não pode ser verificado pela política de caminhos",
+                        arg.text, name
+                    ),
+                ));
+            }
+        }
+
+        // Caminhos sensíveis: qualquer argumento (ou valor de --opcao=valor e
+        // valores grudados em flags curtas) é verificado
+        let recursive = is_recursive(name, args);
+        for arg in args.iter().filter(|a| !a.dynamic) {
+            let texts = match arg.text.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => vec![value],
+                _ if is_flag(&arg.text) => attached_values(&arg.text),
+                _ => vec![arg.text.as_str()],
+            };
+            for text in texts {
+                let word = Word {
+                    text: text.to_string(),
+                    ..arg.clone()
+                };
+                for path in resolve_checked(&word, cwd, true)? {
+                    check_denied(arg, &path, recursive)?;
+                }
+            }
+        }
+
+        // Raízes permitidas para os operandos do comando (os de cada política)
+        let mut allowed_sets = Vec::new();
+        for (rule, policy) in &policies {
+            let key = format!("{}.allowed_paths", rule);
+            let allowed = parse_rules(&policy.allowed_paths)
+                .map_err(|reason| Violation::new(key.as_str(), reason))?;
+            if !allowed.is_empty() {
+                allowed_sets.push((key, allowed));
+            }
+        }
+        let operands = path_operands(name, args);
+
+        for target in write_operands(name, args) {
+            for path in resolve_checked(&target, cwd, true)? {
+                check_denied(&target, &path, false)?;
+                check_writable(&path)?;
+            }
+        }
+
+        if name == "rm" {
+            let allowed_key = config.rule_key("commands.rm.allowed_paths");
+            if allowed_sets.is_empty() {
+                return Err(Violation::new(
+                    allowed_key,
+                    "O comando 'rm' exige 'allowed_paths' em [commands.rm]",
+                ));
+            }
+            if operands.is_empty() {
+                return Err(Violation::new(
+                    allowed_key,
+                    "O comando 'rm' exige ao menos um alvo",
+                ));
+            }
+        }
+
+        for (allowed_key, allowed) in &allowed_sets {
+            for operand in &operands {
+                // rm remove o symlink, não o alvo (veja `rm_follows`), e nunca
+                // a própria raiz
+                let follow = name != "rm" || rm_follows(operand, recursive);
+                let paths = resolve_checked(operand, cwd, follow)?;
+                for path in paths {
+                    let inside = allowed.iter().any(|root| match root {
+                        Rule::Prefix(root) if name == "rm" => {
+                            path.starts_with(root) && path != *root
+                        }
+                        _ => root.contains(&path),
+                    });
+                    if !inside {
+                        return Err(Violation::new(
+                            allowed_key.as_str(),
+                            format!(
+                                "O caminho '{}' ({}) está fora das raízes permitidas para '{}'",
+                                operand.text,
+                                path.display(),
+                                name
+                            ),
+                        ));
+                    }
+                    check_denied(operand, &path, false)?;
+                }
+            }
+        }
+    }
+
+    // Redirecionamentos: leitura/escrita de arquivos pelo próprio shell
+    for redirect in &command.redirects {
+        let target = &redirect.target;
+        let op = redirect_op(redirect);
+
+        if !is_file_redirect(redirect) {
+            continue;
+        }
+        if target.dynamic {
+            return Err(Violation::new(
+                "expansions",
+                format!(
+                    "O destino do redirecionamento '{}' depende de expansões e não pode ser verificado",
+                    redirect.op
+                ),
+            ));
+        }
+
+        for path in resolve_checked(target, cwd, true)? {
+            check_denied(target, &path, false)?;
+            if WRITE_REDIRECTS.contains(&op) {
+                check_writable(&path)?;
+            }
+        }
+    }
+
+    // Raízes do perfil ativo: nenhum alvo pode ficar fora delas
+    if let Some((key, roots)) = profile_roots(config)? {
+        for path in targets(command, cwd) {
+            check_root(&key, &roots, &path)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Raízes de `path_roots` do perfil ativo, com a chave usada nos erros
+fn profile_roots(config: &Config) -> Result<Option<(String, Vec<Rule>)>, Violation> {
+    let Some((name, roots)) = config
+        .profile()
+        .and_then(|(name, profile)| Some((name, profile.path_roots.as_ref()?)))
+    else {
+        return Ok(None);
+    };
+    let key = format!("profiles.{}.path_roots", name);
+    let roots = parse_rules(roots).map_err(|reason| Violation::new(key.as_str(), reason))?;
+    Ok(Some((key, roots)))
+}
+
+fn check_root(key: &str, roots: &[Rule], path: &Path) -> Result<(), Violation> {
+    if roots.iter().any(|root| root.contains(path)) {
+        return Ok(());
+    }
+    Err(Violation::new(
+        key,
+        format!(
+            "O caminho '{}' está fora das raízes permitidas pelo perfil",
+            path.display()
+        ),
+    ))
+}
+
+/// Operador do redirecionamento, sem o descritor (ex: `2>` -> `>`)
+fn redirect_op(redirect: &Redirect) -> &str {
+    redirect.op.trim_start_matches(|c: char| c.is_ascii_digit())
+}
+
+/// Se o redirecionamento lê ou escreve um arquivo
+///
+/// Here-strings e duplicação de descritores (2>&1, >&-) não envolvem arquivos.
+fn is_file_redirect(redirect: &Redirect) -> bool {
+    let op = redirect_op(redirect);
+    let fd_dup = redirect
+        .target
+        .text
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == '-');
+    !(op == "<<<" || op == "<&" || (op == ">&" && fd_dup))
+}
+
+/// Caminhos que um comando (já permitido) deve alcançar, resolvidos
+///
+/// Usado para mostrar ao usuário o que será afetado. Operandos que não
+/// existem e não parecem caminhos (ex: `restart` em `systemctl restart`)
+/// são omitidos.
+pub fn targets(command: &SimpleCommand, cwd: &Path) -> Vec<PathBuf> {
+    let mut words = Vec::new();
+    let mut rm = None;
+    if let Some((first, args)) = command.words.split_first() {
+        let name = Path::new(&first.text)
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or(&first.text);
+        if !NO_FILE_ARGS.contains(&name) {
+            words.extend(path_operands(name, args));
+        }
+        if name == "rm" {
+            rm = Some(is_recursive(name, args));
+        }
+    }
+
+    let mut paths = Vec::new();
+    for word in words.into_iter().filter(|w| !w.dynamic) {
+        let looks_like_path = word.text.contains('/') || word.text.starts_with('~');
+        let follow_final = rm.is_none_or(|recursive| rm_follows(&word, recursive));
+        for path in resolve(&word, cwd, follow_final).unwrap_or_default() {
+            if looks_like_path || path.symlink_metadata().is_ok() {
+                paths.push(path);
+            }
+        }
+    }
+    for redirect in command.redirects.iter().filter(|r| is_file_redirect(r)) {
+        paths.extend(resolve(&redirect.target, cwd, true).unwrap_or_default());
+    }
+    paths
+}
+
+/// Valida o diretório de trabalho solicitado e retorna seu caminho canônico
+///
+/// O diretório precisa existir, não pode estar dentro de `denied_paths` e,
+/// com um perfil com `path_roots`, precisa estar dentro de uma das raízes.
+pub fn check_cwd(dir: &str, config: &Config, cwd: &Path) -> Result<PathBuf, Violation> {
+    let path = expand(dir, cwd).map_err(|reason| Violation::new("cwd", reason))?;
+    let path = canonicalize(&path, true);
+    if !path.is_dir() {
+        return Err(Violation::new(
+            "cwd",
+            format!(
+                "O diretório de trabalho '{}' não existe ou não é um diretório",
+                dir
+            ),
+        ));
+    }
+    if parse_key("denied_paths", &config.denied_paths)?
+        .iter()
+        .any(|(_, rule)| rule.contains(&path))
+    {
+        return Err(Violation::new(
+            "denied_paths",
+            format!(
+                "O diretório de trabalho '{}' ({}) é protegido pela política de caminhos",
+                dir,
+                path.display()
+            ),
+        ));
+    }
+    if let Some((key, roots)) = profile_roots(config)? {
+        check_root(&key, &roots, &path)?;
+    }
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::shell;
+
+    fn check(command_line: &str) -> Result<(), Violation> {
+        let config = Config::default();
+        for command in shell::parse(command_line).unwrap() {
+            let name = command.words.first().map(|w| w.text.clone());
+            check_paths(name.as_deref(), &command, &config, Path::new("/"))?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_canonicalize_nonexistent() {
+        assert_eq!(
+            canonicalize(Path::new("/nonexistent-dir/a/../b"), true),
+            PathBuf::from("/nonexistent-dir/b")
+        );
+        assert_eq!(
+            canonicalize(Path::new("/tmp/../etc/shadow"), true),
+            canonicalize(Path::new("/etc/shadow"), true)
+        );
+    }
+
+    #[test]
+    fn test_denied_paths() {
+        assert!(check("cat /etc/hostname").is_ok());
+        assert!(check("cat /etc/shadow").is_err());
+        assert!(check("cat /tmp/../etc/shadow").is_err());
+        assert!(check("grep root /etc/./shadow").is_err());
+        assert!(check("tail -n 5 ~/.ssh/id_rsa").is_err());
+        assert!(check("grep -r root /etc").is_err());
+        assert!(check("ls -lrt /etc").is_ok());
+        assert!(check("cat /proc/self/environ").is_err());
+        assert!(check("cat < /etc/shadow").is_err());
+        assert!(check("cat $HOME/.ssh/id_rsa").is_err());
+        assert!(check("echo $HOME").is_ok());
+    }
+
+    #[test]
+    fn test_grep_directories_recurse() {
+        assert!(check("grep -d recurse root /etc").is_err());
+        assert!(check("grep -drecurse root /etc").is_err());
+        assert!(check("grep -idrecurse root /etc").is_err());
+        assert!(check("grep --directories=recurse root /etc").is_err());
+        assert!(check("grep --directories recurse root /etc").is_err());
+        assert!(check("grep -d skip root /etc").is_ok());
+        assert!(check("grep --directories=read root /etc").is_ok());
+    }
+
+    #[test]
+    fn test_recursive_pattern_rules() {
+        // /etc/ssh/ssh_host_*_key fica dentro de /etc/ssh
+        assert!(check("grep -r PRIVATE /etc/ssh").is_err());
+        assert!(check("ls -R /etc/ssh").is_err());
+        assert!(check("find /etc/ssh -name '*_key'").is_err());
+        assert!(check("grep -r x /proc/1").is_err());
+        assert!(check("grep -r x /proc/1/net").is_ok());
+        assert!(check("ls -l /etc/ssh").is_ok());
+    }
+
+    #[test]
+    fn test_globs() {
+        assert!(check("cat /etc/passw?").is_ok());
+        // Negação com ^ e classes POSIX não são avaliadas como no shell
+        assert!(check("cat /etc/shado[^x]").is_err());
+        assert!(check("cat /etc/sha[[:alpha:]]ow").is_err());
+        assert!(check("cat /etc/sha[!x]ow").is_err());
+        // Sem correspondência, o texto literal não é verificado como caminho
+        assert!(check("cat /nonexistent-dir/*.log").is_err());
+        assert!(check("cat '/nonexistent-dir/*.log'").is_ok());
+    }
+
+    #[test]
+    fn test_attached_flag_values() {
+        assert!(check("grep -f/etc/shadow x /tmp").is_err());
+        assert!(check("grep -if/etc/shadow x /tmp").is_err());
+        assert!(check("tail -n5 /etc/hostname").is_ok());
+        assert!(check("ls -lrt /etc").is_ok());
+
+        let args = shell::parse("grep -ifpatterns.txt -A3 x /tmp").unwrap()[0].words[1..].to_vec();
+        let operands: Vec<String> = path_operands("grep", &args)
+            .into_iter()
+            .map(|w| w.text)
+            .collect();
+        assert_eq!(operands, ["patterns.txt", "x", "/tmp"]);
+    }
+
+    #[test]
+    fn test_rm_roots() {
+        assert!(check("rm -rf /tmp/junk").is_ok());
+        assert!(check("rm -f ~/.cache/mozilla").is_ok());
+        assert!(check("rm -rf /tmp").is_err());
+        assert!(check("rm -rf /tmp/../etc").is_err());
+        assert!(check("rm /etc/x/.cache/y").is_err());
+        assert!(check("rm -rf").is_err());
+    }
+
+    #[test]
+    fn test_rm_symlink_target() {
+        let link = std::env::temp_dir().join(format!("linux-mcp-link-{}", std::process::id()));
+        std::os::unix::fs::symlink("/etc", &link).unwrap();
+        let link = link.display();
+        let results = [
+            check(&format!("rm -rf {}/", link)).is_err(),
+            check(&format!("rm -rf {}/.", link)).is_err(),
+            check(&format!("rm -r {}", link)).is_err(),
+            check(&format!("rm -f {}", link)).is_ok(),
+        ];
+        std::fs::remove_file(link.to_string()).unwrap();
+        assert_eq!(results, [true; 4]);
+    }
+
+    #[test]
+    fn test_check_cwd() {
+        let config = Config::default();
+        assert_eq!(
+            check_cwd("/tmp/..", &config, Path::new("/")).unwrap(),
+            canonicalize(Path::new("/"), true)
+        );
+        assert!(check_cwd("/nonexistent-dir", &config, Path::new("/")).is_err());
+        assert!(check_cwd("/etc/hostname", &config, Path::new("/")).is_err());
+
+        let config = Config {
+            denied_paths: vec!["/tmp".to_string()],
+            ..Default::default()
+        };
+        assert!(check_cwd("tmp", &config, Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn test_redirect_writes() {
+        assert!(check("ls 2>/dev/null").is_ok());
+        assert!(check("ls > /tmp/out.txt 2>&1").is_ok());
+        assert!(check("echo x >> ~/.bashrc").is_err());
+    }
+
+    #[test]
+    fn test_command_writes() {
+        assert!(check("sort -o /tmp/sorted.txt /etc/hostname").is_ok());
+        assert!(check("sort -o ~/.bashrc /tmp/x").is_err());
+        assert!(check("sort -ru -o~/.bashrc /tmp/x").is_err());
+        assert!(check("sort --output=/root/.bashrc /tmp/x").is_err());
+        assert!(check("sort -k 2 -t , /etc/hostname").is_ok());
+        assert!(check("uniq /etc/hostname /tmp/uniq.txt").is_ok());
+        assert!(check("uniq -f 1 /tmp/x ~/.bashrc").is_err());
+    }
+
+    #[test]
+    fn test_config_files_protected() {
+        let user = crate::config::get_config_path().unwrap();
+        let user = user.display();
+        assert!(check(&format!("sort -o {} /tmp/evil", user)).is_err());
+        assert!(check(&format!("uniq /tmp/evil {}", user)).is_err());
+        assert!(check(&format!("cat {}", user)).is_err());
+        assert!(check("cat /etc/linux-mcp/config.toml").is_err());
+
+        let config = Config {
+            config_files: vec![PathBuf::from("/nonexistent-dir/extra.toml")],
+            writable_paths: vec!["/".to_string()],
+            ..Default::default()
+        };
+        let command = &shell::parse("tee /nonexistent-dir/x").unwrap()[0];
+        let err = check_paths(Some("tee"), command, &config, Path::new("/")).unwrap_err();
+        assert_eq!(err.rule, "config");
+    }
+}
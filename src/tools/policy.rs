@@ -0,0 +1,162 @@
+//! Políticas de argumentos por comando definidas em `[commands.<nome>]` no config.toml
+
+use super::shell::Word;
+use crate::config::CommandPolicy;
+use regex::Regex;
+
+/// Verifica se uma flag proibida aparece em um argumento
+///
+/// Flags curtas de uma letra (ex: `-f`) também são detectadas quando combinadas (`-rf`).
+/// Flags longas são detectadas na forma `--flag=valor`.
+fn matches_flag(arg: &str, flag: &str) -> bool {
+    if arg == flag || arg.starts_with(&format!("{}=", flag)) {
+        return true;
+    }
+
+    let short = flag.len() == 2 && flag.starts_with('-') && flag != "--";
+    short && arg.starts_with('-') && !arg.starts_with("--") && arg[1..].contains(&flag[1..])
+}
+
+/// Aplica a política de argumentos a um comando (args não inclui o binário)
+pub fn check_arguments(
+    name: &str,
+    policy: &CommandPolicy,
+    args: &[Word],
+    use_polkit: bool,
+) -> Result<(), String> {
+    if policy.requires_polkit && !use_polkit {
+        return Err(format!(
+            "O comando '{}' exige elevação via PolicyKit (use_polkit = true)",
+            name
+        ));
+    }
+
+    // Argumentos com variáveis ou substituições não podem ser verificados
+    if let Some(arg) = args.iter().find(|arg| arg.dynamic) {
+        return Err(format!(
+            "O argumento '{}' de '{}' depende de expansões e não pode ser verificado pela política",
+            arg.text, name
+        ));
+    }
+
+    let subcommand = args.iter().find(|arg| !arg.text.starts_with('-'));
+    if let Some(sub) = subcommand {
+        if policy.denied_subcommands.contains(&sub.text) {
+            return Err(format!(
+                "O subcomando '{} {}' é bloqueado pela política",
+                name, sub.text
+            ));
+        }
+    }
+    if !policy.allowed_subcommands.is_empty() {
+        match subcommand {
+            Some(sub) if policy.allowed_subcommands.contains(&sub.text) => {}
+            Some(sub) => {
+                return Err(format!(
+                    "O subcomando '{} {}' não está entre os permitidos: {}",
+                    name,
+                    sub.text,
+                    policy.allowed_subcommands.join(", ")
+                ))
+            }
+            None => {
+                return Err(format!(
+                    "O comando '{}' exige um subcomando permitido",
+                    name
+                ))
+            }
+        }
+    }
+
+    for arg in args {
+        // Após "--" os argumentos não são mais flags
+        if arg.text == "--" {
+            break;
+        }
+        if let Some(flag) = policy
+            .forbidden_flags
+            .iter()
+            .find(|flag| matches_flag(&arg.text, flag))
+        {
+            return Err(format!("A flag '{}' é proibida para '{}'", flag, name));
+        }
+    }
+
+    let compile = |patterns: &[String]| -> Result<Vec<Regex>, String> {
+        patterns
+            .iter()
+            .map(|p| Regex::new(p).map_err(|e| format!("Padrão inválido '{}': {}", p, e)))
+            .collect()
+    };
+    let denied = compile(&policy.denied_arg_patterns)?;
+    let allowed = compile(&policy.allowed_arg_patterns)?;
+
+    for arg in args {
+        if let Some(re) = denied.iter().find(|re| re.is_match(&arg.text)) {
+            return Err(format!(
+                "O argumento '{}' de '{}' casa com o padrão proibido '{}'",
+                arg.text,
+                name,
+                re.as_str()
+            ));
+        }
+        if !allowed.is_empty() && !allowed.iter().any(|re| re.is_match(&arg.text)) {
+            return Err(format!(
+                "O argumento '{}' de '{}' não casa com nenhum padrão permitido",
+                arg.text, name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn check(policy: &CommandPolicy, args: &[&str], use_polkit: bool) -> Result<(), String> {
+        let words: Vec<Word> = args
+            .iter()
+            .map(|a| Word {
+                text: a.to_string(),
+                dynamic: false,
+                glob: false,
+            })
+            .collect();
+        check_arguments("cmd", policy, &words, use_polkit)
+    }
+
+    #[test]
+    fn test_subcommands_and_flags() {
+        let policy = CommandPolicy {
+            allowed_subcommands: vec!["status".to_string(), "restart".to_string()],
+            denied_subcommands: vec!["poweroff".to_string()],
+            forbidden_flags: vec!["-f".to_string(), "--force".to_string()],
+            ..Default::default()
+        };
+
+        assert!(check(&policy, &["--user", "status", "x"], false).is_ok());
+        assert!(check(&policy, &["poweroff"], false).is_err());
+        assert!(check(&policy, &["stop", "x"], false).is_err());
+        assert!(check(&policy, &[], false).is_err());
+        assert!(check(&policy, &["restart", "-qf", "x"], false).is_err());
+        assert!(check(&policy, &["restart", "--force=yes"], false).is_err());
+        assert!(check(&policy, &["restart", "--", "-f"], false).is_ok());
+    }
+
+    #[test]
+    fn test_patterns_and_polkit() {
+        let policy = CommandPolicy {
+            allowed_arg_patterns: vec!["^-".to_string(), "^/var/log/".to_string()],
+            denied_arg_patterns: vec!["\\.\\.".to_string()],
+            requires_polkit: true,
+            ..Default::default()
+        };
+
+        assert!(check(&policy, &["-n", "/var/log/syslog"], true).is_ok());
+        assert!(check(&policy, &["/var/log/syslog"], false).is_err());
+        assert!(check(&policy, &["/etc/passwd"], true).is_err());
+        assert!(check(&policy, &["/var/log/../../etc/passwd"], true).is_err());
+    }
+}
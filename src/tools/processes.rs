@@ -0,0 +1,422 @@
+//! Inspeção de processos sem `ps` (tools `list_processes` e `process_tree`)
+//!
+//! Os dados vêm do cache do `sysinfo` compartilhado pelo servidor; o uso de
+//! CPU é a média desde a atualização anterior do cache. Descritores abertos e
+//! cgroup são lidos de `/proc` só para os processos devolvidos.
+
+use crate::config::Config;
+use crate::redact::Redactor;
+use regex::Regex;
+use rmcp::model::*;
+use rmcp::schemars::JsonSchema;
+use rmcp::ErrorData;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users};
+use tokio::sync::Mutex;
+
+/// Processos devolvidos quando `limit` não é informado
+const DEFAULT_LIMIT: usize = 50;
+/// Limite máximo de processos por consulta
+const MAX_LIMIT: usize = 1000;
+/// Limite de nós de uma árvore de processos
+const MAX_TREE_NODES: usize = 2000;
+
+/// Critério de ordenação de `list_processes`
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[schemars(crate = "rmcp::schemars")]
+#[serde(rename_all = "snake_case")]
+pub enum ProcessSort {
+    /// Maior uso de CPU primeiro
+    #[default]
+    Cpu,
+    /// Maior memória residente (RSS) primeiro
+    Memory,
+    /// Menor PID primeiro
+    Pid,
+    /// Iniciados mais recentemente primeiro
+    StartTime,
+    /// Ordem alfabética do nome
+    Name,
+}
+
+/// Estrutura para os argumentos do tool de listagem de processos
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct ListProcessesArgs {
+    /// Regex aplicada ao nome do processo (ex: "^nginx", "python|node")
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Usuário dono do processo, por nome ou UID (ex: "www-data", "0")
+    #[serde(default)]
+    pub user: Option<String>,
+    /// Uso mínimo de CPU em porcentagem de um núcleo (ex: 5.0)
+    #[serde(default)]
+    pub min_cpu_percent: Option<f32>,
+    /// Memória residente mínima em MiB (ex: 100)
+    #[serde(default)]
+    pub min_memory_mb: Option<u64>,
+    /// Ordenação: "cpu" (padrão), "memory", "pid", "start_time" ou "name"
+    #[serde(default)]
+    pub sort_by: Option<ProcessSort>,
+    /// Quantidade máxima de processos (padrão: 50, máximo: 1000)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// Estrutura para os argumentos do tool de árvore de processos
+#[derive(Debug, Default, Deserialize, Serialize, JsonSchema)]
+#[schemars(crate = "rmcp::schemars")]
+pub struct ProcessTreeArgs {
+    /// PID da raiz da árvore (padrão: 1)
+    #[serde(default)]
+    pub pid: Option<u32>,
+    /// Profundidade máxima abaixo da raiz (padrão: sem limite)
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+}
+
+/// Dados de um processo lidos do cache do `sysinfo`
+#[derive(Clone, Debug, Default)]
+struct Row {
+    pid: u32,
+    ppid: Option<u32>,
+    name: String,
+    cmdline: Vec<String>,
+    uid: Option<u32>,
+    user: Option<String>,
+    state: String,
+    cpu_percent: f32,
+    rss_bytes: u64,
+    /// Segundos desde a época Unix
+    start_time: u64,
+}
+
+/// Atualiza o cache e devolve os processos (sem threads)
+async fn snapshot(system: &Mutex<System>) -> Vec<Row> {
+    let mut sys = system.lock().await;
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::new()
+            .with_cpu()
+            .with_memory()
+            .with_user(UpdateKind::OnlyIfNotSet)
+            .with_cmd(UpdateKind::OnlyIfNotSet),
+    );
+    let users = Users::new_with_refreshed_list();
+
+    sys.processes()
+        .values()
+        .filter(|process| process.thread_kind().is_none())
+        .map(|process| Row {
+            pid: process.pid().as_u32(),
+            ppid: process.parent().map(|pid| pid.as_u32()),
+            name: process.name().to_string_lossy().into_owned(),
+            cmdline: process
+                .cmd()
+                .iter()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect(),
+            uid: process.user_id().map(|uid| **uid),
+            user: process
+                .user_id()
+                .and_then(|uid| users.get_user_by_id(uid))
+                .map(|user| user.name().to_string()),
+            state: process.status().to_string(),
+            cpu_percent: process.cpu_usage(),
+            rss_bytes: process.memory(),
+            start_time: process.start_time(),
+        })
+        .collect()
+}
+
+fn invalid(message: String) -> ErrorData {
+    ErrorData::new(ErrorCode::INVALID_PARAMS, message, None)
+}
+
+/// Aplica os filtros e a ordenação, sem o limite
+fn select(mut rows: Vec<Row>, args: &ListProcessesArgs) -> Result<Vec<Row>, ErrorData> {
+    let name = args
+        .name
+        .as_deref()
+        .map(Regex::new)
+        .transpose()
+        .map_err(|e| invalid(format!("Invalid name regex: {}", e)))?;
+    let min_rss = args.min_memory_mb.map(|mb| mb.saturating_mul(1024 * 1024));
+
+    rows.retain(|row| {
+        name.as_ref().is_none_or(|re| re.is_match(&row.name))
+            && args.user.as_deref().is_none_or(|user| {
+                row.user.as_deref() == Some(user)
+                    || row.uid.is_some_and(|uid| uid.to_string() == user)
+            })
+            && args
+                .min_cpu_percent
+                .is_none_or(|min| row.cpu_percent >= min)
+            && min_rss.is_none_or(|min| row.rss_bytes >= min)
+    });
+
+    match args.sort_by.unwrap_or_default() {
+        ProcessSort::Cpu => rows.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent)),
+        ProcessSort::Memory => rows.sort_by_key(|row| Reverse(row.rss_bytes)),
+        ProcessSort::Pid => rows.sort_by_key(|row| row.pid),
+        ProcessSort::StartTime => rows.sort_by_key(|row| Reverse(row.start_time)),
+        ProcessSort::Name => rows.sort_by(|a, b| a.name.cmp(&b.name).then(a.pid.cmp(&b.pid))),
+    }
+    Ok(rows)
+}
+
+/// Descritores abertos (`None` sem permissão para ler `/proc/<pid>/fd`)
+fn open_fds(pid: u32) -> Option<usize> {
+    fs::read_dir(format!("/proc/{}/fd", pid))
+        .ok()
+        .map(|entries| entries.count())
+}
+
+/// Caminho do cgroup (v2) ou as hierarquias do v1 separadas por ';'
+fn cgroup(pid: u32) -> Option<String> {
+    let content = fs::read_to_string(format!("/proc/{}/cgroup", pid)).ok()?;
+    if let Some(path) = content.lines().find_map(|line| line.strip_prefix("0::")) {
+        return Some(path.to_string());
+    }
+    let hierarchies: Vec<_> = content.lines().filter(|line| !line.is_empty()).collect();
+    (!hierarchies.is_empty()).then(|| hierarchies.join(";"))
+}
+
+fn to_json(row: &Row, redactor: &Redactor) -> Value {
+    let start_time = chrono::DateTime::from_timestamp(row.start_time as i64, 0)
+        .map(|time| time.with_timezone(&chrono::Local).to_rfc3339());
+    json!({
+        "pid": row.pid,
+        "ppid": row.ppid,
+        "user": row.user,
+        "uid": row.uid,
+        "name": row.name,
+        "cmdline": redactor.text(&row.cmdline.join(" ")),
+        "state": row.state,
+        "cpu_percent": (row.cpu_percent * 10.0).round() / 10.0,
+        "rss_bytes": row.rss_bytes,
+        "start_time": start_time,
+        "open_fds": open_fds(row.pid),
+        "cgroup": cgroup(row.pid),
+    })
+}
+
+/// Árvore de processos a partir de `root`
+///
+/// Para de descer em `max_depth` e após `budget` nós; nesse caso o campo
+/// `children_omitted` indica quantos filhos diretos ficaram de fora.
+fn build_tree(
+    rows: &[Row],
+    root: u32,
+    max_depth: Option<usize>,
+    budget: &mut usize,
+    node: &dyn Fn(&Row) -> Value,
+) -> Option<Value> {
+    let by_pid: HashMap<u32, &Row> = rows.iter().map(|row| (row.pid, row)).collect();
+    let mut children: HashMap<u32, Vec<&Row>> = HashMap::new();
+    for row in rows {
+        if let Some(ppid) = row.ppid.filter(|&ppid| ppid != row.pid) {
+            children.entry(ppid).or_default().push(row);
+        }
+    }
+    for list in children.values_mut() {
+        list.sort_by_key(|row| row.pid);
+    }
+
+    fn visit(
+        row: &Row,
+        depth: usize,
+        max_depth: Option<usize>,
+        children: &HashMap<u32, Vec<&Row>>,
+        budget: &mut usize,
+        node: &dyn Fn(&Row) -> Value,
+    ) -> Value {
+        *budget = budget.saturating_sub(1);
+        let mut value = node(row);
+        let kids = children
+            .get(&row.pid)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut visited = Vec::new();
+        if max_depth.is_none_or(|max| depth < max) {
+            for kid in kids {
+                if *budget == 0 {
+                    break;
+                }
+                visited.push(visit(kid, depth + 1, max_depth, children, budget, node));
+            }
+        }
+        if visited.len() < kids.len() {
+            value["children_omitted"] = json!(kids.len() - visited.len());
+        }
+        value["children"] = Value::Array(visited);
+        value
+    }
+
+    let root = by_pid.get(&root)?;
+    Some(visit(root, 0, max_depth, &children, budget, node))
+}
+
+fn serialize(response: &Value) -> Result<CallToolResult, ErrorData> {
+    Ok(CallToolResult::success(vec![Content::text(
+        serde_json::to_string_pretty(response).map_err(|e| {
+            ErrorData::new(
+                ErrorCode::INTERNAL_ERROR,
+                format!("Failed to serialize processes: {}", e),
+                None,
+            )
+        })?,
+    )]))
+}
+
+/// Lista processos com filtros, ordenação e limite
+pub async fn list_processes(
+    system: Arc<Mutex<System>>,
+    args: ListProcessesArgs,
+    config: Arc<Config>,
+) -> Result<CallToolResult, ErrorData> {
+    let rows = select(snapshot(&system).await, &args)?;
+    let limit = args.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+    let redactor = Redactor::from_config(&config);
+    let processes: Vec<_> = rows
+        .iter()
+        .take(limit)
+        .map(|row| to_json(row, &redactor))
+        .collect();
+
+    serialize(&json!({
+        "total": rows.len(),
+        "returned": processes.len(),
+        "processes": processes,
+    }))
+}
+
+/// Mostra a árvore de processos a partir de um PID
+pub async fn process_tree(
+    system: Arc<Mutex<System>>,
+    args: ProcessTreeArgs,
+    config: Arc<Config>,
+) -> Result<CallToolResult, ErrorData> {
+    let rows = snapshot(&system).await;
+    let root = args.pid.unwrap_or(1);
+    let redactor = Redactor::from_config(&config);
+    let mut budget = MAX_TREE_NODES;
+    let tree = build_tree(&rows, root, args.max_depth, &mut budget, &|row| {
+        to_json(row, &redactor)
+    })
+    .ok_or_else(|| invalid(format!("Process {} not found", root)))?;
+
+    serialize(&json!({
+        "root": root,
+        "nodes": MAX_TREE_NODES - budget,
+        "truncated": budget == 0,
+        "tree": tree,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(pid: u32, ppid: u32, name: &str, user: &str, cpu: f32, mb: u64) -> Row {
+        Row {
+            pid,
+            ppid: Some(ppid),
+            name: name.to_string(),
+            uid: Some(if user == "root" { 0 } else { 1000 }),
+            user: Some(user.to_string()),
+            cpu_percent: cpu,
+            rss_bytes: mb * 1024 * 1024,
+            start_time: pid as u64,
+            ..Default::default()
+        }
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                ppid: None,
+                ..row(1, 0, "systemd", "root", 0.1, 12)
+            },
+            row(100, 1, "sshd", "root", 0.0, 8),
+            row(200, 100, "bash", "alice", 1.5, 4),
+            row(300, 200, "python3", "alice", 85.0, 300),
+            row(301, 200, "python3", "alice", 2.0, 150),
+        ]
+    }
+
+    fn pids(rows: &[Row]) -> Vec<u32> {
+        rows.iter().map(|row| row.pid).collect()
+    }
+
+    #[test]
+    fn test_select() {
+        let args = ListProcessesArgs::default();
+        assert_eq!(
+            pids(&select(rows(), &args).unwrap()),
+            [300, 301, 200, 1, 100]
+        );
+
+        let args = ListProcessesArgs {
+            name: Some("^py".to_string()),
+            min_memory_mb: Some(200),
+            ..Default::default()
+        };
+        assert_eq!(pids(&select(rows(), &args).unwrap()), [300]);
+
+        let args = ListProcessesArgs {
+            min_memory_mb: Some(u64::MAX),
+            ..Default::default()
+        };
+        assert!(select(rows(), &args).unwrap().is_empty());
+
+        let args = ListProcessesArgs {
+            user: Some("0".to_string()),
+            sort_by: Some(ProcessSort::Memory),
+            ..Default::default()
+        };
+        assert_eq!(pids(&select(rows(), &args).unwrap()), [1, 100]);
+
+        let args = ListProcessesArgs {
+            user: Some("alice".to_string()),
+            min_cpu_percent: Some(1.0),
+            sort_by: Some(ProcessSort::StartTime),
+            ..Default::default()
+        };
+        assert_eq!(pids(&select(rows(), &args).unwrap()), [301, 300, 200]);
+
+        let args = ListProcessesArgs {
+            name: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(select(rows(), &args).is_err());
+    }
+
+    #[test]
+    fn test_build_tree() {
+        let node = |row: &Row| json!({ "pid": row.pid });
+        let mut budget = MAX_TREE_NODES;
+        let tree = build_tree(&rows(), 100, None, &mut budget, &node).unwrap();
+        assert_eq!(tree["children"][0]["pid"], 200);
+        assert_eq!(tree["children"][0]["children"][1]["pid"], 301);
+        assert_eq!(budget, MAX_TREE_NODES - 4);
+
+        let mut budget = MAX_TREE_NODES;
+        let tree = build_tree(&rows(), 1, Some(1), &mut budget, &node).unwrap();
+        assert_eq!(tree["children"][0]["children_omitted"], 1);
+        assert_eq!(tree["children"][0]["children"], json!([]));
+
+        let mut budget = 3;
+        let tree = build_tree(&rows(), 1, None, &mut budget, &node).unwrap();
+        assert_eq!(budget, 0);
+        assert_eq!(tree["children"][0]["children"][0]["children_omitted"], 2);
+
+        assert!(build_tree(&rows(), 999, None, &mut budget, &node).is_none());
+    }
+}
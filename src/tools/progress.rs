@@ -0,0 +1,59 @@
+//! Streaming da saída de comandos via notificações de progresso MCP
+//!
+//! Quando o cliente envia um `progressToken` em `_meta`, cada linha de
+//! stdout/stderr lida durante a execução é enviada como uma notificação
+//! `notifications/progress`, já com os segredos mascarados. O resultado final
+//! continua sendo retornado normalmente ao fim da execução.
+
+use super::exec::{LineSender, Stream};
+use crate::redact::Redactor;
+use rmcp::model::{Meta, ProgressNotificationParam, ProgressToken};
+use rmcp::{Peer, RoleServer};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Linhas em espera antes de novas linhas serem descartadas do streaming
+const CHANNEL_CAPACITY: usize = 1024;
+
+/// Envia as linhas de saída de uma execução como notificações de progresso
+pub struct ProgressReporter {
+    peer: Peer<RoleServer>,
+    token: ProgressToken,
+}
+
+impl ProgressReporter {
+    /// Retorna `None` quando o cliente não pediu notificações de progresso
+    pub fn new(meta: &Meta, peer: Peer<RoleServer>) -> Option<Self> {
+        meta.get_progress_token().map(|token| Self { peer, token })
+    }
+
+    /// Inicia o envio das notificações; a tarefa termina quando todos os
+    /// `LineSender` forem descartados
+    pub fn spawn(self, redactor: Redactor) -> (LineSender, JoinHandle<()>) {
+        let (tx, mut rx) = mpsc::channel::<(Stream, String)>(CHANNEL_CAPACITY);
+        let handle = tokio::spawn(async move {
+            let mut count = 0u64;
+            // Cada stream tem o seu estado (ex: dentro de uma chave privada)
+            let mut stdout = redactor.clone().lines();
+            let mut stderr = redactor.lines();
+            while let Some((stream, line)) = rx.recv().await {
+                count += 1;
+                let line = match stream {
+                    Stream::Stdout => stdout.line(&line),
+                    Stream::Stderr => stderr.line(&line),
+                };
+                let param = ProgressNotificationParam {
+                    progress_token: self.token.clone(),
+                    progress: count as f64,
+                    total: None,
+                    message: Some(format!("[{}] {}", stream.name(), line)),
+                };
+                // Um cliente desconectado não deve interromper o comando
+                if self.peer.notify_progress(param).await.is_err() {
+                    break;
+                }
+            }
+        });
+        (tx, handle)
+    }
+}
@@ -0,0 +1,455 @@
+//! Sandbox opcional para comandos executados sem elevação
+//!
+//! Um perfil de `[sandbox.profiles.<nome>]` é aplicado no processo filho,
+//! entre o fork e o exec, nesta ordem:
+//!
+//! 1. namespaces novos de usuário, mount e PID (com `/proc` próprio) e, se
+//!    `network = false`, um namespace de rede vazio;
+//! 2. rlimits de CPU, memória, tamanho de arquivo e número de processos;
+//! 3. Landlock, limitando escrita às raízes de `writable_paths`;
+//! 4. seccomp, bloqueando syscalls que não fazem sentido para diagnósticos.
+//!
+//! Tudo que aloca memória (mapas de uid/gid, ruleset do Landlock e programa
+//! BPF) é preparado antes do fork.
+
+use super::paths;
+use crate::config::{Config, SandboxProfile};
+use landlock::{
+    path_beneath_rules, AccessFs, Ruleset, RulesetAttr, RulesetCreated, RulesetCreatedAttr,
+    RulesetStatus, ABI,
+};
+use nix::errno::Errno;
+use nix::fcntl::{open, OFlag};
+use nix::libc;
+use nix::mount::{mount, MsFlags};
+use nix::sched::{unshare, CloneFlags};
+use nix::sys::resource::{setrlimit, Resource};
+use nix::sys::stat::Mode;
+use nix::sys::wait::{waitpid, WaitStatus};
+use nix::unistd::{fork, getgid, getuid, write, ForkResult};
+use seccompiler::{BpfProgram, SeccompAction, SeccompFilter, TargetArch};
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::process::Command;
+
+/// Syscalls bloqueadas pelo filtro seccomp (retornam EPERM)
+const BLOCKED_SYSCALLS: &[libc::c_long] = &[
+    libc::SYS_ptrace,
+    libc::SYS_process_vm_readv,
+    libc::SYS_process_vm_writev,
+    libc::SYS_mount,
+    libc::SYS_umount2,
+    libc::SYS_pivot_root,
+    libc::SYS_chroot,
+    libc::SYS_unshare,
+    libc::SYS_setns,
+    libc::SYS_kexec_load,
+    libc::SYS_init_module,
+    libc::SYS_finit_module,
+    libc::SYS_delete_module,
+    libc::SYS_reboot,
+    libc::SYS_swapon,
+    libc::SYS_swapoff,
+    libc::SYS_bpf,
+    libc::SYS_perf_event_open,
+    libc::SYS_keyctl,
+    libc::SYS_add_key,
+    libc::SYS_request_key,
+    libc::SYS_userfaultfd,
+    libc::SYS_open_by_handle_at,
+    libc::SYS_acct,
+    libc::SYS_settimeofday,
+    libc::SYS_clock_settime,
+    libc::SYS_sethostname,
+    libc::SYS_setdomainname,
+];
+
+/// Perfil de sandbox resolvido para uma execução
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    pub name: String,
+    profile: SandboxProfile,
+    writable: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new(name: &str, profile: &SandboxProfile, config: &Config) -> Result<Self, String> {
+        let writable = profile
+            .writable_paths
+            .as_ref()
+            .unwrap_or(&config.writable_paths)
+            .iter()
+            .map(|p| paths::expand(p, Path::new("/")))
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            name: name.to_string(),
+            profile: profile.clone(),
+            writable,
+        })
+    }
+
+    /// Prepara as camadas do perfil e as instala no `pre_exec` do comando
+    pub fn apply(&self, cmd: &mut Command) -> io::Result<()> {
+        let profile = &self.profile;
+
+        let namespaces = profile.namespaces.then(|| {
+            let mut flags = CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS;
+            flags |= CloneFlags::CLONE_NEWPID;
+            if !profile.network {
+                flags |= CloneFlags::CLONE_NEWNET;
+            }
+            // O usuário é mapeado para ele mesmo: sem privilégios extras após o exec
+            let uid_map = format!("{} {} 1", getuid(), getuid()).into_bytes();
+            let gid_map = format!("{} {} 1", getgid(), getgid()).into_bytes();
+            (flags, uid_map, gid_map)
+        });
+
+        const MIB: u64 = 1024 * 1024;
+        let rlimits: Vec<(Resource, u64)> = [
+            (Resource::RLIMIT_CPU, profile.max_cpu_secs),
+            (Resource::RLIMIT_AS, profile.max_memory_mb.map(|m| m * MIB)),
+            (
+                Resource::RLIMIT_FSIZE,
+                profile.max_file_size_mb.map(|m| m * MIB),
+            ),
+            (Resource::RLIMIT_NPROC, profile.max_processes),
+        ]
+        .into_iter()
+        .filter_map(|(resource, limit)| limit.map(|l| (resource, l)))
+        .collect();
+
+        let ruleset = if profile.landlock {
+            Some(landlock_ruleset(&self.writable).map_err(io::Error::other)?)
+        } else {
+            None
+        };
+        let ruleset = Mutex::new(ruleset);
+
+        let filter = if profile.seccomp {
+            Some(seccomp_filter()?)
+        } else {
+            None
+        };
+
+        let best_effort = profile.best_effort;
+        let pre_exec = move || -> io::Result<()> {
+            if let Some((flags, uid_map, gid_map)) = &namespaces {
+                enter_namespaces(*flags, uid_map, gid_map, best_effort)?;
+            }
+
+            for (resource, limit) in &rlimits {
+                setrlimit(*resource, *limit, *limit)?;
+            }
+
+            if let Some(ruleset) = ruleset.lock().unwrap().take() {
+                let status = ruleset.restrict_self().map_err(io::Error::other)?;
+                if status.ruleset == RulesetStatus::NotEnforced && !best_effort {
+                    return Err(io::Error::other("Landlock não é suportado por este kernel"));
+                }
+            }
+
+            if let Some(filter) = &filter {
+                seccompiler::apply_filter(filter).map_err(io::Error::other)?;
+            }
+            Ok(())
+        };
+
+        // SAFETY: o closure só faz syscalls sobre dados preparados antes do fork;
+        // as alocações restantes acontecem apenas em caminhos de erro
+        unsafe {
+            cmd.pre_exec(pre_exec);
+        }
+        Ok(())
+    }
+}
+
+/// Ruleset do Landlock que controla apenas escrita (leitura continua livre)
+fn landlock_ruleset(writable: &[PathBuf]) -> Result<RulesetCreated, landlock::RulesetError> {
+    let write = AccessFs::from_write(ABI::V3);
+    Ruleset::default()
+        .handle_access(write)?
+        .create()?
+        .add_rules(path_beneath_rules(writable, write))
+}
+
+/// Compila o filtro seccomp para a arquitetura atual
+fn seccomp_filter() -> io::Result<BpfProgram> {
+    // `c_long` só é i64 em arquiteturas de 64 bits
+    #[allow(clippy::unnecessary_cast)]
+    let rules = BLOCKED_SYSCALLS
+        .iter()
+        .map(|&nr| (nr as i64, Vec::new()))
+        .collect::<BTreeMap<_, _>>();
+    let arch = TargetArch::try_from(std::env::consts::ARCH).map_err(io::Error::other)?;
+    let filter = SeccompFilter::new(
+        rules,
+        SeccompAction::Allow,
+        SeccompAction::Errno(libc::EPERM as u32),
+        arch,
+    )
+    .map_err(io::Error::other)?;
+    filter.try_into().map_err(io::Error::other)
+}
+
+fn write_file(path: &CStr, data: &[u8]) -> Result<(), Errno> {
+    let fd = open(path, OFlag::O_WRONLY | OFlag::O_CLOEXEC, Mode::empty())?;
+    write(&fd, data)?;
+    Ok(())
+}
+
+/// Entra nos novos namespaces; roda no processo filho antes do exec
+fn enter_namespaces(
+    flags: CloneFlags,
+    uid_map: &[u8],
+    gid_map: &[u8],
+    best_effort: bool,
+) -> io::Result<()> {
+    if let Err(e) = unshare(flags) {
+        // Ex: namespaces de usuário desabilitados pelo administrador
+        return if best_effort { Ok(()) } else { Err(e.into()) };
+    }
+    write_file(c"/proc/self/setgroups", b"deny")?;
+    write_file(c"/proc/self/uid_map", uid_map)?;
+    write_file(c"/proc/self/gid_map", gid_map)?;
+    mount(
+        None::<&CStr>,
+        c"/",
+        None::<&CStr>,
+        MsFlags::MS_REC | MsFlags::MS_PRIVATE,
+        None::<&CStr>,
+    )?;
+
+    // O novo namespace de PID só vale para os filhos: o processo atual vira
+    // um intermediário que espera o comando e repassa seu código de saída
+    // SAFETY: o filho segue para o exec e o pai só fecha descritores, chama
+    // waitpid e _exit
+    if let ForkResult::Parent { child } = unsafe { fork() }? {
+        // O intermediário nunca faz exec: sem isso ele manteria aberto o pipe
+        // de erro do `spawn` (que só retornaria quando o comando terminasse)
+        // e as pontas de escrita de stdout/stderr
+        close_all_fds();
+        let code = loop {
+            match waitpid(child, None) {
+                Ok(WaitStatus::Exited(_, code)) => break code,
+                Ok(WaitStatus::Signaled(_, signal, _)) => break 128 + signal as i32,
+                Err(Errno::EINTR) | Ok(_) => continue,
+                Err(_) => break 1,
+            }
+        };
+        // SAFETY: encerra o intermediário sem rodar destrutores do processo pai
+        unsafe { libc::_exit(code) };
+    }
+
+    // Se o intermediário morrer (ex: `kill_on_drop`), o comando morre junto;
+    // como PID 1 do namespace, sua saída encerra os demais processos dele
+    // SAFETY: prctl sem ponteiros
+    if unsafe { libc::prctl(libc::PR_SET_PDEATHSIG, libc::SIGKILL) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    mount(
+        Some(c"proc"),
+        c"/proc",
+        Some(c"proc"),
+        MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+        None::<&CStr>,
+    )?;
+    Ok(())
+}
+
+/// Fecha todos os descritores do processo, sem alocar memória
+fn close_all_fds() {
+    // SAFETY: syscalls sem ponteiros; nenhum descritor é usado depois disto
+    unsafe {
+        if libc::syscall(libc::SYS_close_range, 0, libc::c_uint::MAX, 0) == 0 {
+            return;
+        }
+        // Kernels anteriores ao 5.9 não têm close_range
+        let max = match libc::sysconf(libc::_SC_OPEN_MAX) {
+            n if n > 0 => n.min(65536) as libc::c_int,
+            _ => 1024,
+        };
+        for fd in 0..max {
+            libc::close(fd);
+        }
+    }
+}
+
+/// Nome do binário usado para buscar a política em `[commands.<nome>]`
+fn command_name(program: &str) -> &str {
+    Path::new(program)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(program)
+}
+
+/// Seleciona o perfil de sandbox dos programas invocados
+///
+/// Programas sem perfil usam `default_profile`. Um perfil vale para toda a
+/// invocação (ex: todos os comandos de um pipeline); perfis diferentes na
+/// mesma invocação são recusados.
+pub fn select(programs: &[String], config: &Config) -> Result<Option<Sandbox>, String> {
+    let mut selected: Option<&str> = None;
+    for program in programs {
+        let name = command_name(program);
+        // A sandbox da política geral prevalece sobre a do perfil que a redefine
+        let profile = config
+            .command_policies(name)
+            .iter()
+            .rev()
+            .find_map(|(_, p)| p.sandbox.as_deref())
+            .or(config.sandbox.default_profile.as_deref());
+        match (selected, profile) {
+            (Some(current), Some(profile)) if current != profile => {
+                return Err(format!(
+                    "Os comandos desta invocação exigem perfis de sandbox diferentes ('{}' e '{}')",
+                    current, profile
+                ));
+            }
+            (None, Some(profile)) => selected = Some(profile),
+            _ => {}
+        }
+    }
+
+    selected
+        .map(|name| {
+            let profile = config
+                .sandbox
+                .profiles
+                .get(name)
+                .ok_or_else(|| format!("Perfil de sandbox '{}' não existe", name))?;
+            Sandbox::new(name, profile, config)
+        })
+        .transpose()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CommandPolicy;
+    use crate::tools::exec::{self, RunOptions};
+    use std::time::Duration;
+    use tokio_util::sync::CancellationToken;
+
+    fn config_with(profile: SandboxProfile) -> Config {
+        let mut config = Config::default();
+        config.sandbox.profiles.insert("test".to_string(), profile);
+        config.commands.insert(
+            "sh".to_string(),
+            CommandPolicy {
+                sandbox: Some("test".to_string()),
+                ..Default::default()
+            },
+        );
+        config
+    }
+
+    async fn run_sandboxed(config: &Config, script: &str) -> exec::RunOutput {
+        run_sandboxed_for(config, script, Duration::from_secs(10)).await
+    }
+
+    async fn run_sandboxed_for(
+        config: &Config,
+        script: &str,
+        timeout: Duration,
+    ) -> exec::RunOutput {
+        let sandbox = select(&["sh".to_string()], config).unwrap().unwrap();
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        sandbox.apply(&mut cmd).unwrap();
+        let options = RunOptions {
+            timeout,
+            capture_limit: 4096,
+            lines: None,
+            capture: None,
+            stdin: None,
+        };
+        exec::run(cmd, options, &CancellationToken::new())
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn test_select() {
+        let config = config_with(SandboxProfile::default());
+        assert_eq!(
+            select(&["/bin/sh".to_string(), "sort".to_string()], &config)
+                .unwrap()
+                .map(|s| s.name),
+            Some("test".to_string())
+        );
+        assert!(select(&["sort".to_string()], &config).unwrap().is_none());
+
+        let mut config = config;
+        config.sandbox.default_profile = Some("isolated".to_string());
+        assert!(select(&["sh".to_string(), "sort".to_string()], &config).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_and_seccomp() {
+        let config = config_with(SandboxProfile {
+            landlock: false,
+            ..Default::default()
+        });
+        // PID 1 no novo namespace, só a interface de loopback e sem unshare
+        let out = run_sandboxed(
+            &config,
+            "echo $$; grep -c : /proc/net/dev; unshare -U true 2>/dev/null || echo blocked; exit 7",
+        )
+        .await;
+        assert_eq!(out.exit_code(), 7);
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "1\n1\nblocked\n");
+    }
+
+    #[tokio::test]
+    async fn test_namespaces_timeout_and_large_output() {
+        let config = config_with(SandboxProfile {
+            landlock: false,
+            ..Default::default()
+        });
+        // O spawn não pode esperar o comando terminar: o timeout precisa valer
+        let out = run_sandboxed_for(&config, "sleep 30", Duration::from_millis(300)).await;
+        assert_eq!(out.termination, exec::Termination::TimedOut);
+        assert!(out.duration < Duration::from_secs(10));
+
+        // Saída maior que o buffer do pipe precisa ser lida enquanto o comando roda
+        let out = run_sandboxed(&config, "head -c 300000 /dev/zero").await;
+        assert!(out.success());
+        assert_eq!(out.stdout.len() as u64 + out.dropped_bytes, 300000);
+    }
+
+    #[tokio::test]
+    async fn test_landlock() {
+        let dir = std::env::temp_dir().join(format!("linux-mcp-landlock-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let config = config_with(SandboxProfile {
+            namespaces: false,
+            seccomp: false,
+            writable_paths: Some(vec![dir.to_string_lossy().to_string()]),
+            ..Default::default()
+        });
+        let script = format!(
+            "touch {0}/ok && echo inside; touch {0}/../linux-mcp-denied 2>/dev/null || echo denied",
+            dir.display()
+        );
+        let out = run_sandboxed(&config, &script).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "inside\ndenied\n");
+    }
+
+    #[tokio::test]
+    async fn test_rlimits() {
+        let config = config_with(SandboxProfile {
+            namespaces: false,
+            landlock: false,
+            seccomp: false,
+            max_file_size_mb: Some(1),
+            ..Default::default()
+        });
+        let out = run_sandboxed(&config, "ulimit -f").await;
+        assert_eq!(String::from_utf8_lossy(&out.stdout), "2048\n");
+    }
+}
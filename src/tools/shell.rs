@@ -0,0 +1,571 @@
+//! Analisador léxico/sintático mínimo de shell POSIX.
+//!
+//! O objetivo não é executar nada, apenas descobrir com certeza quais binários
+//! uma linha de comando vai invocar (incluindo pipelines, listas, subshells e
+//! substituição de comandos). Qualquer construção que não conseguimos analisar
+//! com segurança é rejeitada com um erro, assim como a sintaxe exclusiva do
+//! bash (`&>`, `|&`, `<(...)`, `>(...)`): a linha roda em `sh -c`, que em
+//! Debian/Ubuntu é o dash e lê esses operadores de outra forma.
+
+/// Uma palavra da linha de comando após a remoção de aspas
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Word {
+    /// Texto literal da palavra (partes dinâmicas são omitidas)
+    pub text: String,
+    /// Contém expansão de variável ou substituição de comando
+    pub dynamic: bool,
+    /// Contém caracteres de glob (`*`, `?`, `[`) fora de aspas
+    pub glob: bool,
+}
+
+/// Redirecionamento associado a um comando simples (ex: `2> /dev/null`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    pub op: String,
+    pub target: Word,
+}
+
+/// Comando simples: o binário invocado, seus argumentos e redirecionamentos
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SimpleCommand {
+    pub words: Vec<Word>,
+    pub redirects: Vec<Redirect>,
+}
+
+/// Palavras reservadas que não suportamos (controle de fluxo, funções, etc.)
+const RESERVED_WORDS: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "do", "done", "case", "esac", "while", "until", "for",
+    "in", "select", "function", "coproc", "[[", "]]",
+];
+
+/// Analisa uma linha de comando e retorna todos os comandos simples que ela executaria
+pub fn parse(command_line: &str) -> Result<Vec<SimpleCommand>, String> {
+    let mut parser = Parser {
+        chars: command_line.chars().collect(),
+        pos: 0,
+        commands: Vec::new(),
+    };
+    parser.parse_list(None)?;
+    Ok(parser.commands)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    commands: Vec<SimpleCommand>,
+}
+
+fn is_blank(c: char) -> bool {
+    c == ' ' || c == '\t'
+}
+
+fn is_metachar(c: char) -> bool {
+    is_blank(c) || matches!(c, ';' | '&' | '|' | '(' | ')' | '<' | '>' | '\n')
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<char> {
+        self.chars.get(self.pos + offset).copied()
+    }
+
+    fn skip_blanks(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if is_blank(c) => self.pos += 1,
+                // Continuação de linha
+                Some('\\') if self.peek_at(1) == Some('\n') => self.pos += 2,
+                // Comentário até o fim da linha
+                Some('#') => {
+                    while let Some(c) = self.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Analisa uma lista de pipelines até `close` (ou o fim da entrada)
+    fn parse_list(&mut self, close: Option<char>) -> Result<(), String> {
+        loop {
+            self.skip_blanks();
+            let c = match self.peek() {
+                Some(c) => c,
+                None => {
+                    return match close {
+                        Some(c) => Err(format!("'{}' esperado antes do fim do comando", c)),
+                        None => Ok(()),
+                    }
+                }
+            };
+
+            if Some(c) == close {
+                self.pos += 1;
+                return Ok(());
+            }
+
+            match c {
+                '|' if self.peek_at(1) == Some('&') => {
+                    return Err("O operador '|&' não existe no sh (use '2>&1 |')".to_string())
+                }
+                ';' | '|' | '\n' => self.pos += 1,
+                '&' if self.peek_at(1) != Some('>') => self.pos += 1,
+                '(' => {
+                    // Subshell
+                    self.pos += 1;
+                    self.parse_list(Some(')'))?;
+                }
+                ')' => return Err("')' inesperado".to_string()),
+                _ => self.parse_simple_command()?,
+            }
+        }
+    }
+
+    fn parse_simple_command(&mut self) -> Result<(), String> {
+        let mut command = SimpleCommand::default();
+
+        loop {
+            self.skip_blanks();
+            let c = match self.peek() {
+                Some(c) => c,
+                None => break,
+            };
+
+            match c {
+                ';' | '|' | '\n' | ')' => break,
+                '&' if self.peek_at(1) != Some('>') => break,
+                '(' => return Err("'(' inesperado no meio de um comando".to_string()),
+                '<' | '>' if self.peek_at(1) == Some('(') => {
+                    return Err(
+                        "Substituição de processo (<(...) e >(...)) não existe no sh".to_string(),
+                    );
+                }
+                _ => {
+                    if let Some(op) = self.read_redirect_op()? {
+                        self.skip_blanks();
+                        let target = self
+                            .read_word()?
+                            .ok_or_else(|| format!("Redirecionamento '{}' sem destino", op))?;
+                        command.redirects.push(Redirect { op, target });
+                        continue;
+                    }
+
+                    let start = self.pos;
+                    let word = match self.read_word()? {
+                        Some(word) => word,
+                        None => return Err(format!("Caractere inesperado '{}'", c)),
+                    };
+                    let raw: String = self.chars[start..self.pos].iter().collect();
+
+                    if command.words.is_empty() {
+                        // Palavras na posição de comando que alteram a sintaxe
+                        if RESERVED_WORDS.contains(&raw.as_str()) {
+                            return Err(format!("Construção de shell não suportada: '{}'", raw));
+                        }
+                        if raw == "!" || raw == "{" || raw == "}" {
+                            continue;
+                        }
+                        if is_assignment(&raw) {
+                            return Err(format!(
+                                "Atribuição de variáveis não é permitida: '{}'",
+                                raw
+                            ));
+                        }
+                    }
+
+                    command.words.push(word);
+                }
+            }
+        }
+
+        if !command.words.is_empty() || !command.redirects.is_empty() {
+            self.commands.push(command);
+        }
+        Ok(())
+    }
+
+    /// Lê um operador de redirecionamento, se houver um na posição atual
+    fn read_redirect_op(&mut self) -> Result<Option<String>, String> {
+        let start = self.pos;
+        let mut i = self.pos;
+        while let Some(c) = self.chars.get(i) {
+            if c.is_ascii_digit() {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+
+        let rest: String = self.chars[i..self.chars.len().min(i + 3)].iter().collect();
+        let op = if i == start && rest.starts_with("&>") {
+            // No sh, `cmd &>arq` é `cmd &` seguido de `>arq`
+            return Err("O operador '&>' não existe no sh (use '> arquivo 2>&1')".to_string());
+        } else if rest.starts_with("<<<") {
+            "<<<"
+        } else if rest.starts_with("<<") {
+            return Err("Here-documents (<<) não são suportados".to_string());
+        } else if rest.starts_with("<>") {
+            "<>"
+        } else if rest.starts_with("<&") {
+            "<&"
+        } else if rest.starts_with(">>") {
+            ">>"
+        } else if rest.starts_with(">&") {
+            ">&"
+        } else if rest.starts_with(">|") {
+            ">|"
+        } else if rest.starts_with('<') {
+            "<"
+        } else if rest.starts_with('>') {
+            ">"
+        } else {
+            return Ok(None);
+        };
+
+        self.pos = i + op.len();
+        let fd: String = self.chars[start..i].iter().collect();
+        Ok(Some(format!("{}{}", fd, op)))
+    }
+
+    /// Lê uma palavra removendo aspas e analisando substituições aninhadas
+    fn read_word(&mut self) -> Result<Option<Word>, String> {
+        let mut word = Word {
+            text: String::new(),
+            dynamic: false,
+            glob: false,
+        };
+        let start = self.pos;
+
+        while let Some(c) = self.peek() {
+            if is_metachar(c) {
+                break;
+            }
+            match c {
+                '\\' => {
+                    self.pos += 1;
+                    match self.peek() {
+                        Some('\n') => self.pos += 1,
+                        Some(escaped) => {
+                            word.text.push(escaped);
+                            self.pos += 1;
+                        }
+                        None => return Err("Barra invertida no fim do comando".to_string()),
+                    }
+                }
+                '\'' => {
+                    self.pos += 1;
+                    loop {
+                        match self.peek() {
+                            Some('\'') => {
+                                self.pos += 1;
+                                break;
+                            }
+                            Some(ch) => {
+                                word.text.push(ch);
+                                self.pos += 1;
+                            }
+                            None => return Err("Aspas simples não fechadas".to_string()),
+                        }
+                    }
+                }
+                '"' => {
+                    self.pos += 1;
+                    self.read_double_quoted(&mut word)?;
+                }
+                '$' => self.read_dollar(&mut word)?,
+                '`' => self.read_backticks(&mut word)?,
+                '*' | '?' | '[' => {
+                    word.glob = true;
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+                '{' => {
+                    self.check_brace_expansion()?;
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+                _ => {
+                    word.text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+
+        if self.pos == start {
+            Ok(None)
+        } else {
+            Ok(Some(word))
+        }
+    }
+
+    fn read_double_quoted(&mut self, word: &mut Word) -> Result<(), String> {
+        loop {
+            match self.peek() {
+                Some('"') => {
+                    self.pos += 1;
+                    return Ok(());
+                }
+                Some('\\') => match self.peek_at(1) {
+                    Some(ch @ ('$' | '`' | '"' | '\\')) => {
+                        word.text.push(ch);
+                        self.pos += 2;
+                    }
+                    Some('\n') => self.pos += 2,
+                    _ => {
+                        word.text.push('\\');
+                        self.pos += 1;
+                    }
+                },
+                Some('$') => self.read_dollar(word)?,
+                Some('`') => self.read_backticks(word)?,
+                Some(ch) => {
+                    word.text.push(ch);
+                    self.pos += 1;
+                }
+                None => return Err("Aspas duplas não fechadas".to_string()),
+            }
+        }
+    }
+
+    /// Analisa expansões iniciadas por `$`
+    fn read_dollar(&mut self, word: &mut Word) -> Result<(), String> {
+        match self.peek_at(1) {
+            Some('(') if self.peek_at(2) == Some('(') => {
+                Err("Expansão aritmética $((...)) não é suportada".to_string())
+            }
+            Some('(') => {
+                // Substituição de comando
+                self.pos += 2;
+                self.parse_list(Some(')'))?;
+                word.dynamic = true;
+                Ok(())
+            }
+            Some('{') => {
+                self.pos += 2;
+                loop {
+                    match self.peek() {
+                        Some('}') => {
+                            self.pos += 1;
+                            break;
+                        }
+                        Some(c) if is_name_char(c) || "#?@*!$-".contains(c) => self.pos += 1,
+                        Some(_) => {
+                            return Err(
+                                "Expansão de parâmetro complexa ${...} não é suportada".to_string()
+                            )
+                        }
+                        None => return Err("'}' esperado antes do fim do comando".to_string()),
+                    }
+                }
+                word.dynamic = true;
+                Ok(())
+            }
+            Some('\'') | Some('"') => Err("Aspas $'...' e $\"...\" não são suportadas".to_string()),
+            Some(c) if is_name_char(c) => {
+                self.pos += 1;
+                if c.is_ascii_digit() {
+                    self.pos += 1;
+                } else {
+                    while self.peek().is_some_and(is_name_char) {
+                        self.pos += 1;
+                    }
+                }
+                word.dynamic = true;
+                Ok(())
+            }
+            Some('#' | '?' | '@' | '*' | '!' | '$' | '-') => {
+                self.pos += 2;
+                word.dynamic = true;
+                Ok(())
+            }
+            _ => {
+                // `$` isolado é literal
+                word.text.push('$');
+                self.pos += 1;
+                Ok(())
+            }
+        }
+    }
+
+    /// Analisa uma substituição de comando no estilo antigo `...`
+    fn read_backticks(&mut self, word: &mut Word) -> Result<(), String> {
+        self.pos += 1;
+        let mut inner = String::new();
+        loop {
+            match self.peek() {
+                Some('`') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some('\\') => {
+                    match self.peek_at(1) {
+                        Some(ch @ ('`' | '$' | '\\')) => inner.push(ch),
+                        Some(ch) => {
+                            inner.push('\\');
+                            inner.push(ch);
+                        }
+                        None => return Err("Crase não fechada".to_string()),
+                    }
+                    self.pos += 2;
+                }
+                Some(ch) => {
+                    inner.push(ch);
+                    self.pos += 1;
+                }
+                None => return Err("Crase não fechada".to_string()),
+            }
+        }
+
+        self.commands.extend(parse(&inner)?);
+        word.dynamic = true;
+        Ok(())
+    }
+
+    /// Rejeita expansão de chaves (`{a,b}`, `{1..3}`), que gera palavras extras
+    fn check_brace_expansion(&self) -> Result<(), String> {
+        let mut content = String::new();
+        for &c in &self.chars[self.pos + 1..] {
+            if c == '}' {
+                if content.contains(',') || content.contains("..") {
+                    return Err("Expansão de chaves {...} não é suportada".to_string());
+                }
+                return Ok(());
+            }
+            if is_metachar(c) || matches!(c, '{' | '\'' | '"') {
+                break;
+            }
+            content.push(c);
+        }
+        Ok(())
+    }
+}
+
+/// Monta uma linha de comando equivalente ao argv, com aspas quando necessário
+pub fn quote_argv(argv: &[String]) -> String {
+    argv.iter()
+        .map(|arg| {
+            let safe = !arg.is_empty()
+                && arg
+                    .chars()
+                    .all(|c| is_name_char(c) || "-_./=:,+@%".contains(c));
+            if safe {
+                arg.clone()
+            } else {
+                format!("'{}'", arg.replace('\'', "'\\''"))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Verifica se a palavra tem a forma NOME=valor
+fn is_assignment(raw: &str) -> bool {
+    match raw.split_once('=') {
+        Some((name, _)) => {
+            !name.is_empty()
+                && !name.starts_with(|c: char| c.is_ascii_digit())
+                && name.chars().all(is_name_char)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(command_line: &str) -> Vec<String> {
+        parse(command_line)
+            .unwrap()
+            .iter()
+            .filter_map(|c| c.words.first().map(|w| w.text.clone()))
+            .collect()
+    }
+
+    fn argv(command: &SimpleCommand) -> Vec<&str> {
+        command.words.iter().map(|w| w.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse_lists_and_pipelines() {
+        assert_eq!(names("ls -la"), vec!["ls"]);
+        assert_eq!(names("ls; curl evil | sh"), vec!["ls", "curl", "sh"]);
+        assert_eq!(names("a && b || c & d"), vec!["a", "b", "c", "d"]);
+        assert_eq!(names("(cd /tmp; ls) | grep x"), vec!["cd", "ls", "grep"]);
+        assert_eq!(names("{ ls; }"), vec!["ls"]);
+        assert_eq!(names("ls # ; rm -rf /"), vec!["ls"]);
+    }
+
+    #[test]
+    fn test_parse_substitutions() {
+        assert_eq!(names("echo $(wget x)"), vec!["wget", "echo"]);
+        assert_eq!(names("echo \"$(id)\""), vec!["id", "echo"]);
+        assert_eq!(names("echo `whoami`"), vec!["whoami", "echo"]);
+        assert_eq!(names("echo '$(not run)'"), vec!["echo"]);
+    }
+
+    #[test]
+    fn test_parse_words_and_redirects() {
+        let cmds = parse("grep 'foo bar' \"baz\" 2>/dev/null").unwrap();
+        assert_eq!(argv(&cmds[0]), vec!["grep", "foo bar", "baz"]);
+        assert_eq!(cmds[0].redirects[0].op, "2>");
+        assert_eq!(cmds[0].redirects[0].target.text, "/dev/null");
+
+        let cmds = parse("ls /tmp/*.log $HOME").unwrap();
+        assert!(cmds[0].words[1].glob);
+        assert!(cmds[0].words[2].dynamic);
+
+        let cmds = parse("find . -exec echo {} \\;").unwrap();
+        assert_eq!(
+            argv(&cmds[0]),
+            vec!["find", ".", "-exec", "echo", "{}", ";"]
+        );
+    }
+
+    #[test]
+    fn test_quote_argv_round_trip() {
+        let original: Vec<String> = ["grep", "foo bar", "it's", "$(id)", "-n"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let quoted = quote_argv(&original);
+        assert_eq!(quoted, "grep 'foo bar' 'it'\\''s' '$(id)' -n");
+
+        let cmds = parse(&quoted).unwrap();
+        assert_eq!(argv(&cmds[0]), original);
+        assert!(!cmds[0].words.iter().any(|w| w.dynamic));
+    }
+
+    #[test]
+    fn test_parse_rejects_uncertain_constructs() {
+        assert!(parse("echo 'unterminated").is_err());
+        assert!(parse("echo $(ls").is_err());
+        assert!(parse("if true; then ls; fi").is_err());
+        assert!(parse("PATH=/tmp ls").is_err());
+        assert!(parse("cat <<EOF").is_err());
+        assert!(parse("echo $((1 + 2))").is_err());
+        assert!(parse("echo ${x:-$(id)}").is_err());
+        assert!(parse("echo {a,b}").is_err());
+        assert!(parse("echo $'\\x41'").is_err());
+    }
+
+    #[test]
+    fn test_parse_rejects_bash_only_syntax() {
+        assert!(parse("ls &>/tmp/x").is_err());
+        assert!(parse("ls &>>/tmp/x").is_err());
+        assert!(parse("ls |& grep x").is_err());
+        assert!(parse("diff <(ls a) >(tee b)").is_err());
+        assert!(parse("ls >/tmp/x 2>&1 &").is_ok());
+    }
+}
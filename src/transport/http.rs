@@ -0,0 +1,337 @@
+//! Transporte streamable HTTP do MCP (`linux-mcp serve --transport http`)
+//!
+//! Toda requisição precisa de um token (`Authorization: Bearer`) ou de um
+//! certificado de cliente (mTLS) listado em `[http]`. As credenciais são
+//! lidas do config vigente a cada requisição, então revogar um token não exige
+//! reiniciar o servidor; endereço e certificados só mudam ao reiniciar.
+//!
+//! A credencial define o perfil da sessão MCP, e uma sessão só aceita
+//! requisições da mesma credencial que a abriu.
+
+use super::Access;
+use crate::config::HttpConfig;
+use crate::reload::ConfigHandle;
+use crate::LinuxMcpServer;
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use http::{header, Request, Response, StatusCode};
+use http_body_util::combinators::BoxBody;
+use http_body_util::{BodyExt, Full};
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use rmcp::transport::streamable_http_server::session::local::LocalSessionManager;
+use rmcp::transport::{StreamableHttpServerConfig, StreamableHttpService};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use tokio_rustls::rustls::server::WebPkiClientVerifier;
+use tokio_rustls::rustls::{RootCertStore, ServerConfig};
+use tokio_rustls::TlsAcceptor;
+
+/// Cabeçalho com o id da sessão MCP
+const SESSION_HEADER: &str = "mcp-session-id";
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// Estado compartilhado pelas conexões
+struct State {
+    config: Arc<ConfigHandle>,
+    mcp: StreamableHttpService<LinuxMcpServer, LocalSessionManager>,
+    /// Credencial que abriu cada sessão
+    sessions: Mutex<HashMap<String, String>>,
+}
+
+/// SHA-256 em hexadecimal, como nos campos `sha256`/`cert_sha256` do config
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Identifica a credencial de uma requisição
+///
+/// O certificado de cliente (já validado pela CA no handshake) tem
+/// precedência sobre o token. Só os hashes são comparados, então o tempo da
+/// comparação não revela nada sobre os tokens configurados.
+fn authenticate(
+    http: &HttpConfig,
+    authorization: Option<&str>,
+    client_cert: Option<&[u8]>,
+) -> Option<Access> {
+    if let Some(cert) = client_cert {
+        let digest = sha256_hex(cert);
+        if let Some(client) = http
+            .clients
+            .iter()
+            .find(|c| c.cert_sha256.eq_ignore_ascii_case(&digest))
+        {
+            return Some(Access {
+                principal: Some(format!("cert:{}", client.name)),
+                profile: client.profile.clone(),
+            });
+        }
+    }
+
+    let (scheme, token) = authorization?.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("bearer") {
+        return None;
+    }
+    let digest = sha256_hex(token.trim().as_bytes());
+    let token = http
+        .tokens
+        .iter()
+        .find(|t| t.sha256.eq_ignore_ascii_case(&digest))?;
+    Some(Access {
+        principal: Some(format!("token:{}", token.name)),
+        profile: token.profile.clone(),
+    })
+}
+
+fn text_response(status: StatusCode, text: &'static str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "text/plain")
+        .body(Full::new(Bytes::from(text)).boxed())
+        .expect("valid response")
+}
+
+async fn handle(
+    mut request: Request<Incoming>,
+    state: Arc<State>,
+    client_cert: Option<Arc<Vec<u8>>>,
+) -> Result<Response<Body>, Infallible> {
+    let config = state.config.load();
+    if request.uri().path() != config.http.path {
+        return Ok(text_response(StatusCode::NOT_FOUND, "Not Found"));
+    }
+
+    let authorization = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok());
+    let Some(access) = authenticate(
+        &config.http,
+        authorization,
+        client_cert.as_deref().map(Vec::as_slice),
+    ) else {
+        let mut response = text_response(StatusCode::UNAUTHORIZED, "Unauthorized");
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            header::HeaderValue::from_static("Bearer"),
+        );
+        return Ok(response);
+    };
+    let principal = access.principal.clone().unwrap_or_default();
+
+    // Uma sessão só aceita a credencial que a abriu
+    let session = request
+        .headers()
+        .get(SESSION_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    if let Some(session) = &session {
+        let sessions = state.sessions.lock().unwrap();
+        if sessions
+            .get(session)
+            .is_some_and(|owner| *owner != principal)
+        {
+            return Ok(text_response(
+                StatusCode::FORBIDDEN,
+                "Forbidden: session belongs to another credential",
+            ));
+        }
+    }
+
+    // Chega ao `initialize` do servidor junto com as partes da requisição
+    request.extensions_mut().insert(access);
+    let deleting = request.method() == http::Method::DELETE;
+    let response = state.mcp.handle(request).await;
+
+    let mut sessions = state.sessions.lock().unwrap();
+    match (session, response.headers().get(SESSION_HEADER)) {
+        (Some(session), _) if deleting && response.status().is_success() => {
+            sessions.remove(&session);
+        }
+        (None, Some(created)) => {
+            if let Ok(created) = created.to_str() {
+                sessions.insert(created.to_string(), principal);
+            }
+        }
+        _ => {}
+    }
+    Ok(response)
+}
+
+/// Lê os certificados de um arquivo PEM
+fn read_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let path = crate::config::get_config_dir()?.join(path);
+    CertificateDer::pem_file_iter(&path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("Failed to read certificates from {}", path.display()))
+}
+
+/// Configuração TLS (e mTLS, com `client_ca`), se `tls_cert` estiver definido
+fn tls_acceptor(http: &HttpConfig) -> Result<Option<TlsAcceptor>> {
+    let (Some(cert), Some(key)) = (&http.tls_cert, &http.tls_key) else {
+        return Ok(None);
+    };
+    let certs = read_certs(cert)?;
+    let key_path = crate::config::get_config_dir()?.join(key);
+    let key = PrivateKeyDer::from_pem_file(&key_path)
+        .with_context(|| format!("Failed to read private key from {}", key_path.display()))?;
+
+    let builder = ServerConfig::builder();
+    let builder = match &http.client_ca {
+        Some(ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in read_certs(ca)? {
+                roots.add(cert).context("Invalid client CA certificate")?;
+            }
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots));
+            // Sem tokens, o certificado de cliente é obrigatório já no handshake
+            let verifier = if http.tokens.is_empty() {
+                verifier.build()
+            } else {
+                verifier.allow_unauthenticated().build()
+            };
+            builder.with_client_cert_verifier(verifier.context("Invalid client CA")?)
+        }
+        None => builder.with_no_client_auth(),
+    };
+    let mut tls = builder
+        .with_single_cert(certs, key)
+        .context("Invalid TLS certificate or key")?;
+    tls.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Some(TlsAcceptor::from(Arc::new(tls))))
+}
+
+async fn serve_connection<I>(io: I, state: Arc<State>, client_cert: Option<Arc<Vec<u8>>>)
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let service = hyper::service::service_fn(move |request| {
+        handle(request, state.clone(), client_cert.clone())
+    });
+    // Erros de conexão (cliente desconectou no meio) não afetam as sessões
+    let _ = auto::Builder::new(TokioExecutor::new())
+        .serve_connection(TokioIo::new(io), service)
+        .await;
+}
+
+/// Atende clientes MCP via HTTP até o processo terminar
+///
+/// `bind` substitui `[http] bind`. Cada sessão MCP recebe sua própria
+/// instância de `server` (ver [`LinuxMcpServer::session`]).
+pub async fn serve(
+    server: LinuxMcpServer,
+    config: Arc<ConfigHandle>,
+    bind: Option<String>,
+) -> Result<()> {
+    let http = config.load().http.clone();
+    if http.tokens.is_empty() && http.client_ca.is_none() {
+        anyhow::bail!(
+            "The HTTP transport requires [[http.tokens]] or [http] client_ca in the config"
+        );
+    }
+    let bind = bind.unwrap_or(http.bind.clone());
+    let addr: SocketAddr = bind
+        .parse()
+        .with_context(|| format!("Invalid HTTP bind address '{}'", bind))?;
+    let tls = tls_acceptor(&http)?;
+    if tls.is_none() && !addr.ip().is_loopback() {
+        eprintln!(
+            "warning: serving MCP over plain HTTP on {}; tokens travel unencrypted (set [http] tls_cert and tls_key)",
+            addr
+        );
+    }
+
+    let mcp = StreamableHttpService::new(
+        move || Ok(server.session()),
+        Arc::new(LocalSessionManager::default()),
+        StreamableHttpServerConfig::default(),
+    );
+    let state = Arc::new(State {
+        config,
+        mcp,
+        sessions: Mutex::new(HashMap::new()),
+    });
+
+    let listener = TcpListener::bind(addr)
+        .await
+        .with_context(|| format!("Failed to listen on {}", addr))?;
+    eprintln!(
+        "linux-mcp: listening on {}://{}{}",
+        if tls.is_some() { "https" } else { "http" },
+        addr,
+        http.path
+    );
+    loop {
+        let (stream, remote) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("warning: failed to accept HTTP connection: {}", e);
+                continue;
+            }
+        };
+        let state = state.clone();
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let Some(tls) = tls else {
+                return serve_connection(stream, state, None).await;
+            };
+            match tls.accept(stream).await {
+                Ok(stream) => {
+                    let cert = stream
+                        .get_ref()
+                        .1
+                        .peer_certificates()
+                        .and_then(|certs| certs.first())
+                        .map(|cert| Arc::new(cert.to_vec()));
+                    serve_connection(stream, state, cert).await
+                }
+                Err(e) => eprintln!("warning: TLS handshake with {} failed: {}", remote, e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{HttpClient, HttpToken};
+
+    #[test]
+    fn test_authenticate() {
+        let http = HttpConfig {
+            tokens: vec![HttpToken {
+                name: "ci".to_string(),
+                sha256: sha256_hex(b"s3cr3t").to_uppercase(),
+                profile: Some("readonly".to_string()),
+            }],
+            clients: vec![HttpClient {
+                name: "laptop".to_string(),
+                cert_sha256: sha256_hex(b"cert-der"),
+                profile: None,
+            }],
+            ..Default::default()
+        };
+
+        let access = authenticate(&http, Some("Bearer s3cr3t"), None).unwrap();
+        assert_eq!(access.principal.as_deref(), Some("token:ci"));
+        assert_eq!(access.profile.as_deref(), Some("readonly"));
+        assert!(authenticate(&http, Some("bearer  s3cr3t "), None).is_some());
+        assert!(authenticate(&http, Some("Bearer wrong"), None).is_none());
+        assert!(authenticate(&http, Some("Basic s3cr3t"), None).is_none());
+        assert!(authenticate(&http, None, None).is_none());
+
+        let access = authenticate(&http, Some("Bearer s3cr3t"), Some(b"cert-der")).unwrap();
+        assert_eq!(access.principal.as_deref(), Some("cert:laptop"));
+        assert_eq!(access.profile, None);
+        assert!(authenticate(&http, None, Some(b"other-cert")).is_none());
+    }
+}
@@ -0,0 +1,138 @@
+//! Transportes além do stdio e a identidade de quem abriu cada conexão
+//!
+//! Cada conexão recebe um perfil de `[profiles]` que restringe o que ela pode
+//! fazer, escolhido pela credencial do transporte, pelo `--profile` do
+//! servidor ou pelo nome do cliente MCP, nessa ordem.
+
+use crate::config::Config;
+
+pub mod http;
+pub mod unix;
+
+/// Quem abriu a conexão e com qual perfil
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Access {
+    /// Credencial que autenticou a conexão (ex: `token:ci`, `cert:ops-laptop`,
+    /// `unix:uid=1000`);
+    /// ausente no stdio
+    pub principal: Option<String>,
+    /// Perfil de `[profiles]` aplicado (ausente: sem restrição de perfil)
+    pub profile: Option<String>,
+}
+
+impl Access {
+    /// Acesso de uma conexão no `initialize`
+    ///
+    /// O perfil da credencial tem precedência; sem ele, vale o do servidor
+    /// (`--profile`) e, por último, o associado ao nome que o cliente informou.
+    /// Como o nome não é verificado, um cliente pode escolher outro perfil de
+    /// `clients`; use credenciais quando o perfil precisa ser imposto.
+    pub fn resolve(
+        credential: Option<Access>,
+        server_profile: Option<&str>,
+        client: &str,
+        config: &Config,
+    ) -> Access {
+        let mut access = credential.unwrap_or_default();
+        if access.profile.is_none() {
+            access.profile = server_profile
+                .map(str::to_string)
+                .or_else(|| config.client_profile(client));
+        }
+        access
+    }
+
+    /// Se o perfil da conexão permite a tool
+    ///
+    /// Um perfil que deixou de existir no config (ex: após uma recarga) não
+    /// permite nada.
+    pub fn allows_tool(&self, config: &Config, tool: &str) -> bool {
+        let Some(name) = &self.profile else {
+            return true;
+        };
+        config.profiles.get(name).is_some_and(|profile| {
+            profile
+                .tools
+                .as_ref()
+                .is_none_or(|tools| tools.iter().any(|t| t == tool))
+        })
+    }
+
+    /// Se o perfil da conexão permite o resource (o da tool que ele espelha)
+    pub fn allows_resource(&self, config: &Config, uri: &str) -> bool {
+        crate::resources::mirrored_tool(uri).is_none_or(|tool| self.allows_tool(config, tool))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Profile;
+
+    #[test]
+    fn test_allows_tool() {
+        let mut config = Config::default();
+        config.profiles.insert(
+            "readonly".to_string(),
+            Profile {
+                tools: Some(vec!["get_system_info".to_string()]),
+                ..Default::default()
+            },
+        );
+        config
+            .profiles
+            .insert("ops".to_string(), Profile::default());
+        let access = |profile: Option<&str>| Access {
+            principal: Some("token:t".to_string()),
+            profile: profile.map(str::to_string),
+        };
+
+        assert!(access(None).allows_tool(&config, "execute_command"));
+        assert!(access(Some("ops")).allows_tool(&config, "execute_command"));
+        assert!(access(Some("readonly")).allows_tool(&config, "get_system_info"));
+        assert!(!access(Some("readonly")).allows_tool(&config, "execute_command"));
+        assert!(!access(Some("removed")).allows_tool(&config, "get_system_info"));
+
+        let readonly = access(Some("readonly"));
+        assert!(readonly.allows_resource(&config, "linux://system/status"));
+        assert!(readonly.allows_resource(&config, "linux://mcp/capabilities"));
+        assert!(!readonly.allows_resource(&config, "linux://audit/recent"));
+        assert!(!readonly.allows_resource(&config, "linux://logs/auth"));
+        assert!(!readonly.allows_resource(&config, "linux://jobs"));
+        assert!(access(Some("ops")).allows_resource(&config, "linux://jobs"));
+    }
+
+    #[test]
+    fn test_resolve() {
+        let mut config = Config::default();
+        config.profiles.insert(
+            "ops".to_string(),
+            Profile {
+                clients: vec!["ops-*".to_string()],
+                ..Default::default()
+            },
+        );
+        let profile = |credential: Option<Access>, server: Option<&str>, client: &str| {
+            Access::resolve(credential, server, client, &config).profile
+        };
+        let token = |profile: Option<&str>| Access {
+            principal: Some("token:ci".to_string()),
+            profile: profile.map(str::to_string),
+        };
+
+        assert_eq!(profile(None, None, "claude"), None);
+        assert_eq!(profile(None, None, "ops-agent").as_deref(), Some("ops"));
+        assert_eq!(
+            profile(None, Some("readonly"), "ops-agent").as_deref(),
+            Some("readonly")
+        );
+        assert_eq!(
+            profile(Some(token(Some("ci"))), Some("readonly"), "ops-agent").as_deref(),
+            Some("ci")
+        );
+        assert_eq!(
+            profile(Some(token(None)), None, "ops-agent").as_deref(),
+            Some("ops")
+        );
+    }
+}
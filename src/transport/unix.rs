@@ -0,0 +1,185 @@
+//! Transporte MCP por socket Unix (`linux-mcp serve --socket`)
+//!
+//! Um único servidor, com o cache do `sysinfo` já aquecido, atende vários
+//! agentes locais sem abrir porta de rede. Cada conexão é identificada pelas
+//! credenciais do processo cliente (SO_PEERCRED) e recebe o perfil da entrada
+//! de `[[socket.peers]]` correspondente; processos de outros usuários sem
+//! entrada são recusados.
+
+use super::Access;
+use crate::audit;
+use crate::config::SocketConfig;
+use crate::reload::ConfigHandle;
+use crate::LinuxMcpServer;
+use anyhow::{Context, Result};
+use rmcp::service::ServerInitializeError;
+use rmcp::ServiceExt;
+use std::fs;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::net::{UnixListener, UnixStream};
+
+/// Credenciais do processo do outro lado do socket
+#[derive(Clone, Copy, Debug)]
+struct Peer {
+    uid: u32,
+    gid: u32,
+    pid: Option<i32>,
+}
+
+/// Socket padrão: `$XDG_RUNTIME_DIR/linux-mcp.sock`
+pub fn default_path() -> Result<PathBuf> {
+    let dir = dirs::runtime_dir().context("XDG_RUNTIME_DIR is not set; pass a path to --socket")?;
+    Ok(dir.join("linux-mcp.sock"))
+}
+
+/// Acesso concedido ao processo (`None`: conexão recusada)
+///
+/// Vale a primeira entrada de `[[socket.peers]]` que descreve o processo; sem
+/// nenhuma, só o usuário do próprio servidor é aceito, sem perfil.
+fn authorize(socket: &SocketConfig, peer: Peer, server_uid: u32) -> Option<Access> {
+    let entry = socket.peers.iter().find(|entry| {
+        entry.uid.is_none_or(|uid| uid == peer.uid) && entry.gid.is_none_or(|gid| gid == peer.gid)
+    });
+    match entry {
+        Some(entry) => Some(Access {
+            principal: Some(format!("unix:{}", entry.name)),
+            profile: entry.profile.clone(),
+        }),
+        None if peer.uid == server_uid => Some(Access {
+            principal: Some(format!("unix:uid={}", peer.uid)),
+            profile: None,
+        }),
+        None => None,
+    }
+}
+
+/// Remove o socket de um servidor que já terminou
+///
+/// Recusa substituir um socket com um servidor ativo ou um arquivo que não
+/// seja socket.
+async fn remove_stale(path: &Path) -> Result<()> {
+    let Ok(metadata) = fs::symlink_metadata(path) else {
+        return Ok(());
+    };
+    if !metadata.file_type().is_socket() {
+        anyhow::bail!("{} exists and is not a socket", path.display());
+    }
+    if UnixStream::connect(path).await.is_ok() {
+        anyhow::bail!("Another server is already listening on {}", path.display());
+    }
+    fs::remove_file(path)
+        .with_context(|| format!("Failed to remove stale socket {}", path.display()))
+}
+
+/// Atende clientes MCP pelo socket até o processo terminar
+///
+/// Cada conexão recebe sua própria sessão de `server` (ver
+/// [`LinuxMcpServer::session`]); as entradas de `[[socket.peers]]` são lidas do
+/// config vigente a cada conexão.
+pub async fn serve(server: LinuxMcpServer, config: Arc<ConfigHandle>, path: PathBuf) -> Result<()> {
+    let mode = config.load().socket.mode()?;
+    remove_stale(&path).await?;
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    fs::set_permissions(&path, fs::Permissions::from_mode(mode))
+        .with_context(|| format!("Failed to set permissions of {}", path.display()))?;
+    eprintln!("linux-mcp: listening on unix:{}", path.display());
+
+    let server_uid = nix::unistd::getuid().as_raw();
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(e) => {
+                eprintln!("warning: failed to accept socket connection: {}", e);
+                continue;
+            }
+        };
+        let peer = match stream.peer_cred() {
+            Ok(cred) => Peer {
+                uid: cred.uid(),
+                gid: cred.gid(),
+                pid: cred.pid(),
+            },
+            Err(e) => {
+                eprintln!("warning: failed to read socket peer credentials: {}", e);
+                continue;
+            }
+        };
+
+        let current = config.load();
+        let Some(access) = authorize(&current.socket, peer, server_uid) else {
+            let event = audit::Event {
+                status: "BLOCKED".to_string(),
+                command: "connect".to_string(),
+                rule: Some("socket.peers".to_string()),
+                details: Some(format!(
+                    "Socket peer uid={} gid={} pid={} is not allowed",
+                    peer.uid,
+                    peer.gid,
+                    peer.pid.map_or("?".to_string(), |pid| pid.to_string())
+                )),
+                ..Default::default()
+            };
+            if let Err(e) = audit::log(&current, event) {
+                eprintln!("warning: failed to write audit record: {:#}", e);
+            }
+            continue;
+        };
+
+        let session = server.session().with_credential(access);
+        tokio::spawn(async move {
+            match session.serve(stream.into_split()).await {
+                Ok(running) => {
+                    let _ = running.waiting().await;
+                }
+                // Cliente que desconectou antes do `initialize` (ex: verificação de saúde)
+                Err(ServerInitializeError::ConnectionClosed(_)) => {}
+                Err(e) => eprintln!("warning: MCP session over the socket failed: {}", e),
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SocketPeer;
+
+    #[test]
+    fn test_authorize() {
+        let socket = SocketConfig {
+            peers: vec![
+                SocketPeer {
+                    name: "ci".to_string(),
+                    uid: Some(1001),
+                    gid: None,
+                    profile: Some("readonly".to_string()),
+                },
+                SocketPeer {
+                    name: "ops".to_string(),
+                    uid: None,
+                    gid: Some(27),
+                    profile: None,
+                },
+            ],
+            ..Default::default()
+        };
+        let peer = |uid, gid| Peer {
+            uid,
+            gid,
+            pid: Some(4242),
+        };
+
+        let access = authorize(&socket, peer(1001, 27), 1000).unwrap();
+        assert_eq!(access.principal.as_deref(), Some("unix:ci"));
+        assert_eq!(access.profile.as_deref(), Some("readonly"));
+        let access = authorize(&socket, peer(1002, 27), 1000).unwrap();
+        assert_eq!(access.principal.as_deref(), Some("unix:ops"));
+        let access = authorize(&socket, peer(1000, 1000), 1000).unwrap();
+        assert_eq!(access.principal.as_deref(), Some("unix:uid=1000"));
+        assert_eq!(access.profile, None);
+        assert!(authorize(&socket, peer(1002, 100), 1000).is_none());
+    }
+}